@@ -0,0 +1,537 @@
+// Records what a file offers to the code that uses it, so that a new version can be checked against the last one
+// before it's released. This is the schema checker's counterpart for code rather than data. There's no `pub` in the
+// language, so everything a file declares is part of its API.
+//
+// An API is saved as JSON, with one item for each declaration and each field, variant, method, getter and setter in
+// it:
+//
+// {"items":[
+// {"kind":"fn","path":"area","signature":"fn area(radius: f32) -> f32"}
+// ]}
+
+use crate::parsing::layout::ByteOrder;
+use crate::parsing::{NLArgument, NLEncapsulationBlock, NLFile, NLFunction, NLImplementor, NLType};
+use nom::{
+    character::complete::{char, multispace0},
+    combinator::{all_consuming, recognize},
+    error::{convert_error, VerboseError, VerboseErrorKind},
+    multi::separated_list0,
+    sequence::{delimited, tuple},
+    Err as NomErr, IResult,
+};
+use std::fmt::Formatter;
+
+// All tests are kept in their own module.
+#[cfg(test)]
+mod tests;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ApiItem {
+    kind: String,
+    path: String,
+    signature: String,
+}
+
+impl ApiItem {
+    fn new(kind: &str, path: String, signature: String) -> ApiItem {
+        ApiItem {
+            kind: kind.to_string(),
+            path,
+            signature,
+        }
+    }
+
+    /// What was declared, like `struct`, `field` or `fn`. Trait methods, getters and setters without a body have a
+    /// kind starting with `required`, since whatever implements the trait has to give them one.
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+    /// The name of the item, with the names of what it's in before it, like `Circle::area`.
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+    /// How the item is declared, without its body.
+    pub fn get_signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn is_required(&self) -> bool {
+        self.kind.starts_with("required ")
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ApiError {
+    message: String,
+}
+
+impl std::error::Error for ApiError {}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Everything a file declares, in the order it was declared.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Api {
+    items: Vec<ApiItem>,
+}
+
+impl Api {
+    pub fn from_file(file: &NLFile) -> Api {
+        let mut items = vec![];
+
+        for nl_struct in file.get_structs() {
+            let name = nl_struct.get_name();
+            items.push(ApiItem::new(
+                "struct",
+                name.to_string(),
+                format!("struct {}", name),
+            ));
+            for field in nl_struct.get_variables() {
+                items.push(ApiItem::new(
+                    "field",
+                    format!("{}::{}", name, field.get_name()),
+                    format!("{}: {}", field.get_name(), type_text(field.get_type())),
+                ));
+            }
+            for implementation in nl_struct.get_implementations() {
+                add_implementors(name, implementation.get_implementors(), false, &mut items);
+            }
+        }
+
+        for nl_enum in file.get_enums() {
+            let kind = if nl_enum.is_error() { "error" } else { "enum" };
+            let name = nl_enum.get_name();
+            items.push(ApiItem::new(
+                kind,
+                name.to_string(),
+                format!("{} {}", kind, name),
+            ));
+            for variant in nl_enum.get_variants() {
+                let payload: Vec<String> =
+                    variant.get_payload().into_iter().map(type_text).collect();
+                let signature = if payload.is_empty() {
+                    variant.get_name().to_string()
+                } else {
+                    format!("{}({})", variant.get_name(), payload.join(", "))
+                };
+                items.push(ApiItem::new(
+                    "variant",
+                    format!("{}::{}", name, variant.get_name()),
+                    signature,
+                ));
+            }
+        }
+
+        for nl_trait in file.get_traits() {
+            let name = nl_trait.get_name();
+            items.push(ApiItem::new(
+                "trait",
+                name.to_string(),
+                format!("trait {}", name),
+            ));
+            add_implementors(name, nl_trait.get_implementors(), true, &mut items);
+        }
+
+        for function in file.get_functions() {
+            items.push(ApiItem::new(
+                "fn",
+                function.get_name().to_string(),
+                function_signature("fn", function),
+            ));
+        }
+
+        // What an implementation of a trait has in it is already part of the trait.
+        for implementation in file.get_implementations() {
+            match implementation.get_target() {
+                Some(target) => {
+                    let path = format!("{} for {}", implementation.get_name(), target);
+                    items.push(ApiItem::new("impl", path.clone(), format!("impl {}", path)));
+                }
+                None => add_implementors(
+                    implementation.get_name(),
+                    implementation.get_implementors(),
+                    false,
+                    &mut items,
+                ),
+            }
+        }
+
+        Api { items }
+    }
+
+    pub fn get_items(&self) -> &[ApiItem] {
+        &self.items
+    }
+
+    pub fn to_json(&self) -> String {
+        let items: Vec<String> = self
+            .items
+            .iter()
+            .map(|item| {
+                format!(
+                    "{{\"kind\":{},\"path\":{},\"signature\":{}}}",
+                    json_string(&item.kind),
+                    json_string(&item.path),
+                    json_string(&item.signature)
+                )
+            })
+            .collect();
+
+        format!("{{\"items\":[\n{}\n]}}\n", items.join(",\n"))
+    }
+
+    /// Reads an API written by `to_json`. Keys other than `kind`, `path` and `signature` are ignored.
+    pub fn from_json(json: &str) -> Result<Api, ApiError> {
+        match all_consuming(read_api)(json) {
+            Ok((_, items)) => Ok(Api { items }),
+            Err(NomErr::Error(e)) | Err(NomErr::Failure(e)) => Err(ApiError {
+                message: convert_error(json, e),
+            }),
+            Err(NomErr::Incomplete(_)) => Err(ApiError {
+                message: "the API ended early".to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ApiChange<'a> {
+    Removed(&'a ApiItem),
+    Added(&'a ApiItem),
+    Changed { old: &'a ApiItem, new: &'a ApiItem },
+}
+
+impl<'a> ApiChange<'a> {
+    /// Whether code written against the old API might not build against the new one. Removing or changing anything
+    /// is breaking. So is adding a field, since struct literals have to give every field, adding a variant, since
+    /// matches have to cover every variant, and adding something required to a trait.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ApiChange::Removed(_) => true,
+            ApiChange::Added(item) => {
+                item.kind == "field" || item.kind == "variant" || item.is_required()
+            }
+            // A trait giving a body to something that didn't have one only changes what implementations have to do.
+            ApiChange::Changed { old, new } => {
+                old.signature != new.signature || !old.is_required() || new.is_required()
+            }
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for ApiChange<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let kind = if self.is_breaking() {
+            "breaking"
+        } else {
+            "compatible"
+        };
+        match self {
+            ApiChange::Removed(item) => {
+                write!(f, "{}: {} `{}` was removed", kind, item.kind, item.path)
+            }
+            ApiChange::Added(item) => {
+                write!(f, "{}: {} `{}` was added", kind, item.kind, item.path)
+            }
+            ApiChange::Changed { old, new } if old.signature != new.signature => write!(
+                f,
+                "{}: {} `{}` changed from `{}` to `{}`",
+                kind, new.kind, new.path, old.signature, new.signature
+            ),
+            ApiChange::Changed { old, new } if old.is_required() => {
+                write!(f, "{}: {} `{}` was given a body", kind, new.kind, new.path)
+            }
+            ApiChange::Changed { new, .. } => write!(
+                f,
+                "{}: {} `{}` no longer has a body",
+                kind, new.kind, new.path
+            ),
+        }
+    }
+}
+
+/// Every difference between the two APIs. Removed and changed items come in the order of `old`, and added ones after
+/// them in the order of `new`.
+pub fn compare<'a>(old: &'a Api, new: &'a Api) -> Vec<ApiChange<'a>> {
+    let mut changes = vec![];
+
+    for old_item in &old.items {
+        match new
+            .items
+            .iter()
+            .find(|new_item| new_item.path == old_item.path)
+        {
+            Some(new_item)
+                if new_item.kind != old_item.kind || new_item.signature != old_item.signature =>
+            {
+                changes.push(ApiChange::Changed {
+                    old: old_item,
+                    new: new_item,
+                })
+            }
+            Some(_) => {}
+            None => changes.push(ApiChange::Removed(old_item)),
+        }
+    }
+    for new_item in &new.items {
+        if !old
+            .items
+            .iter()
+            .any(|old_item| old_item.path == new_item.path)
+        {
+            changes.push(ApiChange::Added(new_item));
+        }
+    }
+
+    changes
+}
+
+// How a type is written in NL.
+pub(crate) fn type_text(nl_type: &NLType) -> String {
+    match nl_type {
+        NLType::None => "()".to_string(),
+        NLType::Boolean => "bool".to_string(),
+        NLType::Char => "char".to_string(),
+        NLType::I8 => "i8".to_string(),
+        NLType::I16 => "i16".to_string(),
+        NLType::I32 => "i32".to_string(),
+        NLType::I64 => "i64".to_string(),
+        NLType::U8 => "u8".to_string(),
+        NLType::U16 => "u16".to_string(),
+        NLType::U32 => "u32".to_string(),
+        NLType::U64 => "u64".to_string(),
+        NLType::F32 => "f32".to_string(),
+        NLType::F64 => "f64".to_string(),
+        NLType::OwnedString => "String".to_string(),
+        NLType::BorrowedString => "str".to_string(),
+        NLType::Bytes => "bytes".to_string(),
+        NLType::Tuple(items) if items.len() == 1 => format!("({},)", type_text(&items[0])),
+        NLType::Tuple(items) => {
+            let items: Vec<String> = items.iter().map(type_text).collect();
+            format!("({})", items.join(", "))
+        }
+        NLType::List(item) => format!("List<{}>", type_text(item)),
+        NLType::Map(key, value) => format!("Map<{}, {}>", type_text(key), type_text(value)),
+        NLType::OwnedStruct(name) | NLType::Enum(name) => name.to_string(),
+        NLType::ReferencedStruct(name) => format!("&{}", name),
+        NLType::MutableReferencedStruct(name) => format!("&mut {}", name),
+        NLType::OwnedTrait(name) => format!("dyn {}", name),
+        NLType::ReferencedTrait(name) => format!("&dyn {}", name),
+        NLType::MutableReferencedTrait(name) => format!("&mut dyn {}", name),
+        NLType::SelfReference => "&self".to_string(),
+        NLType::MutableSelfReference => "&mut self".to_string(),
+        NLType::Pointer(pointee) => format!("*const {}", type_text(pointee)),
+        NLType::MutablePointer(pointee) => format!("*mut {}", type_text(pointee)),
+        NLType::Shared(value) => format!("rc {}", type_text(value)),
+        NLType::Weak(value) => format!("weak {}", type_text(value)),
+        NLType::Endian(ByteOrder::Little, number) => format!("{}_le", type_text(number)),
+        NLType::Endian(ByteOrder::Big, number) => format!("{}_be", type_text(number)),
+    }
+}
+
+fn arguments_text(arguments: &[NLArgument]) -> String {
+    let arguments: Vec<String> = arguments
+        .iter()
+        .map(|argument| match argument.get_type() {
+            NLType::SelfReference | NLType::MutableSelfReference => type_text(argument.get_type()),
+            nl_type => format!("{}: {}", argument.get_name(), type_text(nl_type)),
+        })
+        .collect();
+
+    arguments.join(", ")
+}
+
+fn function_signature(keyword: &str, function: &NLFunction) -> String {
+    let mut signature = String::new();
+    if function.is_const() {
+        signature.push_str("const ");
+    }
+    if function.is_unsafe() {
+        signature.push_str("unsafe ");
+    }
+    signature.push_str(&format!(
+        "{} {}({})",
+        keyword,
+        function.get_name(),
+        arguments_text(function.get_arguments())
+    ));
+    if *function.get_return_type() != NLType::None {
+        signature.push_str(&format!(" -> {}", type_text(function.get_return_type())));
+    }
+
+    signature
+}
+
+fn add_implementors(
+    owner: &str,
+    implementors: &[NLImplementor],
+    in_trait: bool,
+    items: &mut Vec<ApiItem>,
+) {
+    for implementor in implementors {
+        let (kind, name, signature, has_body) = match implementor {
+            NLImplementor::Method(method) => (
+                "method",
+                method.get_name(),
+                function_signature("met", method),
+                method.get_block().is_some(),
+            ),
+            NLImplementor::Getter(getter) => (
+                "getter",
+                getter.get_name(),
+                format!(
+                    "get {}({}) -> {}",
+                    getter.get_name(),
+                    arguments_text(getter.get_arguments()),
+                    type_text(getter.get_type())
+                ),
+                *getter.get_block() != NLEncapsulationBlock::None,
+            ),
+            NLImplementor::Setter(setter) => (
+                "setter",
+                setter.get_name(),
+                format!(
+                    "set {}({})",
+                    setter.get_name(),
+                    arguments_text(setter.get_arguments())
+                ),
+                *setter.get_block() != NLEncapsulationBlock::None,
+            ),
+        };
+
+        let kind = if in_trait && !has_body {
+            format!("required {}", kind)
+        } else {
+            kind.to_string()
+        };
+        items.push(ApiItem::new(
+            &kind,
+            format!("{}::{}", owner, name),
+            signature,
+        ));
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+
+    json
+}
+
+type JsonResult<'a, O> = IResult<&'a str, O, VerboseError<&'a str>>;
+
+fn json_error<'a, O>(input: &'a str, message: &'static str) -> JsonResult<'a, O> {
+    Err(NomErr::Error(VerboseError {
+        errors: vec![(input, VerboseErrorKind::Context(message))],
+    }))
+}
+
+fn read_string(input: &str) -> JsonResult<String> {
+    let (input, _) = char('"')(input)?;
+    let mut text = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((&input[index + 1..], text)),
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, '"')) => '"',
+                    Some((_, '\\')) => '\\',
+                    Some((_, '/')) => '/',
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'r')) => '\r',
+                    Some((start, 'u')) => {
+                        let code = input
+                            .get(start + 1..start + 5)
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                            .and_then(std::char::from_u32);
+                        match code {
+                            Some(code) => {
+                                chars.nth(3);
+                                code
+                            }
+                            None => return json_error(&input[index..], "unknown escape"),
+                        }
+                    }
+                    _ => return json_error(&input[index..], "unknown escape"),
+                };
+                text.push(escaped);
+            }
+            c => text.push(c),
+        }
+    }
+
+    json_error(input, "string isn't closed")
+}
+
+fn separator(input: &str) -> JsonResult<&str> {
+    recognize(tuple((multispace0, char(','), multispace0)))(input)
+}
+
+fn read_item(input: &str) -> JsonResult<ApiItem> {
+    fn read_field(input: &str) -> JsonResult<(String, String)> {
+        let (input, key) = read_string(input)?;
+        let (input, _) = tuple((multispace0, char(':'), multispace0))(input)?;
+        let (input, value) = read_string(input)?;
+
+        Ok((input, (key, value)))
+    }
+
+    let start = input;
+    let (input, fields) = delimited(
+        tuple((char('{'), multispace0)),
+        separated_list0(separator, read_field),
+        tuple((multispace0, char('}'))),
+    )(input)?;
+
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    match (field("kind"), field("path"), field("signature")) {
+        (Some(kind), Some(path), Some(signature)) => Ok((
+            input,
+            ApiItem {
+                kind,
+                path,
+                signature,
+            },
+        )),
+        _ => json_error(start, "items need a kind, a path and a signature"),
+    }
+}
+
+fn read_api(input: &str) -> JsonResult<Vec<ApiItem>> {
+    let (input, _) = tuple((multispace0, char('{'), multispace0))(input)?;
+    let (input, key) = read_string(input)?;
+    if key != "items" {
+        return json_error(input, "expected `items`");
+    }
+    let (input, _) = tuple((multispace0, char(':'), multispace0))(input)?;
+    let (input, items) = delimited(
+        tuple((char('['), multispace0)),
+        separated_list0(separator, read_item),
+        tuple((multispace0, char(']'))),
+    )(input)?;
+    let (input, _) = tuple((multispace0, char('}'), multispace0))(input)?;
+
+    Ok((input, items))
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::parsing::parse_string;
+
+const OLD: &str = "struct Circle { radius: f32 }
+impl Self { met area(&self) -> f32 { 3 } }
+enum Shape { Round(f32), Square }
+trait Drawable { met draw(&self); met hide(&self) {} }
+fn scale(circle: &Circle, by: f32) -> Circle {}
+fn unused() {}";
+
+#[test]
+fn from_file() {
+    let file = parse_string(OLD, "old.nl").unwrap();
+    let api = Api::from_file(&file);
+
+    let items: Vec<(&str, &str, &str)> = api
+        .get_items()
+        .iter()
+        .map(|item| (item.get_kind(), item.get_path(), item.get_signature()))
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            ("struct", "Circle", "struct Circle"),
+            ("field", "Circle::radius", "radius: f32"),
+            ("method", "Circle::area", "met area(&self) -> f32"),
+            ("enum", "Shape", "enum Shape"),
+            ("variant", "Shape::Round", "Round(f32)"),
+            ("variant", "Shape::Square", "Square"),
+            ("trait", "Drawable", "trait Drawable"),
+            ("required method", "Drawable::draw", "met draw(&self)"),
+            ("method", "Drawable::hide", "met hide(&self)"),
+            (
+                "fn",
+                "scale",
+                "fn scale(circle: &Circle, by: f32) -> Circle"
+            ),
+            ("fn", "unused", "fn unused()"),
+        ]
+    );
+}
+
+#[test]
+fn json() {
+    let file = parse_string(OLD, "old.nl").unwrap();
+    let api = Api::from_file(&file);
+
+    assert_eq!(Api::from_json(&api.to_json()), Ok(api));
+    assert_eq!(
+        Api::from_json(
+            "{ \"items\": [ { \"path\": \"a\\\"b\", \"kind\": \"fn\", \"signature\": \"fn \\u0061()\", \"since\": \"1.2\" } ] }"
+        ),
+        Ok(Api {
+            items: vec![ApiItem::new("fn", "a\"b".to_string(), "fn a()".to_string())]
+        })
+    );
+    assert!(Api::from_json("{\"items\":[{\"kind\":\"fn\"}]}").is_err());
+    assert!(Api::from_json("{\"items\":[]").is_err());
+}
+
+#[test]
+fn changes() {
+    let old = Api::from_file(&parse_string(OLD, "old.nl").unwrap());
+    let new = Api::from_file(
+        &parse_string(
+            "struct Circle { radius: f64, center: (f32, f32) }
+impl Self { met area(&self) -> f32 { 3 } met perimeter(&self) -> f32 { 6 } }
+enum Shape { Round(f32), Square, Triangle }
+trait Drawable { met draw(&self) {} met hide(&self); }
+fn scale(circle: &Circle, by: f32) -> Circle {}",
+            "new.nl",
+        )
+        .unwrap(),
+    );
+
+    let changes: Vec<String> = compare(&old, &new)
+        .iter()
+        .map(|change| change.to_string())
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            "breaking: field `Circle::radius` changed from `radius: f32` to `radius: f64`",
+            "compatible: method `Drawable::draw` was given a body",
+            "breaking: required method `Drawable::hide` no longer has a body",
+            "breaking: fn `unused` was removed",
+            "breaking: field `Circle::center` was added",
+            "compatible: method `Circle::perimeter` was added",
+            "breaking: variant `Shape::Triangle` was added",
+        ]
+    );
+    assert!(compare(&old, &old).is_empty());
+}
@@ -0,0 +1,100 @@
+// Checks that `const fn` and `const met` bodies only do what could be run while compiling: arithmetic, comparisons,
+// variables, tuples, `if`, `match`, `loop` and `while`, `sizeof` and `alignof`, and calls to other const functions.
+// Anything that needs memory or the outside world, like taking an address, building a list or an unsafe block, isn't
+// allowed.
+
+use super::*;
+
+// What an operation is called in an error, if it can't be used in a const function.
+fn forbidden(operation: &NLOperation) -> Option<&'static str> {
+    match operation {
+        NLOperation::Operator(OpOperator::AddressOf(_)) => Some("taking an address"),
+        NLOperation::Operator(OpOperator::Dereference(_)) => Some("dereferencing"),
+        NLOperation::Operator(OpOperator::PropError(_)) => Some("`?`"),
+        NLOperation::List(_) => Some("a list"),
+        NLOperation::ForLoop(_) => Some("a for loop"),
+        NLOperation::TypeMatch(_) => Some("a type match"),
+        NLOperation::Catch(_) => Some("a catch block"),
+        NLOperation::Unsafe(_) => Some("an unsafe block"),
+        _ => None,
+    }
+}
+
+fn check_function<'b>(
+    function: &'b NLFunction,
+    const_functions: &[&str],
+    errors: &mut Vec<Finding<'b>>,
+) {
+    let block = match &function.block {
+        Some(block) if function.is_const => block,
+        _ => return,
+    };
+
+    block.walk(&mut |operation| {
+        if let Some(what) = forbidden(operation) {
+            errors.push(Finding::in_operation(
+                operation,
+                function.name,
+                format!("{} can't be used in const fn `{}`", what, function.name),
+            ));
+        }
+
+        if let NLOperation::FunctionCall(call) = operation {
+            if !const_functions.contains(&call.path) {
+                errors.push(Finding::new(
+                    vec![call.path],
+                    format!(
+                        "const fn `{}` calls `{}`, which isn't a const fn",
+                        function.name, call.path
+                    ),
+                ));
+            }
+        }
+        // There are no types to say whose method it is, so any const method of the same name will do.
+        if let NLOperation::MethodCall(call) = operation {
+            if !const_functions.contains(&call.method) {
+                errors.push(Finding::new(
+                    vec![call.method],
+                    format!(
+                        "const fn `{}` calls method `{}`, which isn't a const met",
+                        function.name, call.method
+                    ),
+                ));
+            }
+        }
+    });
+}
+
+// Every operation in a const function that can't be run while compiling, with messages naming the function.
+pub(super) fn check_const_functions<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let mut functions: Vec<&NLFunction> = file.functions.iter().collect();
+    let implementors = file
+        .traits
+        .iter()
+        .flat_map(|nl_trait| &nl_trait.implementors)
+        .chain(
+            file.structs
+                .iter()
+                .flat_map(|nl_struct| &nl_struct.implementations)
+                .chain(&file.implementations)
+                .flat_map(|implementation| &implementation.implementors),
+        );
+    for implementor in implementors {
+        if let NLImplementor::Method(method) = implementor {
+            functions.push(method);
+        }
+    }
+
+    let const_functions: Vec<&str> = functions
+        .iter()
+        .filter(|function| function.is_const)
+        .map(|function| function.name)
+        .collect();
+
+    let mut errors = vec![];
+    for function in functions {
+        check_function(function, &const_functions, &mut errors);
+    }
+
+    errors
+}
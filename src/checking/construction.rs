@@ -0,0 +1,92 @@
+// Checks that struct literals like `Point { x: 1, y: 2 }` name a struct declared in the file and give each of its
+// fields a value exactly once. The fields can be given in any order.
+
+use super::*;
+use crate::diagnostics::did_you_mean;
+
+fn check_construction<'b>(
+    body: &str,
+    construction: &'b OpStructConstruction,
+    structs: &[NLStruct],
+    errors: &mut Vec<Finding<'b>>,
+) {
+    let name = construction.name;
+    let nl_struct = match structs.iter().find(|nl_struct| nl_struct.name == name) {
+        Some(nl_struct) => nl_struct,
+        None => {
+            let names = structs.iter().map(|nl_struct| nl_struct.name);
+            errors.push(Finding::new(
+                vec![name],
+                format!(
+                    "`{}` built in `{}` isn't a struct{}",
+                    name,
+                    body,
+                    did_you_mean(name, names)
+                ),
+            ));
+            return;
+        }
+    };
+    let fields: Vec<&str> = nl_struct
+        .variables
+        .iter()
+        .map(|variable| variable.name)
+        .collect();
+
+    let mut given: Vec<&str> = vec![];
+    for (field, _) in &construction.fields {
+        if !fields.contains(field) {
+            errors.push(Finding::new(
+                vec![*field],
+                format!(
+                    "`{}` built in `{}` has no field `{}`{}",
+                    name,
+                    body,
+                    field,
+                    did_you_mean(field, fields.iter().copied())
+                ),
+            ));
+        } else if given.contains(field) {
+            errors.push(Finding::new(
+                vec![*field],
+                format!(
+                    "`{}` built in `{}` is given `{}` more than once",
+                    name, body, field
+                ),
+            ));
+        } else {
+            given.push(field);
+        }
+    }
+
+    let missing: Vec<String> = fields
+        .iter()
+        .filter(|field| !given.contains(field))
+        .map(|field| format!("`{}`", field))
+        .collect();
+    if !missing.is_empty() {
+        errors.push(Finding::new(
+            vec![name],
+            format!(
+                "`{}` built in `{}` is missing {}",
+                name,
+                body,
+                missing.join(", ")
+            ),
+        ));
+    }
+}
+
+// Every struct literal in the file that doesn't build the struct it names, with messages naming the function it's in.
+pub(super) fn check_constructions<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let mut errors = vec![];
+    for (body, block) in file.bodies() {
+        block.walk(&mut |operation| {
+            if let NLOperation::StructConstruction(construction) = operation {
+                check_construction(body, construction, &file.structs, &mut errors);
+            }
+        });
+    }
+
+    errors
+}
@@ -0,0 +1,37 @@
+// An error declaration can only hold another error in one of its variants, since `?` couldn't tell which of them to
+// wrap it in otherwise.
+
+use super::*;
+
+// A finding for each error declaration with more than one variant holding the same other error, at the later
+// variant.
+pub(super) fn check_error_conversions<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let is_error = |name: &str| {
+        file.enums
+            .iter()
+            .any(|nl_enum| nl_enum.is_error && nl_enum.name == name)
+    };
+
+    let mut errors = vec![];
+    for nl_enum in file.enums.iter().filter(|nl_enum| nl_enum.is_error) {
+        let mut seen: Vec<(&str, &str)> = vec![];
+        for variant in &nl_enum.variants {
+            let held = match enums::wraps(variant) {
+                Some(held) if is_error(held) => held,
+                _ => continue,
+            };
+            match seen.iter().find(|(name, _)| *name == held) {
+                Some((_, first)) => errors.push(Finding::new(
+                    vec![variant.name],
+                    format!(
+                        "error `{}` holds a `{}` in both `{}` and `{}`, so `?` can't tell which to wrap it in",
+                        nl_enum.name, held, first, variant.name
+                    ),
+                )),
+                None => seen.push((held, variant.name)),
+            }
+        }
+    }
+
+    errors
+}
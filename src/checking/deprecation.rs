@@ -0,0 +1,252 @@
+// Warns about uses of functions, methods and getters marked `#[deprecated]`, once the whole file has been read and it's
+// known which ones those are. Functions are called by name and methods by `Owner::method` paths. There's no type
+// inference yet, so a `.method()` or `.getter` is only reported when it's reached through `self` or an argument, whose
+// types are written down. Another type could have a method or getter of the same name that isn't deprecated.
+
+use super::*;
+
+struct Deprecated<'b> {
+    kind: &'static str,
+    // The struct or trait a method or getter belongs to. Functions have none.
+    owner: Option<&'b str>,
+    name: &'b str,
+    message: &'b str,
+}
+
+impl<'b> Deprecated<'b> {
+    // The warning for a use of it at `at`, in the body named `caller`.
+    fn used<'c>(&self, at: &'c str, caller: &str) -> Finding<'c> {
+        let name = match self.owner {
+            Some(owner) => format!("{}::{}", owner, self.name),
+            None => self.name.to_string(),
+        };
+        let use_kind = if self.kind == "getter" {
+            "use of"
+        } else {
+            "call to"
+        };
+        let message = match self.message {
+            "" => format!(
+                "{} deprecated {} `{}` in `{}`",
+                use_kind, self.kind, name, caller
+            ),
+            message => format!(
+                "{} deprecated {} `{}` in `{}`: {}",
+                use_kind, self.kind, name, caller, message
+            ),
+        };
+
+        Finding::new(vec![at], message)
+    }
+}
+
+fn collect_implementors<'b>(
+    owner: &'b str,
+    implementors: &'b [NLImplementor],
+    deprecated: &mut Vec<Deprecated<'b>>,
+) {
+    for implementor in implementors {
+        let (kind, name, deprecation) = match implementor {
+            NLImplementor::Method(method) => ("method", method.name, method.get_deprecation()),
+            NLImplementor::Getter(getter) => {
+                ("getter", getter.name.as_str(), getter.get_deprecation())
+            }
+            NLImplementor::Setter(_) => continue,
+        };
+        if let Some(message) = deprecation {
+            deprecated.push(Deprecated {
+                kind,
+                owner: Some(owner),
+                name,
+                message,
+            });
+        }
+    }
+}
+
+// A body of code, with what's known about the types of the variables it starts with.
+struct Body<'b, 'a> {
+    name: &'b str,
+    // The struct or trait `self` is, in a method, getter or setter.
+    owner: Option<&'b str>,
+    arguments: &'b [NLArgument<'a>],
+    block: &'b NLBlock<'a>,
+}
+
+impl<'b, 'a> Body<'b, 'a> {
+    // The struct or trait the receiver of a method call or field access is, if it's `self` or an argument.
+    fn receiver_type(&self, receiver: &NLOperation) -> Option<&'b str> {
+        let name = match receiver {
+            NLOperation::VariableAccess(variable) => variable.name,
+            _ => return None,
+        };
+        let argument = self
+            .arguments
+            .iter()
+            .find(|argument| argument.name == name)?;
+        match argument.nl_type {
+            NLType::SelfReference | NLType::MutableSelfReference => self.owner,
+            NLType::OwnedStruct(name)
+            | NLType::ReferencedStruct(name)
+            | NLType::MutableReferencedStruct(name)
+            | NLType::OwnedTrait(name)
+            | NLType::ReferencedTrait(name)
+            | NLType::MutableReferencedTrait(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+fn implementor_bodies<'b, 'a>(
+    owner: &'b str,
+    implementors: &'b [NLImplementor<'a>],
+    bodies: &mut Vec<Body<'b, 'a>>,
+) {
+    for implementor in implementors {
+        let (name, arguments, block) = match implementor {
+            NLImplementor::Method(method) => match &method.block {
+                Some(block) => (method.name, &method.arguments, block),
+                None => continue,
+            },
+            NLImplementor::Getter(getter) => match &getter.block {
+                NLEncapsulationBlock::Some(block) => (getter.name.as_str(), &getter.args, block),
+                _ => continue,
+            },
+            NLImplementor::Setter(setter) => match &setter.block {
+                NLEncapsulationBlock::Some(block) => (setter.name, &setter.args, block),
+                _ => continue,
+            },
+        };
+        bodies.push(Body {
+            name,
+            owner: Some(owner),
+            arguments,
+            block,
+        });
+    }
+}
+
+// Whether a value of the type has the methods and getters of `owner`: it's `owner` itself, or a struct that implements
+// it.
+fn has_items_of(file: &NLFile, type_name: &str, owner: &str) -> bool {
+    type_name == owner
+        || file.implementations.iter().any(|implementation| {
+            implementation.target == Some(type_name) && implementation.name == owner
+        })
+        || file
+            .structs
+            .iter()
+            .filter(|nl_struct| nl_struct.name == type_name)
+            .flat_map(|nl_struct| &nl_struct.implementations)
+            .any(|implementation| implementation.name == owner)
+}
+
+// Every use of something deprecated, at the name it's used by, with messages naming it and the body it's in.
+pub(super) fn find_deprecated_uses<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let mut deprecated: Vec<Deprecated> = file
+        .functions
+        .iter()
+        .filter_map(|function| {
+            function.get_deprecation().map(|message| Deprecated {
+                kind: "function",
+                owner: None,
+                name: function.name,
+                message,
+            })
+        })
+        .collect();
+    for nl_trait in &file.traits {
+        collect_implementors(nl_trait.name, &nl_trait.implementors, &mut deprecated);
+    }
+    for nl_struct in &file.structs {
+        for implementation in &nl_struct.implementations {
+            collect_implementors(
+                nl_struct.name,
+                &implementation.implementors,
+                &mut deprecated,
+            );
+        }
+    }
+    for implementation in &file.implementations {
+        if let Some(target) = implementation.target {
+            collect_implementors(target, &implementation.implementors, &mut deprecated);
+        }
+    }
+
+    let mut warnings = vec![];
+    if deprecated.is_empty() {
+        return warnings;
+    }
+
+    let mut bodies: Vec<Body> = file
+        .functions
+        .iter()
+        .filter_map(|function| {
+            function.block.as_ref().map(|block| Body {
+                name: function.name,
+                owner: None,
+                arguments: &function.arguments,
+                block,
+            })
+        })
+        .collect();
+    for nl_trait in &file.traits {
+        implementor_bodies(nl_trait.name, &nl_trait.implementors, &mut bodies);
+    }
+    for nl_struct in &file.structs {
+        for implementation in &nl_struct.implementations {
+            implementor_bodies(nl_struct.name, &implementation.implementors, &mut bodies);
+        }
+    }
+    for implementation in &file.implementations {
+        if let Some(target) = implementation.target {
+            implementor_bodies(target, &implementation.implementors, &mut bodies);
+        }
+    }
+
+    let reached_through = |body: &Body, receiver: &NLOperation, item: &Deprecated| match (
+        body.receiver_type(receiver),
+        item.owner,
+    ) {
+        (Some(type_name), Some(owner)) => has_items_of(file, type_name, owner),
+        _ => false,
+    };
+
+    for body in &bodies {
+        body.block.walk(&mut |operation| {
+            let found = match operation {
+                NLOperation::FunctionCall(call) => match call.path.split_once("::") {
+                    Some((owner, name)) => deprecated.iter().find(|item| {
+                        item.kind == "method" && item.owner == Some(owner) && item.name == name
+                    }),
+                    None => deprecated
+                        .iter()
+                        .find(|item| item.owner.is_none() && item.name == call.path),
+                }
+                .map(|item| (item, call.path)),
+                NLOperation::MethodCall(call) => deprecated
+                    .iter()
+                    .find(|item| {
+                        item.kind == "method"
+                            && item.name == call.method
+                            && reached_through(body, &call.receiver, item)
+                    })
+                    .map(|item| (item, call.method)),
+                NLOperation::FieldAccess(access) => deprecated
+                    .iter()
+                    .find(|item| {
+                        item.kind == "getter"
+                            && item.name == access.field
+                            && reached_through(body, &access.structure, item)
+                    })
+                    .map(|item| (item, access.field)),
+                _ => None,
+            };
+            if let Some((item, at)) = found {
+                warnings.push(item.used(at, body.name));
+            }
+        });
+    }
+
+    warnings
+}
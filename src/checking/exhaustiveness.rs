@@ -0,0 +1,382 @@
+// Checks that a match over an enum has a branch for every one of its variants, or a `_` branch. Only matches whose
+// branches all name variants of the same declared enum, apart from `_`, are checked, since there's nothing yet to
+// tell what type the input of any other match is.
+//
+// A branch with a guard might not be taken even when its pattern matches, so it doesn't count as covering its variant.
+// The variant still needs a branch without one.
+//
+// Any struct can implement a trait, including ones in files that haven't been written yet, so a type match always
+// needs a `_` branch. The structs it names have to be declared.
+//
+// A pattern that names any of the values a variant holds has to name all of them.
+//
+// A `let` with a pattern that names a variant of an enum with more than one of them might not match, so it needs an
+// `else` block for when it doesn't. The variables would have no values after that block, so it has to leave with
+// `break`, `continue` or `return` rather than carrying on.
+
+use super::*;
+use crate::diagnostics::did_you_mean;
+
+// Whether the pattern matches any value at all, like `_` or `name @ _`.
+fn matches_anything(pattern: &MatchBranch) -> bool {
+    match pattern {
+        MatchBranch::AllOther => true,
+        MatchBranch::Binding(_, pattern) => matches_anything(pattern),
+        MatchBranch::Or(patterns) => patterns.iter().any(matches_anything),
+        _ => false,
+    }
+}
+
+// The enum and variant of each variant the pattern matches, or nothing if it can match values that aren't variants.
+fn variants<'a>(pattern: &MatchBranch<'a>) -> Option<Vec<(&'a str, &'a str)>> {
+    match pattern {
+        MatchBranch::Enum(branch) => Some(vec![(branch.nl_enum, branch.variant)]),
+        MatchBranch::Binding(_, pattern) => variants(pattern),
+        MatchBranch::Or(patterns) => {
+            let variants: Option<Vec<Vec<_>>> = patterns.iter().map(variants).collect();
+            variants.map(|variants| variants.concat())
+        }
+        _ => None,
+    }
+}
+
+// The enum all the branches name, if they're all enum branches or match anything.
+fn enum_of<'a>(nl_match: &Match<'a>) -> Option<&'a str> {
+    let mut names = vec![];
+    for (branch, _) in &nl_match.branches {
+        if !matches_anything(branch.pattern()) {
+            names.extend(
+                variants(branch.pattern())?
+                    .into_iter()
+                    .map(|(name, _)| name),
+            );
+        }
+    }
+
+    let first = *names.first()?;
+    if names.iter().all(|name| *name == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+// The enum branches in the pattern, including those inside `|` and `@`.
+fn enum_branches<'b, 'a>(pattern: &'b MatchBranch<'a>) -> Vec<&'b MatchEnumBranch<'a>> {
+    match pattern {
+        MatchBranch::Enum(branch) => vec![branch],
+        MatchBranch::Or(patterns) => patterns.iter().flat_map(enum_branches).collect(),
+        MatchBranch::Binding(_, pattern) | MatchBranch::Guarded(pattern, _) => {
+            enum_branches(pattern)
+        }
+        _ => vec![],
+    }
+}
+
+// A pattern can leave out what a variant holds, but if it names any of it, it has to name all of it. `place` is where
+// the pattern is, like "`let` in `main`", and `at` is the variant's name in the pattern.
+fn check_payload_count<'b>(
+    place: &str,
+    at: &'b str,
+    name: &str,
+    variant: &EnumVariant,
+    given: usize,
+    errors: &mut Vec<Finding<'b>>,
+) {
+    let held = variant.get_payload().len();
+    if given != 0 && given != held {
+        errors.push(Finding::new(
+            vec![at],
+            format!(
+                "{} gives `{}::{}` {} value{}, but it holds {}",
+                place,
+                name,
+                variant.name,
+                given,
+                if given == 1 { "" } else { "s" },
+                held
+            ),
+        ));
+    }
+}
+
+fn check_match<'b>(
+    file: &NLFile,
+    body: &'b str,
+    nl_match: &'b Match,
+    errors: &mut Vec<Finding<'b>>,
+) {
+    for (branch, _) in &nl_match.branches {
+        for branch in enum_branches(branch) {
+            let variant = file
+                .enums
+                .iter()
+                .filter(|nl_enum| nl_enum.name == branch.nl_enum)
+                .flat_map(|nl_enum| &nl_enum.variants)
+                .find(|variant| variant.name == branch.variant);
+            if let Some(variant) = variant {
+                check_payload_count(
+                    &format!("match in `{}`", body),
+                    branch.variant,
+                    branch.nl_enum,
+                    variant,
+                    branch.variables.len(),
+                    errors,
+                );
+            }
+        }
+    }
+
+    let name = match enum_of(nl_match) {
+        Some(name) => name,
+        None => return,
+    };
+    // Only a `_` without a guard is sure to catch whatever's left.
+    if nl_match
+        .branches
+        .iter()
+        .any(|(branch, _)| branch.guard().is_none() && matches_anything(branch))
+    {
+        return;
+    }
+    let nl_enum = match file.enums.iter().find(|nl_enum| nl_enum.name == name) {
+        Some(nl_enum) => nl_enum,
+        None => return,
+    };
+
+    for variant in &nl_enum.variants {
+        // Whether each branch naming the variant has a guard.
+        let guarded: Vec<bool> = nl_match
+            .branches
+            .iter()
+            .filter(|(branch, _)| {
+                variants(branch.pattern())
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|(_, covered)| *covered == variant.name)
+            })
+            .map(|(branch, _)| branch.guard().is_some())
+            .collect();
+
+        match (guarded.contains(&false), guarded.is_empty()) {
+            (true, _) => {}
+            (false, false) => errors.push(Finding::in_operation(
+                &nl_match.input,
+                body,
+                format!(
+                    "match in `{}` doesn't cover `{}::{}`, which only has branches with guards",
+                    body, name, variant.name
+                ),
+            )),
+            (false, true) => errors.push(Finding::in_operation(
+                &nl_match.input,
+                body,
+                format!(
+                    "match in `{}` doesn't cover `{}::{}`",
+                    body, name, variant.name
+                ),
+            )),
+        }
+    }
+}
+
+fn check_type_match<'b>(
+    file: &NLFile,
+    body: &'b str,
+    type_match: &'b TypeMatch,
+    errors: &mut Vec<Finding<'b>>,
+) {
+    for (branch, _) in &type_match.branches {
+        if let TypeMatchBranch::Struct(name, _) = branch {
+            if !file.structs.iter().any(|nl_struct| nl_struct.name == *name) {
+                let structs = file.structs.iter().map(|nl_struct| nl_struct.name);
+                errors.push(Finding::new(
+                    vec![name],
+                    format!(
+                        "type match in `{}` names `{}`, which isn't a struct{}",
+                        body,
+                        name,
+                        did_you_mean(name, structs)
+                    ),
+                ));
+            }
+        }
+    }
+
+    if !type_match
+        .branches
+        .iter()
+        .any(|(branch, _)| *branch == TypeMatchBranch::AllOther)
+    {
+        errors.push(Finding::in_operation(
+            &type_match.input,
+            body,
+            format!(
+                "type match in `{}` needs a `_` branch for the structs it doesn't name",
+                body
+            ),
+        ));
+    }
+}
+
+// The first variant the pattern names of an enum that has others, if it names one.
+fn refutable_variant<'a>(file: &NLFile, pattern: &LetPattern<'a>) -> Option<(&'a str, &'a str)> {
+    match pattern {
+        LetPattern::Variable(_) => None,
+        LetPattern::Struct(_, fields) => fields
+            .iter()
+            .find_map(|(_, pattern)| refutable_variant(file, pattern)),
+        LetPattern::Variant(name, variant, patterns) => {
+            let has_others = file
+                .enums
+                .iter()
+                .any(|nl_enum| nl_enum.name == *name && nl_enum.variants.len() > 1);
+            if has_others {
+                Some((name, variant))
+            } else {
+                patterns
+                    .iter()
+                    .find_map(|pattern| refutable_variant(file, pattern))
+            }
+        }
+    }
+}
+
+// Structs, fields, enums and variants the pattern names that were never declared.
+fn check_pattern_names<'b>(
+    file: &NLFile,
+    body: &str,
+    pattern: &'b LetPattern,
+    errors: &mut Vec<Finding<'b>>,
+) {
+    match pattern {
+        LetPattern::Variable(_) => {}
+        LetPattern::Struct(name, fields) => {
+            match file
+                .structs
+                .iter()
+                .find(|nl_struct| nl_struct.name == *name)
+            {
+                Some(nl_struct) => {
+                    for (field, _) in fields {
+                        if !nl_struct
+                            .variables
+                            .iter()
+                            .any(|variable| variable.name == *field)
+                        {
+                            errors.push(Finding::new(
+                                vec![field],
+                                format!(
+                                    "`let` in `{}` destructures `{}`, which has no field `{}`",
+                                    body, name, field
+                                ),
+                            ));
+                        }
+                    }
+                }
+                None => errors.push(Finding::new(
+                    vec![name],
+                    format!(
+                        "`let` in `{}` destructures `{}`, which isn't a struct",
+                        body, name
+                    ),
+                )),
+            }
+            for (_, pattern) in fields {
+                check_pattern_names(file, body, pattern, errors);
+            }
+        }
+        LetPattern::Variant(name, variant, patterns) => {
+            match file.enums.iter().find(|nl_enum| nl_enum.name == *name) {
+                Some(nl_enum) => match nl_enum
+                    .variants
+                    .iter()
+                    .find(|declared| declared.name == *variant)
+                {
+                    Some(declared) => check_payload_count(
+                        &format!("`let` in `{}`", body),
+                        variant,
+                        name,
+                        declared,
+                        patterns.len(),
+                        errors,
+                    ),
+                    None => errors.push(Finding::new(
+                        vec![variant],
+                        format!(
+                            "`let` in `{}` destructures `{}`, which has no variant `{}`",
+                            body, name, variant
+                        ),
+                    )),
+                },
+                None => errors.push(Finding::new(
+                    vec![name],
+                    format!(
+                        "`let` in `{}` destructures `{}`, which isn't an enum",
+                        body, name
+                    ),
+                )),
+            }
+            for pattern in patterns {
+                check_pattern_names(file, body, pattern, errors);
+            }
+        }
+    }
+}
+
+fn check_let_pattern<'b>(
+    file: &NLFile,
+    body: &'b str,
+    let_pattern: &'b OpLetPattern,
+    errors: &mut Vec<Finding<'b>>,
+) {
+    check_pattern_names(file, body, &let_pattern.pattern, errors);
+
+    match (
+        refutable_variant(file, &let_pattern.pattern),
+        &let_pattern.else_block,
+    ) {
+        (Some((name, variant)), None) => errors.push(Finding::new(
+            vec![name, variant],
+            format!(
+                "`let` in `{}` needs an `else` block, since `{}::{}` might not match",
+                body, name, variant
+            ),
+        )),
+        (_, Some(block)) => match block.operations.last() {
+            Some(NLOperation::Break(_))
+            | Some(NLOperation::Continue)
+            | Some(NLOperation::Return(_)) => {}
+            last => errors.push(Finding::in_operation(
+                last.unwrap_or(&let_pattern.assignment),
+                body,
+                format!(
+                    "the `else` block of `let` in `{}` has to end with `break`, `continue` or `return`, since the variables it gives values to would have none after it",
+                    body
+                ),
+            )),
+        },
+        (None, None) => {}
+    }
+}
+
+// Every variant left out of a match over an enum, every type match that's missing a `_` branch or names something
+// that isn't a struct, and every `let` pattern that might not match without an `else` block that leaves, with messages
+// naming the function they're in.
+pub(super) fn check_matches<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let mut errors = vec![];
+    for (body, block) in file.bodies() {
+        block.walk(&mut |operation| match operation {
+            NLOperation::Match(nl_match) => check_match(file, body, nl_match, &mut errors),
+            NLOperation::TypeMatch(type_match) => {
+                check_type_match(file, body, type_match, &mut errors)
+            }
+            NLOperation::LetPattern(let_pattern) => {
+                check_let_pattern(file, body, let_pattern, &mut errors)
+            }
+            _ => {}
+        });
+    }
+
+    errors
+}
@@ -0,0 +1,148 @@
+// The checks run on a file once it's been parsed and what stands for other code in it has been expanded. They find
+// the mistakes the grammar can't rule out: paths and struct literals that don't name what's declared, values used
+// after they've been moved, const and safe code doing what it can't, and matches that leave variants out. The lints
+// are run here too, at the levels the file and its options give them.
+//
+// There's no type inference yet, so none of the checks know the type of an expression, and each says what it leaves
+// alone because of that. There are no positions in the tree either, so what a check finds is reported at the names
+// written where it found it, which are slices of the text the file was parsed from.
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink, TextEdit};
+use crate::parsing::pragmas::{Lint, LintLevel};
+use crate::parsing::*;
+use std::fmt::Formatter;
+
+// All tests are kept in their own module.
+#[cfg(test)]
+mod tests;
+
+mod constness;
+mod construction;
+mod conversions;
+mod deprecation;
+mod exhaustiveness;
+mod moves;
+mod naming;
+mod paths;
+mod shadowing;
+mod unsafety;
+
+// Something a check found.
+struct Finding<'b> {
+    // Names written where the problem is. It's reported from the first of them to the end of the last.
+    names: Vec<&'b str>,
+    message: String,
+    // Edits that fix it, for the lints that know how to.
+    fix: Vec<TextEdit>,
+}
+
+impl<'b> Finding<'b> {
+    fn new(names: Vec<&'b str>, message: String) -> Finding<'b> {
+        Finding {
+            names,
+            message,
+            fix: vec![],
+        }
+    }
+
+    // At the names the operation writes, or at `body`, the name of the function it's in, if it doesn't write any.
+    fn in_operation(operation: &'b NLOperation, body: &'b str, message: String) -> Finding<'b> {
+        let mut names = operation.written_names();
+        if names.is_empty() {
+            names.push(body);
+        }
+
+        Finding::new(names, message)
+    }
+
+    fn with_fix(mut self, fix: Vec<TextEdit>) -> Finding<'b> {
+        self.fix = fix;
+        self
+    }
+
+    // The diagnostic made by `severity` for it, saying where it is in the file. A finding whose names aren't in the
+    // file's text, like `self`, is only reported with the file's name.
+    fn into_diagnostic(self, file: &NLFile, severity: fn(String) -> Diagnostic) -> Diagnostic {
+        let found = span(file.source, self.names);
+        let message = match found {
+            Some((offset, _)) => {
+                let before = &file.source[..offset];
+                let line = before.matches('\n').count() + 1;
+                let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+                let column = before[line_start..].chars().count() + 1;
+                format!(
+                    "{}: at line {}, column {}: {}",
+                    file.name, line, column, self.message
+                )
+            }
+            None => format!("{}: {}", file.name, self.message),
+        };
+
+        let diagnostic = severity(message).with_fix(self.fix);
+        match found {
+            Some((offset, length)) => diagnostic.with_span(offset, length),
+            None => diagnostic,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckError {
+    message: String,
+}
+
+impl std::error::Error for CheckError {}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Runs every check on a file that's been parsed, reporting what they find to `sink`. Lints report warnings or errors
+/// depending on their level. If anything was an error, the errors are given back together as well.
+pub fn check_file(file: &NLFile, sink: &mut dyn DiagnosticSink) -> Result<(), CheckError> {
+    let mut errors = conversions::check_error_conversions(file);
+    errors.append(&mut paths::resolve_paths(file));
+    errors.append(&mut construction::check_constructions(file));
+    errors.append(&mut moves::find_uses_after_move(file));
+    errors.append(&mut constness::check_const_functions(file));
+    errors.append(&mut unsafety::check_unsafety(file));
+    errors.append(&mut exhaustiveness::check_matches(file));
+
+    for warning in deprecation::find_deprecated_uses(file) {
+        sink.report(warning.into_diagnostic(file, Diagnostic::warning));
+    }
+
+    let linted = vec![
+        (Lint::Shadowing, shadowing::find_shadowing(file)),
+        (Lint::TypeNames, naming::check_type_names(file)),
+        (Lint::FunctionNames, naming::check_function_names(file)),
+    ];
+    for (lint, mut found) in linted {
+        match file.get_lint_level(lint) {
+            LintLevel::Allow => {}
+            LintLevel::Warn => {
+                for warning in found {
+                    sink.report(warning.into_diagnostic(file, Diagnostic::warning));
+                }
+            }
+            LintLevel::Deny => errors.append(&mut found),
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let mut messages = vec![];
+    for error in errors {
+        let diagnostic = error.into_diagnostic(file, Diagnostic::error);
+        messages.push(diagnostic.get_message().to_string());
+        sink.report(diagnostic);
+    }
+
+    Err(CheckError {
+        message: messages.join("\n"),
+    })
+}
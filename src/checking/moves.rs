@@ -0,0 +1,404 @@
+// Reports values that are used after they've been moved somewhere else.
+//
+// Numbers, booleans, enums, borrowed strings, references and pointers are copied when they're used. Everything else,
+// owned structs, strings and traits, shared and weak values, bytes, lists, maps and tuples holding any of those, is
+// moved: passing one to a function, assigning it to another variable or putting it in a tuple or list leaves the
+// original without a value until it's assigned again. Taking its address with `&`, indexing it or reading it in an
+// operator doesn't move it.
+//
+// A `break`, `continue` or `return` ends the path it's on, so what was moved on that path only matters where it goes:
+// after the loop, at the start of the loop's next pass, or nowhere.
+//
+// Only variables whose type is known are checked. That's arguments, variables declared with a type, and variables
+// assigned straight from one of those, since there is no type inference yet.
+
+use super::*;
+use std::collections::HashSet;
+
+#[derive(Default)]
+struct LoopExits<'a> {
+    // What might have been moved wherever the loop is left, or `None` if it never is.
+    breaks: Option<HashSet<&'a str>>,
+    // What might have been moved wherever the loop goes back around, or `None` if it never does.
+    continues: Option<HashSet<&'a str>>,
+}
+
+fn merge<'a>(into: &mut Option<HashSet<&'a str>>, moved: &HashSet<&'a str>) {
+    into.get_or_insert_with(HashSet::new).extend(moved.iter());
+}
+
+struct Checker<'b, 'a> {
+    body: &'b str,
+
+    // Variables that hold a value which would be moved by using it.
+    owned: HashSet<&'a str>,
+    // Variables that might have had their value moved out, including by a branch that might not have been taken.
+    moved: HashSet<&'a str>,
+    // Each variable is only reported once per body, however many times it's used afterwards.
+    reported: HashSet<&'a str>,
+    // Whether the path being checked has left through a `break`, `continue` or `return`.
+    diverges: bool,
+    // The loops around what's being checked, innermost last.
+    loops: Vec<LoopExits<'a>>,
+    errors: Vec<Finding<'a>>,
+}
+
+impl<'b, 'a> Checker<'b, 'a> {
+    fn is_moved_by_use(&self, nl_type: &NLType) -> bool {
+        match nl_type {
+            // Another owner of a shared value has to be made on purpose, so using one moves it too.
+            NLType::OwnedString
+            | NLType::OwnedTrait(_)
+            | NLType::Bytes
+            | NLType::List(_)
+            | NLType::Map(_, _)
+            | NLType::OwnedStruct(_)
+            | NLType::Shared(_)
+            | NLType::Weak(_) => true,
+            NLType::Tuple(items) => items.iter().any(|item| self.is_moved_by_use(item)),
+            _ => false,
+        }
+    }
+
+    fn use_variable(&mut self, name: &'a str, moves: bool) {
+        if self.moved.contains(name) {
+            if self.reported.insert(name) {
+                self.errors.push(Finding::new(
+                    vec![name],
+                    format!("use of moved value `{}` in `{}`", name, self.body),
+                ));
+            }
+        } else if moves && self.owned.contains(name) {
+            self.moved.insert(name);
+        }
+    }
+
+    fn assign(&mut self, assignment: &OpAssignment<'a>) {
+        self.check_operation(&assignment.assignment, true);
+
+        for (index, variable) in assignment.to_assign.iter().enumerate() {
+            let owned = match (
+                assignment.type_assignments.get(index),
+                &*assignment.assignment,
+            ) {
+                (Some(nl_type), _) => Some(self.is_moved_by_use(nl_type)),
+                (None, NLOperation::VariableAccess(value)) if assignment.to_assign.len() == 1 => {
+                    Some(self.owned.contains(value.name))
+                }
+                // A variable that's only being given a new value keeps the type it had.
+                (None, _) if !assignment.is_new => None,
+                (None, _) => Some(false),
+            };
+
+            match owned {
+                Some(true) => {
+                    self.owned.insert(variable.name);
+                }
+                Some(false) => {
+                    self.owned.remove(variable.name);
+                }
+                None => {}
+            }
+            self.moved.remove(variable.name);
+        }
+    }
+
+    // Checks each of the branches from the same starting point. Afterwards anything moved by any branch that gets to
+    // the end might have been moved, and the branches only diverge if all of them do.
+    fn check_branches<T>(&mut self, branches: &[T], check: impl Fn(&mut Self, &T)) {
+        let before = self.moved.clone();
+        let mut after = None;
+
+        for branch in branches {
+            self.moved = before.clone();
+            self.diverges = false;
+            check(self, branch);
+            if !self.diverges {
+                merge(&mut after, &self.moved);
+            }
+        }
+
+        match after {
+            Some(after) => {
+                self.moved = after;
+                self.diverges = false;
+            }
+            None => {
+                self.moved = before;
+                self.diverges = !branches.is_empty();
+            }
+        }
+    }
+
+    // Nothing after an operation that diverges is ever reached.
+    fn check_block(&mut self, block: &NLBlock<'a>) {
+        for operation in &block.operations {
+            if self.diverges {
+                break;
+            }
+            self.check_operation(operation, false);
+        }
+    }
+
+    // Checks a loop's body twice, since a value moved on one pass through it isn't there for the next. A body that never
+    // goes back around is only checked once. `ends_by_itself` is true when the loop can be left before any pass, without
+    // a `break`.
+    fn check_loop(
+        &mut self,
+        condition: Option<&NLOperation<'a>>,
+        ends_by_itself: bool,
+        block: &NLBlock<'a>,
+    ) {
+        let before = self.moved.clone();
+        self.loops.push(LoopExits::default());
+
+        for _ in 0..2 {
+            self.diverges = false;
+            if let Some(condition) = condition {
+                self.check_operation(condition, false);
+            }
+            if ends_by_itself {
+                let moved = self.moved.clone();
+                merge(&mut self.loops.last_mut().unwrap().breaks, &moved);
+            }
+            self.check_block(block);
+
+            let exits = self.loops.last_mut().unwrap();
+            if !self.diverges {
+                merge(&mut exits.continues, &self.moved);
+            }
+            match exits.continues.take() {
+                Some(continues) => self.moved = before.union(&continues).copied().collect(),
+                None => break,
+            }
+        }
+
+        match self.loops.pop().unwrap().breaks {
+            Some(breaks) => {
+                self.moved = breaks;
+                self.diverges = false;
+            }
+            None => {
+                self.moved = before;
+                self.diverges = true;
+            }
+        }
+    }
+
+    // `moves` is true when the value of the operation is being taken somewhere else.
+    fn check_operation(&mut self, operation: &NLOperation<'a>, moves: bool) {
+        match operation {
+            NLOperation::VariableAccess(variable) => self.use_variable(variable.name, moves),
+            NLOperation::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    self.check_operation(argument, true);
+                }
+            }
+            NLOperation::Assign(assignment) => self.assign(assignment),
+            // The types of the variables a pattern gives values to aren't known, so they're not checked.
+            NLOperation::LetPattern(let_pattern) => {
+                self.check_operation(&let_pattern.assignment, true);
+                // The else block has to leave, so nothing it moves is missing afterwards.
+                if let Some(block) = &let_pattern.else_block {
+                    let moved = self.moved.clone();
+                    self.check_block(block);
+                    self.moved = moved;
+                    self.diverges = false;
+                }
+                for name in let_pattern.pattern.variables() {
+                    self.owned.remove(name);
+                    self.moved.remove(name);
+                }
+            }
+            NLOperation::Tuple(items) | NLOperation::List(items) => {
+                for item in items {
+                    self.check_operation(item, true);
+                }
+            }
+            NLOperation::StructConstruction(construction) => {
+                for (_, value) in &construction.fields {
+                    self.check_operation(value, true);
+                }
+            }
+            // Indexing reads from the collection without taking it.
+            NLOperation::Index(index) => {
+                self.check_operation(&index.collection, false);
+                self.check_operation(&index.index, false);
+            }
+            // Fields and methods are reached through the structure without taking it. What the arguments are given to
+            // takes them, the same as for a function.
+            NLOperation::FieldAccess(access) => self.check_operation(&access.structure, false),
+            NLOperation::MethodCall(call) => {
+                self.check_operation(&call.receiver, false);
+                for argument in &call.arguments {
+                    self.check_operation(argument, true);
+                }
+            }
+            NLOperation::Operator(operator) => match operator {
+                OpOperator::LogicalNegate(operand)
+                | OpOperator::ArithmeticNegate(operand)
+                | OpOperator::BitNegate(operand)
+                | OpOperator::PropError(operand)
+                | OpOperator::AddressOf(operand)
+                | OpOperator::Dereference(operand) => self.check_operation(operand, false),
+                OpOperator::CompareEqual((left, right))
+                | OpOperator::CompareNotEqual((left, right))
+                | OpOperator::CompareGreater((left, right))
+                | OpOperator::CompareLess((left, right))
+                | OpOperator::CompareGreaterEqual((left, right))
+                | OpOperator::CompareLessEqual((left, right))
+                | OpOperator::LogicalAnd((left, right))
+                | OpOperator::LogicalOr((left, right))
+                | OpOperator::LogicalXor((left, right))
+                | OpOperator::BitAnd((left, right))
+                | OpOperator::BitOr((left, right))
+                | OpOperator::BitXor((left, right))
+                | OpOperator::BitLeftShift((left, right))
+                | OpOperator::BitRightShift((left, right))
+                | OpOperator::ArithmeticMod((left, right))
+                | OpOperator::ArithmeticAdd((left, right))
+                | OpOperator::ArithmeticSub((left, right))
+                | OpOperator::ArithmeticMul((left, right))
+                | OpOperator::ArithmeticDiv((left, right))
+                | OpOperator::Range((left, right)) => {
+                    self.check_operation(left, false);
+                    self.check_operation(right, false);
+                }
+            },
+            NLOperation::Block(block) | NLOperation::Catch(block) | NLOperation::Unsafe(block) => {
+                self.check_block(block)
+            }
+            NLOperation::If(statement) => {
+                self.check_operation(&statement.condition, false);
+                self.check_branches(
+                    &[&statement.true_block, &statement.false_block],
+                    |checker, block| checker.check_block(block),
+                );
+            }
+            NLOperation::Loop(block) => self.check_loop(None, false, block),
+            NLOperation::WhileLoop(while_loop) => {
+                self.check_loop(Some(&while_loop.condition), true, &while_loop.block)
+            }
+            NLOperation::ForLoop(for_loop) => {
+                self.check_operation(&for_loop.iterator, false);
+                self.owned.remove(for_loop.variable.name);
+                self.moved.remove(for_loop.variable.name);
+                self.check_loop(None, true, &for_loop.block);
+            }
+            NLOperation::Match(nl_match) => {
+                self.check_operation(&nl_match.input, false);
+                self.check_branches(&nl_match.branches, |checker, (pattern, branch)| {
+                    if let Some(guard) = pattern.guard() {
+                        checker.check_operation(guard, false);
+                    }
+                    checker.check_operation(branch, false)
+                });
+            }
+            NLOperation::TypeMatch(type_match) => {
+                self.check_operation(&type_match.input, false);
+                self.check_branches(&type_match.branches, |checker, (_, branch)| {
+                    checker.check_operation(branch, false)
+                });
+            }
+            // What a loop or function gives back is taken out of it.
+            NLOperation::Break(value) => {
+                if let Some(value) = value {
+                    self.check_operation(value, true);
+                }
+                if let Some(exits) = self.loops.last_mut() {
+                    merge(&mut exits.breaks, &self.moved);
+                }
+                self.diverges = true;
+            }
+            NLOperation::Continue => {
+                if let Some(exits) = self.loops.last_mut() {
+                    merge(&mut exits.continues, &self.moved);
+                }
+                self.diverges = true;
+            }
+            NLOperation::Return(value) => {
+                if let Some(value) = value {
+                    self.check_operation(value, true);
+                }
+                self.diverges = true;
+            }
+            NLOperation::Constant(_)
+            | NLOperation::SizeOf(_)
+            | NLOperation::AlignOf(_)
+            | NLOperation::NameOf(_)
+            | NLOperation::FieldsOf(_)
+            | NLOperation::VariantsOf(_) => {}
+        }
+    }
+}
+
+fn check_body<'a>(
+    name: &str,
+    arguments: &[NLArgument<'a>],
+    block: &NLBlock<'a>,
+    errors: &mut Vec<Finding<'a>>,
+) {
+    let mut checker = Checker {
+        body: name,
+        owned: HashSet::new(),
+        moved: HashSet::new(),
+        reported: HashSet::new(),
+        diverges: false,
+        loops: vec![],
+        errors: vec![],
+    };
+
+    for argument in arguments {
+        if checker.is_moved_by_use(&argument.nl_type) {
+            checker.owned.insert(argument.name);
+        }
+    }
+
+    checker.check_block(block);
+    errors.append(&mut checker.errors);
+}
+
+// Every use of a moved value in the file, at the variable, with messages naming it and the function it's in.
+pub(super) fn find_uses_after_move<'a>(file: &NLFile<'a>) -> Vec<Finding<'a>> {
+    fn check_implementors<'a>(implementors: &[NLImplementor<'a>], errors: &mut Vec<Finding<'a>>) {
+        for implementor in implementors {
+            match implementor {
+                NLImplementor::Method(method) => {
+                    if let Some(block) = &method.block {
+                        check_body(method.name, &method.arguments, block, errors);
+                    }
+                }
+                NLImplementor::Getter(getter) => {
+                    if let NLEncapsulationBlock::Some(block) = &getter.block {
+                        check_body(&getter.name, &getter.args, block, errors);
+                    }
+                }
+                NLImplementor::Setter(setter) => {
+                    if let NLEncapsulationBlock::Some(block) = &setter.block {
+                        check_body(setter.name, &setter.args, block, errors);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut errors = vec![];
+    for function in &file.functions {
+        if let Some(block) = &function.block {
+            check_body(function.name, &function.arguments, block, &mut errors);
+        }
+    }
+    for nl_trait in &file.traits {
+        check_implementors(&nl_trait.implementors, &mut errors);
+    }
+    for nl_struct in &file.structs {
+        for implementation in &nl_struct.implementations {
+            check_implementors(&implementation.implementors, &mut errors);
+        }
+    }
+    for implementation in &file.implementations {
+        check_implementors(&implementation.implementors, &mut errors);
+    }
+
+    errors
+}
@@ -0,0 +1,373 @@
+// Checks names against how what they name is meant to be written, for the `type_names` and `function_names` lints.
+// Structs, enums and traits are written in PascalCase, like `LightSwitch`, and functions and methods in snake_case,
+// like `turn_on`. There are no constants in the language yet, so there's no rule for them.
+//
+// Each name that's written the wrong way comes with a fix renaming it everywhere the file writes it: where it's
+// declared, in types, in the paths of calls and variables, and in method calls. A method is renamed along with every
+// other method of the same name, since a trait's methods and their implementations have to keep matching.
+
+use super::*;
+use crate::diagnostics::TextEdit;
+
+// The words in a name. Anything that isn't a letter or a digit separates words, and so does a capital letter
+// straight after a lowercase letter or a digit.
+fn words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            previous = None;
+            continue;
+        }
+
+        let starts_word = match previous {
+            Some(previous) => c.is_uppercase() && !previous.is_uppercase(),
+            None => true,
+        };
+        match words.last_mut() {
+            Some(word) if !starts_word => word.push(c),
+            _ => words.push(c.to_string()),
+        }
+        previous = Some(c);
+    }
+
+    words
+}
+
+// Leading underscores mean something, like that a function isn't meant to be used, so they're kept as they are.
+fn split_underscores(name: &str) -> (&str, &str) {
+    let rest = name.trim_start_matches('_');
+    (&name[..name.len() - rest.len()], rest)
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    let (_, rest) = split_underscores(name);
+    match rest.chars().next() {
+        Some(first) => first.is_uppercase() && !rest.contains('_'),
+        None => true,
+    }
+}
+
+fn is_snake_case(name: &str) -> bool {
+    !name.chars().any(char::is_uppercase)
+}
+
+fn pascal_case(name: &str) -> String {
+    let (underscores, rest) = split_underscores(name);
+    let words: Vec<String> = words(rest)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    format!("{}{}", underscores, words.concat())
+}
+
+fn snake_case(name: &str) -> String {
+    let (underscores, rest) = split_underscores(name);
+    let words: Vec<String> = words(rest).iter().map(|word| word.to_lowercase()).collect();
+
+    format!("{}{}", underscores, words.join("_"))
+}
+
+// Every name the file writes for something that's declared somewhere else in it.
+#[derive(Default)]
+struct Uses<'a> {
+    // Structs, enums and traits named as types or by implementations.
+    types: Vec<&'a str>,
+    // The paths of calls and variables, split at each `::`.
+    paths: Vec<Vec<&'a str>>,
+    // The methods called with `.`.
+    methods: Vec<&'a str>,
+}
+
+impl<'a> Uses<'a> {
+    fn add_type(&mut self, nl_type: &NLType<'a>) {
+        match nl_type {
+            NLType::OwnedStruct(name)
+            | NLType::ReferencedStruct(name)
+            | NLType::MutableReferencedStruct(name)
+            | NLType::OwnedTrait(name)
+            | NLType::ReferencedTrait(name)
+            | NLType::MutableReferencedTrait(name)
+            | NLType::Enum(name) => self.types.push(name),
+            NLType::Tuple(items) => {
+                for item in items {
+                    self.add_type(item);
+                }
+            }
+            NLType::Map(key, value) => {
+                self.add_type(key);
+                self.add_type(value);
+            }
+            NLType::List(nl_type)
+            | NLType::Pointer(nl_type)
+            | NLType::MutablePointer(nl_type)
+            | NLType::Shared(nl_type)
+            | NLType::Weak(nl_type)
+            | NLType::Endian(_, nl_type) => self.add_type(nl_type),
+            _ => {}
+        }
+    }
+
+    fn add_arguments(&mut self, arguments: &[NLArgument<'a>]) {
+        for argument in arguments {
+            self.add_type(&argument.nl_type);
+        }
+    }
+
+    fn add_block(&mut self, block: &NLBlock<'a>) {
+        block.walk(&mut |operation| match operation {
+            NLOperation::FunctionCall(call) => self.paths.push(call.path.split("::").collect()),
+            NLOperation::MethodCall(call) => self.methods.push(call.method),
+            NLOperation::StructConstruction(construction) => self.types.push(construction.name),
+            NLOperation::VariableAccess(variable) => {
+                self.paths.push(variable.name.split("::").collect())
+            }
+            NLOperation::Assign(assignment) => {
+                for nl_type in &assignment.type_assignments {
+                    self.add_type(nl_type);
+                }
+            }
+            NLOperation::SizeOf(nl_type)
+            | NLOperation::AlignOf(nl_type)
+            | NLOperation::NameOf(nl_type)
+            | NLOperation::FieldsOf(nl_type)
+            | NLOperation::VariantsOf(nl_type) => self.add_type(nl_type),
+            _ => {}
+        });
+    }
+
+    fn add_function(&mut self, function: &NLFunction<'a>) {
+        self.add_arguments(&function.arguments);
+        self.add_type(&function.return_type);
+        if let Some(block) = &function.block {
+            self.add_block(block);
+        }
+    }
+
+    fn add_implementors(&mut self, implementors: &[NLImplementor<'a>]) {
+        for implementor in implementors {
+            let (arguments, block) = match implementor {
+                NLImplementor::Method(method) => {
+                    self.add_function(method);
+                    continue;
+                }
+                NLImplementor::Getter(getter) => {
+                    self.add_type(&getter.nl_type);
+                    (&getter.args, &getter.block)
+                }
+                NLImplementor::Setter(setter) => (&setter.args, &setter.block),
+            };
+
+            self.add_arguments(arguments);
+            if let NLEncapsulationBlock::Some(block) = block {
+                self.add_block(block);
+            }
+        }
+    }
+
+    fn add_implementation(&mut self, implementation: &NLImplementation<'a>) {
+        self.types.push(implementation.name);
+        self.types.extend(implementation.target);
+        self.add_implementors(&implementation.implementors);
+    }
+
+    // Macros are included as they were written, so that names only they use are found too. The copies expanded into
+    // bodies point at the same text, so they don't add anything.
+    fn new(file: &NLFile<'a>) -> Uses<'a> {
+        let mut uses = Uses::default();
+
+        for nl_enum in &file.enums {
+            for variant in &nl_enum.variants {
+                uses.add_arguments(&variant.arguments);
+                for nl_type in &variant.payload {
+                    uses.add_type(nl_type);
+                }
+            }
+        }
+        for function in &file.functions {
+            uses.add_function(function);
+        }
+        for nl_trait in &file.traits {
+            uses.add_implementors(&nl_trait.implementors);
+        }
+        for nl_struct in &file.structs {
+            for variable in &nl_struct.variables {
+                uses.add_type(&variable.my_type);
+            }
+            for implementation in &nl_struct.implementations {
+                uses.add_implementation(implementation);
+            }
+        }
+        for implementation in &file.implementations {
+            uses.add_implementation(implementation);
+        }
+        for nl_macro in &file.macros {
+            uses.add_block(&nl_macro.block);
+        }
+
+        uses
+    }
+}
+
+// The edits swapping each of the names for `new_name`, in the order they're found in `source`. Names that aren't
+// part of it are left alone, and a name that was found twice is only swapped once.
+fn rename<'a>(
+    source: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    new_name: &str,
+) -> Vec<TextEdit> {
+    let start = source.as_ptr() as usize;
+    let mut edits: Vec<TextEdit> = names
+        .into_iter()
+        .filter_map(|name| {
+            let offset = (name.as_ptr() as usize).checked_sub(start)?;
+            if offset + name.len() > source.len() {
+                return None;
+            }
+
+            Some(TextEdit::new(offset, name.len(), new_name.to_string()))
+        })
+        .collect();
+
+    edits.sort_by_key(TextEdit::get_offset);
+    edits.dedup_by_key(|edit| edit.get_offset());
+    edits
+}
+
+// Every struct, enum and trait that isn't written in PascalCase, at its declaration, with the fix renaming it.
+pub(super) fn check_type_names<'a>(file: &NLFile<'a>) -> Vec<Finding<'a>> {
+    let declared = file
+        .structs
+        .iter()
+        .map(|nl_struct| ("struct", nl_struct.name))
+        .chain(file.enums.iter().map(|nl_enum| {
+            let kind = if nl_enum.is_error { "error" } else { "enum" };
+            (kind, nl_enum.name)
+        }))
+        .chain(file.traits.iter().map(|nl_trait| ("trait", nl_trait.name)));
+
+    let uses = Uses::new(file);
+    let mut found = vec![];
+    for (kind, name) in declared {
+        let new_name = pascal_case(name);
+        if is_pascal_case(name) || new_name == name {
+            continue;
+        }
+
+        let paths = uses
+            .paths
+            .iter()
+            .filter(|path| path.len() > 1)
+            .map(|path| path[0]);
+        let names = std::iter::once(name)
+            .chain(uses.types.iter().copied())
+            .chain(paths)
+            .filter(|written| *written == name);
+
+        found.push(
+            Finding::new(
+                vec![name],
+                format!(
+                    "{} `{}` should be written in PascalCase, like `{}`",
+                    kind, name, new_name
+                ),
+            )
+            .with_fix(rename(file.source, names, &new_name)),
+        );
+    }
+
+    found
+}
+
+// Every function and method that isn't written in snake_case, at its first declaration, with the fix renaming it.
+pub(super) fn check_function_names<'a>(file: &NLFile<'a>) -> Vec<Finding<'a>> {
+    let implementors = file
+        .traits
+        .iter()
+        .flat_map(|nl_trait| &nl_trait.implementors)
+        .chain(
+            file.structs
+                .iter()
+                .flat_map(|nl_struct| &nl_struct.implementations)
+                .chain(&file.implementations)
+                .flat_map(|implementation| &implementation.implementors),
+        );
+    let mut methods: Vec<&str> = vec![];
+    for implementor in implementors {
+        if let NLImplementor::Method(method) = implementor {
+            methods.push(method.name);
+        }
+    }
+
+    let uses = Uses::new(file);
+    let mut found = vec![];
+
+    for function in &file.functions {
+        let new_name = snake_case(function.name);
+        if is_snake_case(function.name) || new_name == function.name {
+            continue;
+        }
+
+        let calls = uses
+            .paths
+            .iter()
+            .filter(|path| path.len() == 1)
+            .map(|path| path[0]);
+        let names = std::iter::once(function.name)
+            .chain(calls)
+            .filter(|written| *written == function.name);
+
+        found.push(
+            Finding::new(
+                vec![function.name],
+                format!(
+                    "function `{}` should be written in snake_case, like `{}`",
+                    function.name, new_name
+                ),
+            )
+            .with_fix(rename(file.source, names, &new_name)),
+        );
+    }
+
+    let mut reported: Vec<&str> = vec![];
+    for name in methods.iter().copied() {
+        let new_name = snake_case(name);
+        if is_snake_case(name) || new_name == name || reported.contains(&name) {
+            continue;
+        }
+        reported.push(name);
+
+        let calls = uses
+            .paths
+            .iter()
+            .filter(|path| path.len() > 1)
+            .filter_map(|path| path.last().copied());
+        let names = methods
+            .iter()
+            .copied()
+            .chain(calls)
+            .chain(uses.methods.iter().copied())
+            .filter(|written| *written == name);
+
+        found.push(
+            Finding::new(
+                vec![name],
+                format!(
+                    "method `{}` should be written in snake_case, like `{}`",
+                    name, new_name
+                ),
+            )
+            .with_fix(rename(file.source, names, &new_name)),
+        );
+    }
+
+    found
+}
@@ -0,0 +1,170 @@
+// Checks that paths like `Circle::new()` and `Color::Red` name something declared in the file. The first part of a path
+// is the struct, enum or trait it belongs to. A call names one of its methods, and anything else names a variant of an
+// enum. A variant that holds values is built by calling it with them, like `Shape::Circle(1.0)`, and one that holds
+// nothing is used without calling it. There are no modules yet, so a path can't start with anything else or go any
+// deeper, and the errors for those say so.
+
+use super::*;
+use crate::diagnostics::did_you_mean;
+
+struct Items<'b> {
+    // Structs and traits with the names of their methods.
+    types: Vec<(&'b str, &'static str, Vec<&'b str>)>,
+    // Enums with their variants and how many values each holds.
+    enums: Vec<(&'b str, Vec<(&'b str, usize)>)>,
+}
+
+impl<'b> Items<'b> {
+    fn new(file: &'b NLFile) -> Self {
+        fn methods<'b>(implementors: &'b [NLImplementor], methods: &mut Vec<&'b str>) {
+            for implementor in implementors {
+                if let NLImplementor::Method(method) = implementor {
+                    methods.push(method.name);
+                }
+            }
+        }
+
+        let mut types = vec![];
+        for nl_struct in &file.structs {
+            let mut struct_methods = vec![];
+            for implementation in &nl_struct.implementations {
+                methods(&implementation.implementors, &mut struct_methods);
+            }
+            for implementation in &file.implementations {
+                if implementation.target == Some(nl_struct.name) {
+                    methods(&implementation.implementors, &mut struct_methods);
+                }
+            }
+            types.push((nl_struct.name, "struct", struct_methods));
+        }
+        for nl_trait in &file.traits {
+            let mut trait_methods = vec![];
+            methods(&nl_trait.implementors, &mut trait_methods);
+            types.push((nl_trait.name, "trait", trait_methods));
+        }
+
+        let enums = file
+            .enums
+            .iter()
+            .map(|nl_enum| {
+                let variants = nl_enum
+                    .variants
+                    .iter()
+                    .map(|variant| (variant.name, variant.get_payload().len()))
+                    .collect();
+                (nl_enum.name, variants)
+            })
+            .collect();
+
+        Items { types, enums }
+    }
+
+    // Why the path doesn't name anything, if it doesn't. `arguments` is how many it's called with, if it's a call.
+    fn resolve(&self, path: &str, arguments: Option<usize>) -> Option<String> {
+        let segments: Vec<&str> = path.split("::").collect();
+        let (owner, item) = match segments.as_slice() {
+            [_] => return None,
+            [owner, item] => (*owner, *item),
+            [] => unreachable!("a path always has a name in it"),
+            _ => {
+                return Some(
+                    "qualified module paths aren't supported yet, so a path can only name a struct, enum or \
+                     trait and one of its items"
+                        .to_string(),
+                )
+            }
+        };
+
+        let found_type = self.types.iter().find(|(name, _, _)| *name == owner);
+        let found_enum = self.enums.iter().find(|(name, _)| *name == owner);
+        match (found_type, found_enum, arguments) {
+            (Some((_, _, methods)), _, Some(_)) if !methods.contains(&item) => Some(format!(
+                "`{}` has no method `{}`{}",
+                owner,
+                item,
+                did_you_mean(item, methods.iter().copied())
+            )),
+            (Some((_, kind, _)), _, None) => Some(format!(
+                "`{}` is a {}, and only the variants of enums can be used without calling them",
+                owner, kind
+            )),
+            (_, Some((_, variants)), arguments) => {
+                let held = match variants.iter().find(|(variant, _)| *variant == item) {
+                    Some((_, held)) => *held,
+                    None => {
+                        return Some(format!(
+                            "`{}` has no variant `{}`{}",
+                            owner,
+                            item,
+                            did_you_mean(item, variants.iter().map(|(variant, _)| *variant))
+                        ))
+                    }
+                };
+                match arguments {
+                    Some(_) if held == 0 => Some(format!(
+                        "`{}` holds nothing, so it's used without calling it",
+                        path
+                    )),
+                    Some(given) if given != held => Some(format!(
+                        "`{}` is given {} value{}, but it holds {}",
+                        path,
+                        given,
+                        if given == 1 { "" } else { "s" },
+                        held
+                    )),
+                    None if held != 0 => Some(format!(
+                        "`{}` holds {} value{}, so it has to be called with {}",
+                        path,
+                        held,
+                        if held == 1 { "" } else { "s" },
+                        if held == 1 { "it" } else { "them" }
+                    )),
+                    _ => None,
+                }
+            }
+            (None, None, _) => {
+                let names = self
+                    .types
+                    .iter()
+                    .map(|(name, _, _)| *name)
+                    .chain(self.enums.iter().map(|(name, _)| *name));
+                Some(format!(
+                    "there's no struct, enum or trait named `{}`, and qualified module paths aren't supported \
+                     yet{}",
+                    owner,
+                    did_you_mean(owner, names)
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Every path in the file that doesn't name anything, at the path, with messages naming it and the function it's in.
+pub(super) fn resolve_paths<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    let items = Items::new(file);
+
+    let mut errors = vec![];
+    for (body, block) in file.bodies() {
+        block.walk(&mut |operation| {
+            let problem = match operation {
+                NLOperation::FunctionCall(call) => items
+                    .resolve(call.path, Some(call.arguments.len()))
+                    .map(|problem| (call.path, problem)),
+                NLOperation::VariableAccess(variable) => items
+                    .resolve(variable.name, None)
+                    .map(|problem| (variable.name, problem)),
+                _ => None,
+            };
+
+            if let Some((path, problem)) = problem {
+                errors.push(Finding::new(
+                    vec![path],
+                    format!("unresolved path `{}` in `{}`: {}", path, body, problem),
+                ));
+            }
+        });
+    }
+
+    errors
+}
@@ -0,0 +1,227 @@
+// Finds variables declared with the same name as one that's still in scope, for the `shadowing` lint. Declaring one
+// is always allowed, and the new variable hides the old one until the end of the block it was declared in. Whether
+// that's worth a warning or an error is up to the lint level.
+//
+// Arguments, `let` variables including those given values by a pattern, for loop variables and variables bound by
+// match and type match branches are all declarations.
+
+use super::*;
+
+struct Scopes<'a> {
+    // Innermost last.
+    scopes: Vec<Vec<&'a str>>,
+    shadowing: Vec<&'a str>,
+}
+
+impl<'a> Scopes<'a> {
+    fn declare(&mut self, name: &'a str) {
+        if self.scopes.iter().any(|scope| scope.contains(&name)) {
+            self.shadowing.push(name);
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name);
+        }
+    }
+
+    // Checks the block in a scope of its own, with some variables already declared in it.
+    fn check_scope(
+        &mut self,
+        declared: &[&'a str],
+        block: Option<&NLBlock<'a>>,
+        operation: Option<&NLOperation<'a>>,
+    ) {
+        self.scopes.push(vec![]);
+        for name in declared {
+            self.declare(name);
+        }
+        for operation in block
+            .into_iter()
+            .flat_map(|block| &block.operations)
+            .chain(operation)
+        {
+            self.check_operation(operation);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_block(&mut self, block: &NLBlock<'a>) {
+        self.check_scope(&[], Some(block), None);
+    }
+
+    fn check_operation(&mut self, operation: &NLOperation<'a>) {
+        match operation {
+            NLOperation::Assign(assignment) => {
+                self.check_operation(&assignment.assignment);
+                if assignment.is_new {
+                    for variable in &assignment.to_assign {
+                        self.declare(variable.name);
+                    }
+                }
+            }
+            // The variables aren't declared yet in the `else` block, which runs when the pattern doesn't match.
+            NLOperation::LetPattern(let_pattern) => {
+                self.check_operation(&let_pattern.assignment);
+                if let Some(block) = &let_pattern.else_block {
+                    self.check_block(block);
+                }
+                for name in let_pattern.pattern.variables() {
+                    self.declare(name);
+                }
+            }
+            NLOperation::Block(block)
+            | NLOperation::Loop(block)
+            | NLOperation::Catch(block)
+            | NLOperation::Unsafe(block) => self.check_block(block),
+            NLOperation::Tuple(items) | NLOperation::List(items) => {
+                for item in items {
+                    self.check_operation(item);
+                }
+            }
+            NLOperation::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    self.check_operation(argument);
+                }
+            }
+            NLOperation::StructConstruction(construction) => {
+                for (_, value) in &construction.fields {
+                    self.check_operation(value);
+                }
+            }
+            NLOperation::Operator(operator) => {
+                for operand in operator.operands() {
+                    self.check_operation(operand);
+                }
+            }
+            NLOperation::Index(index) => {
+                self.check_operation(&index.collection);
+                self.check_operation(&index.index);
+            }
+            NLOperation::FieldAccess(access) => self.check_operation(&access.structure),
+            NLOperation::MethodCall(call) => {
+                self.check_operation(&call.receiver);
+                for argument in &call.arguments {
+                    self.check_operation(argument);
+                }
+            }
+            NLOperation::If(statement) => {
+                self.check_operation(&statement.condition);
+                self.check_block(&statement.true_block);
+                self.check_block(&statement.false_block);
+            }
+            NLOperation::WhileLoop(while_loop) => {
+                self.check_operation(&while_loop.condition);
+                self.check_block(&while_loop.block);
+            }
+            NLOperation::ForLoop(for_loop) => {
+                self.check_operation(&for_loop.iterator);
+                self.check_scope(&[for_loop.variable.name], Some(&for_loop.block), None);
+            }
+            NLOperation::Match(nl_match) => {
+                self.check_operation(&nl_match.input);
+                for (branch, operation) in &nl_match.branches {
+                    // The guard can already use what the pattern bound.
+                    self.scopes.push(vec![]);
+                    for name in branch.variables() {
+                        self.declare(name);
+                    }
+                    if let Some(guard) = branch.guard() {
+                        self.check_operation(guard);
+                    }
+                    self.check_operation(operation);
+                    self.scopes.pop();
+                }
+            }
+            NLOperation::TypeMatch(type_match) => {
+                self.check_operation(&type_match.input);
+                for (branch, operation) in &type_match.branches {
+                    let declared = match branch {
+                        TypeMatchBranch::Struct(_, Some(variable)) => vec![*variable],
+                        _ => vec![],
+                    };
+                    self.check_scope(&declared, None, Some(operation));
+                }
+            }
+            NLOperation::Break(Some(value)) | NLOperation::Return(Some(value)) => {
+                self.check_operation(value)
+            }
+            NLOperation::Constant(_)
+            | NLOperation::VariableAccess(_)
+            | NLOperation::Break(None)
+            | NLOperation::Return(None)
+            | NLOperation::Continue
+            | NLOperation::SizeOf(_)
+            | NLOperation::AlignOf(_)
+            | NLOperation::NameOf(_)
+            | NLOperation::FieldsOf(_)
+            | NLOperation::VariantsOf(_) => {}
+        }
+    }
+}
+
+fn check_body<'a>(
+    name: &str,
+    arguments: &[NLArgument<'a>],
+    block: &NLBlock<'a>,
+    found: &mut Vec<Finding<'a>>,
+) {
+    let mut scopes = Scopes {
+        scopes: vec![],
+        shadowing: vec![],
+    };
+    let arguments: Vec<&str> = arguments.iter().map(|argument| argument.name).collect();
+    scopes.check_scope(&arguments, Some(block), None);
+
+    for shadowing in scopes.shadowing {
+        found.push(Finding::new(
+            vec![shadowing],
+            format!(
+                "`{}` in `{}` shadows a variable of the same name",
+                shadowing, name
+            ),
+        ));
+    }
+}
+
+// Every declaration in the file that shadows another variable, at the declaration, with messages naming the variable and
+// the function it's in.
+pub(super) fn find_shadowing<'a>(file: &NLFile<'a>) -> Vec<Finding<'a>> {
+    let mut found = vec![];
+    for function in &file.functions {
+        if let Some(block) = &function.block {
+            check_body(function.name, &function.arguments, block, &mut found);
+        }
+    }
+
+    let implementors = file
+        .traits
+        .iter()
+        .flat_map(|nl_trait| &nl_trait.implementors)
+        .chain(
+            file.structs
+                .iter()
+                .flat_map(|nl_struct| &nl_struct.implementations)
+                .chain(&file.implementations)
+                .flat_map(|implementation| &implementation.implementors),
+        );
+    for implementor in implementors {
+        match implementor {
+            NLImplementor::Method(method) => {
+                if let Some(block) = &method.block {
+                    check_body(method.name, &method.arguments, block, &mut found);
+                }
+            }
+            NLImplementor::Getter(getter) => {
+                if let NLEncapsulationBlock::Some(block) = &getter.block {
+                    check_body(&getter.name, &getter.args, block, &mut found);
+                }
+            }
+            NLImplementor::Setter(setter) => {
+                if let NLEncapsulationBlock::Some(block) = &setter.block {
+                    check_body(setter.name, &setter.args, block, &mut found);
+                }
+            }
+        }
+    }
+
+    found
+}
@@ -0,0 +1,740 @@
+use super::*;
+use crate::diagnostics::{apply_fixes, Severity};
+use crate::parsing::pragmas::*;
+
+fn check_with_options(
+    code: &str,
+    options: &ParseOptions,
+) -> (Result<(), CheckError>, Vec<Diagnostic>) {
+    let file = parse_string_with_options(code, "test_file.nl", options, &mut Vec::new()).unwrap();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let result = check_file(&file, &mut diagnostics);
+
+    (result, diagnostics)
+}
+
+fn check(code: &str) -> (Result<(), CheckError>, Vec<Diagnostic>) {
+    check_with_options(code, &ParseOptions::default())
+}
+
+fn messages(diagnostics: &[Diagnostic], severity: Severity) -> Vec<String> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.get_severity() == severity)
+        .map(|diagnostic| diagnostic.get_message().to_string())
+        .collect()
+}
+
+// The errors the checks find in `code`, which must be what makes them fail.
+fn errors(code: &str) -> Vec<String> {
+    let (result, diagnostics) = check(code);
+    let errors = messages(&diagnostics, Severity::Error);
+    assert_eq!(result.is_err(), !errors.is_empty());
+
+    errors
+}
+
+fn warnings(code: &str) -> Vec<String> {
+    let (_, diagnostics) = check(code);
+
+    messages(&diagnostics, Severity::Warning)
+}
+
+mod conversions {
+    use super::*;
+
+    #[test]
+    fn duplicate_conversions() {
+        assert_eq!(
+            errors(
+                "error FileError { NotFound, Denied(str), }
+error ConfigError { File(FileError), Missing(name: str), }
+error AppError { Read(FileError), Write(FileError), Config(ConfigError), }"
+            ),
+            vec!["test_file.nl: at line 3, column 35: error `AppError` holds a `FileError` in both `Read` and `Write`, so `?` can't tell which to wrap it in"]
+        );
+    }
+}
+
+mod deprecation {
+    use super::*;
+
+    #[test]
+    fn calls_warn() {
+        assert_eq!(
+            warnings(
+                "#[deprecated(\"use new_api\")] fn old_api() {}
+#[deprecated] fn older_api() {}
+fn new_api() {}
+fn main() { if true { old_api() } else { new_api() } }
+struct Thing {} impl Thing { met update() { older_api() } }",
+            ),
+            vec![
+                "test_file.nl: at line 4, column 23: call to deprecated function `old_api` in `main`: use new_api",
+                "test_file.nl: at line 5, column 45: call to deprecated function `older_api` in `update`",
+            ]
+        );
+    }
+
+    #[test]
+    fn methods_and_getters_warn() {
+        assert_eq!(
+            warnings(
+                "struct S {} impl Self { #[deprecated(\"use other\")] met old(&self); met other(&self) { self.old() } }
+trait Sized { #[deprecated] get size(&self) -> u32; }
+fn f(s: &S) { s.old() s.other() S::old() S::other() }
+fn g(s: &dyn Sized) { s.size }",
+            ),
+            vec![
+                "test_file.nl: at line 3, column 17: call to deprecated method `S::old` in `f`: use other",
+                "test_file.nl: at line 3, column 33: call to deprecated method `S::old` in `f`: use other",
+                "test_file.nl: at line 4, column 25: use of deprecated getter `Sized::size` in `g`",
+                "test_file.nl: at line 1, column 92: call to deprecated method `S::old` in `other`: use other",
+            ]
+        );
+    }
+
+    // Without types, a method or getter reached through anything but `self` or an argument could belong to another
+    // type with the same names.
+    #[test]
+    fn unknown_receivers() {
+        let (result, diagnostics) = check(
+            "struct S {} impl Self { #[deprecated] met old(&self); #[deprecated] get size(&self) -> u32; }
+struct T {} impl Self { met old(&self); get size(&self) -> u32; }
+fn make() -> T;
+fn f(t: &T) { t.old() t.size make().old() make().size }",
+        );
+        assert!(
+            result.is_ok() && diagnostics.is_empty(),
+            "{:?}",
+            diagnostics
+        );
+    }
+}
+
+mod moves {
+    use super::*;
+
+    const DECLARATIONS: &str = "struct Point { x: i32, }
+enum Level { Low, High, }
+fn take(point: Point) {}
+fn make() -> Point;
+";
+
+    // The errors reported for a body added to the declarations above.
+    fn body_errors(code: &str) -> Vec<String> {
+        errors(&format!("{}{}", DECLARATIONS, code))
+    }
+
+    #[test]
+    fn use_after_move() {
+        assert_eq!(
+            body_errors("fn main(point: Point) { take(point) take(point) take(point) }"),
+            vec!["test_file.nl: at line 5, column 42: use of moved value `point` in `main`"]
+        );
+        assert_eq!(
+            body_errors("fn main() { let point: Point = make() let other = point take(point) }"),
+            vec!["test_file.nl: at line 5, column 62: use of moved value `point` in `main`"]
+        );
+        assert_eq!(
+            body_errors("fn main(point: Point) { let pair = (point, 1) point }"),
+            vec!["test_file.nl: at line 5, column 47: use of moved value `point` in `main`"]
+        );
+    }
+
+    #[test]
+    fn copied_values() {
+        assert!(body_errors("fn main(level: Level, count: u32, point: &Point, name: &str) { take(level) take(level) take(count) take(count) take(point) take(point) take(name) take(name) }").is_empty());
+    }
+
+    #[test]
+    fn assigned_again() {
+        assert!(
+            body_errors("fn main(point: Point) { take(point) point = make() take(point) }")
+                .is_empty()
+        );
+        assert!(body_errors(
+            "fn main(point: Point) { take(point) let point: Point = make() take(point) }"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn shared_values() {
+        assert_eq!(
+            body_errors("fn main(node: rc Point, parent: weak Point) { take(node) take(parent) take(node) take(parent) }"),
+            vec![
+                "test_file.nl: at line 5, column 76: use of moved value `node` in `main`",
+                "test_file.nl: at line 5, column 87: use of moved value `parent` in `main`",
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_and_loops() {
+        assert_eq!(
+            body_errors("fn main(point: Point) { if true { take(point) } else {} take(point) }"),
+            vec!["test_file.nl: at line 5, column 62: use of moved value `point` in `main`"]
+        );
+        assert!(body_errors(
+            "fn main(point: Point) { if true { take(point) } else { take(point) } }"
+        )
+        .is_empty());
+        assert_eq!(
+            body_errors("fn main(point: Point) { loop { take(point) } }"),
+            vec!["test_file.nl: at line 5, column 37: use of moved value `point` in `main`"]
+        );
+        assert_eq!(
+            body_errors("struct Line {} impl Line { met draw(point: Point) { while true { take(point) } } }"),
+            vec!["test_file.nl: at line 5, column 71: use of moved value `point` in `draw`"]
+        );
+    }
+
+    #[test]
+    fn leaving_a_path() {
+        assert!(body_errors("fn main(point: Point) { loop { take(point) break } }").is_empty());
+        assert!(body_errors(
+            "fn main(point: Point) { if true { take(point) return } take(point) }"
+        )
+        .is_empty());
+        assert!(body_errors(
+            "fn main(point: Point) { while true { if true { take(point) return } } take(point) }"
+        )
+        .is_empty());
+        assert_eq!(
+            body_errors("fn main(point: Point) { loop { take(point) break } take(point) }"),
+            vec!["test_file.nl: at line 5, column 57: use of moved value `point` in `main`"]
+        );
+        assert_eq!(
+            body_errors(
+                "fn main(point: Point) { loop { if true { take(point) continue } break } }"
+            ),
+            vec!["test_file.nl: at line 5, column 47: use of moved value `point` in `main`"]
+        );
+    }
+}
+
+mod constness {
+    use super::*;
+
+    #[test]
+    fn allowed_operations() {
+        let errors = errors(
+            "const fn size() -> u32 { sizeof(u64) }
+const fn pick(x: i32) -> i32 { let y = size() if x > 0 { (x, y) } else { loop { break } } }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn forbidden_operations() {
+        assert_eq!(
+            errors("const fn bad() { [1, 2] unsafe { } }"),
+            vec![
+                "test_file.nl: at line 1, column 10: a list can't be used in const fn `bad`",
+                "test_file.nl: at line 1, column 10: an unsafe block can't be used in const fn `bad`",
+            ]
+        );
+        assert_eq!(
+            errors("fn now() -> u64; const fn later() -> u64 { now() }"),
+            vec!["test_file.nl: at line 1, column 44: const fn `later` calls `now`, which isn't a const fn"]
+        );
+        assert_eq!(
+            errors("const fn area(p: Point) -> u64 { p.width() }"),
+            vec!["test_file.nl: at line 1, column 36: const fn `area` calls method `width`, which isn't a const met"]
+        );
+    }
+}
+
+mod unsafety {
+    use super::*;
+
+    #[test]
+    fn dereference() {
+        assert_eq!(
+            errors("fn read(pointer: *const i32) -> i32 { *pointer }"),
+            vec!["test_file.nl: at line 1, column 40: dereferencing a pointer in `read` needs an unsafe block or unsafe fn"]
+        );
+        let errors = errors(
+            "fn read(pointer: *const i32) -> i32 { unsafe { *pointer } }
+unsafe fn read_raw(pointer: *const i32) -> i32 { *pointer }
+struct Reader {} impl Reader { unsafe met read(pointer: *const i32) -> i32 { *pointer } }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn unsafe_calls() {
+        assert_eq!(
+            errors(
+                "unsafe fn raw() {}
+struct Reader {} impl Reader { unsafe met read(&self); }
+fn main(reader: &Reader) { raw() Reader::read() reader.read() unsafe { raw() reader.read() } }
+unsafe fn inner() { raw() }"
+            ),
+            vec![
+                "test_file.nl: at line 3, column 28: call to unsafe fn `raw` in `main` needs an unsafe block or unsafe fn",
+                "test_file.nl: at line 3, column 34: call to unsafe fn `Reader::read` in `main` needs an unsafe block or unsafe fn",
+            ]
+        );
+    }
+
+    // Another type's method with the same name might be the one being called, so `.method()` calls are left alone.
+    #[test]
+    fn method_names() {
+        let errors = errors(
+            "struct A {} impl A { unsafe met read(&self); }
+struct B {} impl B { met read(&self) {} }
+fn main(b: &B) { b.read() }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
+
+mod exhaustiveness {
+    use super::*;
+
+    const LIGHT: &str = "enum Light { Red, Amber, Green, }\n";
+
+    #[test]
+    fn every_variant() {
+        let code = format!(
+            "{}fn f(light: Light) {{ match light {{ Light::Red => 0, Light::Amber => 1, Light::Green => 2 }} }}",
+            LIGHT
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+    }
+
+    #[test]
+    fn missing_variant() {
+        let code = format!(
+            "{}fn f(light: Light) {{ match light {{ Light::Red => 0, Light::Amber => 1 }} }}",
+            LIGHT
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 28: match in `f` doesn't cover `Light::Green`"]
+        );
+    }
+
+    #[test]
+    fn guarded_branches() {
+        let code = format!(
+            "{}fn f(light: Light, x: i32) {{ match light {{ Light::Red if x > 0 => 0, Light::Red => 1, Light::Amber => 2, Light::Green if x > 0 => 3 }} }}",
+            LIGHT
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 36: match in `f` doesn't cover `Light::Green`, which only has branches with guards"]
+        );
+    }
+
+    #[test]
+    fn or_pattern() {
+        let code = format!(
+            "{}fn f(light: Light) {{ match light {{ Light::Red | Light::Amber => 0, light @ Light::Green => 1 }} }}",
+            LIGHT
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(light: Light) {{ match light {{ Light::Red | Light::Amber => 0 }} }}",
+            LIGHT
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 28: match in `f` doesn't cover `Light::Green`"]
+        );
+    }
+
+    #[test]
+    fn type_match() {
+        const SHAPES: &str = "trait Shape {} struct Circle {} struct Square {}\n";
+
+        let code = format!(
+            "{}fn f(shape: &Shape) {{ match type shape {{ Circle(circle) => 0, _ => 1 }} }}",
+            SHAPES
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(shape: &Shape) {{ match type shape {{ Circel => 0, Square => 1 }} }}",
+            SHAPES
+        );
+        assert_eq!(
+            errors(&code),
+            vec![
+                "test_file.nl: at line 2, column 42: type match in `f` names `Circel`, which isn't a struct, did you mean `Circle`?",
+                "test_file.nl: at line 2, column 34: type match in `f` needs a `_` branch for the structs it doesn't name",
+            ]
+        );
+    }
+
+    #[test]
+    fn let_patterns() {
+        const SHAPES: &str = "struct Point { x: i32, y: i32, }
+enum Shape { Circle, Square, }
+enum Only { One, }
+";
+
+        let code = format!(
+            "{}fn f(point: Point, only: Only) {{ let Point {{ x, y }} = point let Only::One = only }}",
+            SHAPES
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(point: Point) {{ let Point {{ x, z }} = point }}",
+            SHAPES
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 4, column 37: `let` in `f` destructures `Point`, which has no field `z`"]
+        );
+    }
+
+    #[test]
+    fn let_else() {
+        const SHAPE: &str = "enum Shape { Circle, Square, }\n";
+
+        let code = format!(
+            "{}fn f(shapes: List<Shape>) {{ for shape in shapes {{ let Shape::Circle = shape else {{ continue }} }} }}",
+            SHAPE
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(shape: Shape) {{ let Shape::Circle = shape }}",
+            SHAPE
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 26: `let` in `f` needs an `else` block, since `Shape::Circle` might not match"]
+        );
+
+        let code = format!(
+            "{}fn f(shape: Shape) {{ let Shape::Circle = shape else {{ 0 }} }}",
+            SHAPE
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 4: the `else` block of `let` in `f` has to end with `break`, `continue` or `return`, since the variables it gives values to would have none after it"]
+        );
+    }
+
+    #[test]
+    fn payload_count() {
+        const SHAPES: &str = "enum Shape { Circle(f32), Rect(f32, f32), }\n";
+
+        let code = format!(
+            "{}fn f(shape: Shape) {{ match shape {{ Shape::Circle(radius) => 0, Shape::Rect => 1 }} }}",
+            SHAPES
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(shape: Shape) {{ match shape {{ Shape::Circle => 0, Shape::Rect(width) => 1 }} let Shape::Circle(a, b) = shape else {{ break }} }}",
+            SHAPES
+        );
+        assert_eq!(
+            errors(&code),
+            vec![
+                "test_file.nl: at line 2, column 63: match in `f` gives `Shape::Rect` 1 value, but it holds 2",
+                "test_file.nl: at line 2, column 93: `let` in `f` gives `Shape::Circle` 2 values, but it holds 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn all_other() {
+        let code = format!(
+            "{}fn f(light: Light, x: i32) {{ match light {{ Light::Red => 0, _ => 1 }} }}",
+            LIGHT
+        );
+        assert!(errors(&code).is_empty(), "{:?}", errors(&code));
+
+        let code = format!(
+            "{}fn f(light: Light, x: i32) {{ match light {{ Light::Red => 0, Light::Amber => 1, _ if x > 0 => 2 }} }}",
+            LIGHT
+        );
+        assert_eq!(
+            errors(&code),
+            vec!["test_file.nl: at line 2, column 36: match in `f` doesn't cover `Light::Green`"]
+        );
+    }
+}
+
+mod paths {
+    use super::*;
+
+    #[test]
+    fn resolved() {
+        let errors = errors(
+            "enum Color { Red, Green } trait Shape { met area(); } struct Square {}
+impl Shape for Square { met area() {} met side() {} }
+fn main() { Square::side() Shape::area() Color::Green }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn unresolved() {
+        assert_eq!(
+            errors(
+                "enum Color { Red, Green } struct Circle {} impl Circle { met new() {} }
+fn main() { Circle::nwe() Color::Gren math::PI Color::Red() Circle::new }"
+            ),
+            vec![
+                "test_file.nl: at line 2, column 13: unresolved path `Circle::nwe` in `main`: `Circle` has no method `nwe`, did you mean `new`?",
+                "test_file.nl: at line 2, column 27: unresolved path `Color::Gren` in `main`: `Color` has no variant `Gren`, did you mean `Green`?",
+                "test_file.nl: at line 2, column 39: unresolved path `math::PI` in `main`: there's no struct, enum or trait named `math`, and qualified module paths aren't supported yet",
+                "test_file.nl: at line 2, column 48: unresolved path `Color::Red` in `main`: `Color::Red` holds nothing, so it's used without calling it",
+                "test_file.nl: at line 2, column 61: unresolved path `Circle::new` in `main`: `Circle` is a struct, and only the variants of enums can be used without calling them",
+            ]
+        );
+        assert_eq!(
+            errors("struct Circle {} impl Circle { met new() {} } fn main() { Circle::new::twice() }"),
+            vec![
+                "test_file.nl: at line 1, column 59: unresolved path `Circle::new::twice` in `main`: qualified module paths aren't supported yet, so a path can only name a struct, enum or trait and one of its items"
+            ]
+        );
+    }
+
+    #[test]
+    fn variant_constructors() {
+        const SHAPE: &str =
+            "enum Shape { Circle(f32), Rectangle(width: f32, height: f32), Empty }\n";
+        let found = errors(&format!(
+            "{}fn main() {{ let s = Shape::Circle(1.0) let r = Shape::Rectangle(1.0, 2.0) let e = Shape::Empty }}",
+            SHAPE
+        ));
+        assert!(found.is_empty(), "{:?}", found);
+
+        assert_eq!(
+            errors(&format!(
+                "{}fn main() {{ let s = Shape::Circle let r = Shape::Rectangle(1.0) Shape::Empty(1) }}",
+                SHAPE
+            )),
+            vec![
+                "test_file.nl: at line 2, column 21: unresolved path `Shape::Circle` in `main`: `Shape::Circle` holds 1 value, so it has to be called with it",
+                "test_file.nl: at line 2, column 43: unresolved path `Shape::Rectangle` in `main`: `Shape::Rectangle` is given 1 value, but it holds 2",
+                "test_file.nl: at line 2, column 65: unresolved path `Shape::Empty` in `main`: `Shape::Empty` holds nothing, so it's used without calling it",
+            ]
+        );
+    }
+
+    #[test]
+    fn module_paths() {
+        assert_eq!(
+            errors("fn main() { math::PI }"),
+            vec![
+                "test_file.nl: at line 1, column 13: unresolved path `math::PI` in `main`: there's no struct, enum or trait named `math`, and qualified module paths aren't supported yet"
+            ]
+        );
+        assert_eq!(
+            errors("struct Circle {} impl Circle { met new() {} } fn main() { shapes::Circle::new() }"),
+            vec![
+                "test_file.nl: at line 1, column 59: unresolved path `shapes::Circle::new` in `main`: qualified module paths aren't supported yet, so a path can only name a struct, enum or trait and one of its items"
+            ]
+        );
+    }
+}
+
+mod construction {
+    use super::*;
+
+    #[test]
+    fn complete() {
+        let errors =
+            errors("struct Point { x: i32, y: i32 } fn main() { p = Point { y: 2, x: 1 } }");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn incomplete() {
+        assert_eq!(
+            errors(
+                "struct Point { x: i32, y: i32 } struct Line { start: Point, end: Point }
+fn main() { a = Pont { x: 1, y: 2 } b = Point { x: 1, x: 2, zz: 3, yy: 4 } c = Line {} }"
+            ),
+            vec![
+                "test_file.nl: at line 2, column 17: `Pont` built in `main` isn't a struct, did you mean `Point`?",
+                "test_file.nl: at line 2, column 55: `Point` built in `main` is given `x` more than once",
+                "test_file.nl: at line 2, column 61: `Point` built in `main` has no field `zz`",
+                "test_file.nl: at line 2, column 68: `Point` built in `main` has no field `yy`, did you mean `y`?",
+                "test_file.nl: at line 2, column 41: `Point` built in `main` is missing `y`",
+                "test_file.nl: at line 2, column 80: `Line` built in `main` is missing `start`, `end`",
+            ]
+        );
+    }
+}
+
+mod shadowing {
+    use super::*;
+
+    fn diagnostics(code: &str, options: &ParseOptions) -> Vec<(Severity, String)> {
+        let (_, diagnostics) = check_with_options(code, options);
+
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.get_severity(),
+                    diagnostic.get_message().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    const SHADOWING: &str = "fn f(x: i32) {
+    let y = x
+    if y > 0 { let y = 2 }
+    for x in y { let z = x }
+    let z = 3
+}";
+
+    #[test]
+    fn allowed_by_default() {
+        let file = parse_string(SHADOWING, "test_file.nl").unwrap();
+        assert_eq!(file.get_lint_level(Lint::Shadowing), LintLevel::Allow);
+        assert!(diagnostics(SHADOWING, &ParseOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn warn() {
+        let code = format!("#![warn(shadowing)]\n{}", SHADOWING);
+        assert_eq!(
+            diagnostics(&code, &ParseOptions::default()),
+            vec![
+                (
+                    Severity::Warning,
+                    "test_file.nl: at line 4, column 20: `y` in `f` shadows a variable of the same name".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "test_file.nl: at line 5, column 9: `x` in `f` shadows a variable of the same name".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn deny() {
+        let options = ParseOptions {
+            lint_levels: vec![(Lint::Shadowing, LintLevel::Deny)],
+            ..ParseOptions::default()
+        };
+        let (result, _) = check_with_options(SHADOWING, &options);
+        let error = result.expect_err("No error when one was expected.");
+        assert_eq!(
+            error.to_string(),
+            "test_file.nl: at line 3, column 20: `y` in `f` shadows a variable of the same name
+test_file.nl: at line 4, column 9: `x` in `f` shadows a variable of the same name"
+        );
+
+        // The file's own pragma wins over the options.
+        let code = format!("#![allow(shadowing)]\n{}", SHADOWING);
+        assert!(check_with_options(&code, &options).0.is_ok());
+    }
+}
+
+mod naming {
+    use super::*;
+    use crate::diagnostics::TextEdit;
+
+    const BADLY_NAMED: &str = "struct pointData { x: i32, }
+enum lightState { On, Off, }
+struct Lamp { state: lightState, }
+impl Lamp {
+    met TurnOn(&self) {}
+}
+fn MakePoint(x: i32) -> pointData { MakePoint(x) }
+fn main() { let p: pointData = MakePoint(x) p.TurnOn() Lamp::TurnOn(p) let s = lightState::On }";
+
+    #[test]
+    fn warn_by_default() {
+        let (_, diagnostics) = check(BADLY_NAMED);
+
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.get_message())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "test_file.nl: at line 1, column 8: struct `pointData` should be written in PascalCase, like `PointData`",
+                "test_file.nl: at line 2, column 6: enum `lightState` should be written in PascalCase, like `LightState`",
+                "test_file.nl: at line 7, column 4: function `MakePoint` should be written in snake_case, like `make_point`",
+                "test_file.nl: at line 5, column 9: method `TurnOn` should be written in snake_case, like `turn_on`",
+            ]
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.get_severity() == Severity::Warning));
+    }
+
+    #[test]
+    fn fixes() {
+        let (_, diagnostics) = check(BADLY_NAMED);
+
+        let code = apply_fixes(BADLY_NAMED, &diagnostics);
+        assert_eq!(
+            code,
+            "struct PointData { x: i32, }
+enum LightState { On, Off, }
+struct Lamp { state: LightState, }
+impl Lamp {
+    met turn_on(&self) {}
+}
+fn make_point(x: i32) -> PointData { make_point(x) }
+fn main() { let p: PointData = make_point(x) p.turn_on() Lamp::turn_on(p) let s = LightState::On }"
+        );
+
+        let (_, diagnostics) = check(&code);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    // Types named through references are renamed along with the declaration.
+    #[test]
+    fn referenced_types() {
+        let code = "struct pointData { x: i32, }
+trait drawable {}
+fn draw(point: &pointData, target: &mut pointData, shape: &dyn drawable, other: &mut dyn drawable) {}";
+        let (_, diagnostics) = check(code);
+
+        let code = apply_fixes(code, &diagnostics);
+        assert_eq!(
+            code,
+            "struct PointData { x: i32, }
+trait Drawable {}
+fn draw(point: &PointData, target: &mut PointData, shape: &dyn Drawable, other: &mut dyn Drawable) {}"
+        );
+
+        let (_, diagnostics) = check(&code);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn levels() {
+        let code = format!("#![allow(type_names)]\n{}", BADLY_NAMED);
+        let (_, diagnostics) = check(&code);
+        assert_eq!(diagnostics.len(), 2);
+
+        // Denied names are errors, and keep their fixes.
+        let options = ParseOptions {
+            lint_levels: vec![(Lint::FunctionNames, LintLevel::Deny)],
+            ..ParseOptions::default()
+        };
+        let (result, diagnostics) = check_with_options("fn _DoIt() {}", &options);
+        assert!(result.is_err());
+        assert_eq!(diagnostics[0].get_severity(), Severity::Error);
+        assert_eq!(
+            diagnostics[0].get_message(),
+            "test_file.nl: at line 1, column 4: function `_DoIt` should be written in snake_case, like `_do_it`"
+        );
+        assert_eq!(
+            diagnostics[0].get_fix(),
+            &[TextEdit::new(3, 5, "_do_it".to_string())]
+        );
+    }
+}
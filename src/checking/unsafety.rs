@@ -0,0 +1,150 @@
+// Checks that what can only be done in an unsafe context is: dereferencing a pointer and calling an `unsafe fn` or
+// `unsafe met`. The body of an unsafe function or method is an unsafe context, and so is an `unsafe { }` block
+// anywhere else.
+//
+// There are no types yet to tell whose method a `.method()` call is, so those calls aren't checked. Only calls through
+// an `Owner::method` path are.
+
+use super::*;
+use std::collections::HashSet;
+
+struct UnsafeFunction<'b> {
+    // The struct or trait a method belongs to. Functions have none.
+    owner: Option<&'b str>,
+    name: &'b str,
+}
+
+fn check_body<'b>(
+    name: &'b str,
+    block: &'b NLBlock,
+    unsafe_functions: &[UnsafeFunction],
+    errors: &mut Vec<Finding<'b>>,
+) {
+    // The walk can't skip what's inside an unsafe block, so those operations are found first and left out by address.
+    let mut in_unsafe_block = HashSet::new();
+    block.walk(&mut |operation| {
+        if let NLOperation::Unsafe(inner) = operation {
+            inner.walk(&mut |operation| {
+                in_unsafe_block.insert(operation as *const NLOperation);
+            });
+        }
+    });
+
+    block.walk(&mut |operation| {
+        if in_unsafe_block.contains(&(operation as *const NLOperation)) {
+            return;
+        }
+
+        match operation {
+            NLOperation::Operator(OpOperator::Dereference(_)) => {
+                errors.push(Finding::in_operation(
+                    operation,
+                    name,
+                    format!(
+                        "dereferencing a pointer in `{}` needs an unsafe block or unsafe fn",
+                        name
+                    ),
+                ))
+            }
+            NLOperation::FunctionCall(call) => {
+                let is_unsafe = match call.path.split_once("::") {
+                    Some((owner, method)) => unsafe_functions
+                        .iter()
+                        .any(|function| function.owner == Some(owner) && function.name == method),
+                    None => unsafe_functions
+                        .iter()
+                        .any(|function| function.owner.is_none() && function.name == call.path),
+                };
+                if is_unsafe {
+                    errors.push(Finding::new(
+                        vec![call.path],
+                        format!(
+                            "call to unsafe fn `{}` in `{}` needs an unsafe block or unsafe fn",
+                            call.path, name
+                        ),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+// Every unsafe operation outside of an unsafe context, with messages naming the function it's in.
+pub(super) fn check_unsafety<'b>(file: &'b NLFile) -> Vec<Finding<'b>> {
+    // Bodies with whether they're an unsafe context, and the methods of each struct and trait with their owner.
+    let mut bodies: Vec<(&str, &NLBlock, bool)> = vec![];
+    let mut methods: Vec<(&str, &NLFunction)> = vec![];
+
+    for function in &file.functions {
+        if let Some(block) = &function.block {
+            bodies.push((function.name, block, function.is_unsafe));
+        }
+    }
+
+    let implementors = file
+        .traits
+        .iter()
+        .map(|nl_trait| (nl_trait.name, &nl_trait.implementors))
+        .chain(file.structs.iter().flat_map(|nl_struct| {
+            nl_struct
+                .implementations
+                .iter()
+                .map(move |implementation| (nl_struct.name, &implementation.implementors))
+        }))
+        .chain(file.implementations.iter().filter_map(|implementation| {
+            implementation
+                .target
+                .map(|target| (target, &implementation.implementors))
+        }));
+    for (owner, implementors) in implementors {
+        for implementor in implementors {
+            match implementor {
+                NLImplementor::Method(method) => {
+                    methods.push((owner, method));
+                    if let Some(block) = &method.block {
+                        bodies.push((method.name, block, method.is_unsafe));
+                    }
+                }
+                NLImplementor::Getter(getter) => {
+                    if let NLEncapsulationBlock::Some(block) = &getter.block {
+                        bodies.push((&getter.name, block, false));
+                    }
+                }
+                NLImplementor::Setter(setter) => {
+                    if let NLEncapsulationBlock::Some(block) = &setter.block {
+                        bodies.push((setter.name, block, false));
+                    }
+                }
+            }
+        }
+    }
+
+    let unsafe_functions: Vec<UnsafeFunction> = file
+        .functions
+        .iter()
+        .filter(|function| function.is_unsafe)
+        .map(|function| UnsafeFunction {
+            owner: None,
+            name: function.name,
+        })
+        .chain(
+            methods
+                .iter()
+                .filter(|(_, method)| method.is_unsafe)
+                .map(|(owner, method)| UnsafeFunction {
+                    owner: Some(owner),
+                    name: method.name,
+                }),
+        )
+        .collect();
+
+    let mut errors = vec![];
+    for (name, block, is_unsafe) in bodies {
+        if !is_unsafe {
+            check_body(name, block, &unsafe_functions, &mut errors);
+        }
+    }
+
+    errors
+}
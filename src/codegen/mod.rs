@@ -0,0 +1,7 @@
+// Generators that turn NL declarations into code for use outside of the language itself.
+
+// All tests are kept in their own module.
+#[cfg(test)]
+mod tests;
+
+pub mod serde_nl;
@@ -0,0 +1,180 @@
+// Generates Rust code to encode and decode structs marked `#[serializable(format = "binary")]`, so a Rust program can
+// read and write the same bytes that NL describes.
+//
+// The binary format is every field in the order it was declared with no padding between them. Numbers are written in
+// the byte order given by their type (`u32_be`) and little endian when the type doesn't say. Booleans are one byte.
+
+use crate::parsing::layout::ByteOrder;
+use crate::parsing::{NLFile, NLStruct, NLType, SerialFormat};
+use std::fmt::Write;
+
+#[derive(Debug)]
+pub enum SerdeError<'a> {
+    UnsupportedField(&'a str, &'a str), // The names of the struct and of the field that can't be serialized.
+}
+
+type Result<'a, T> = std::result::Result<T, SerdeError<'a>>;
+
+struct WireField<'a> {
+    name: &'a str,
+    rust_type: &'static str,
+    byte_order: ByteOrder,
+    size: usize,
+}
+
+fn rust_number_type(nl_type: &NLType) -> Option<&'static str> {
+    match nl_type {
+        NLType::I8 => Some("i8"),
+        NLType::I16 => Some("i16"),
+        NLType::I32 => Some("i32"),
+        NLType::I64 => Some("i64"),
+        NLType::U8 => Some("u8"),
+        NLType::U16 => Some("u16"),
+        NLType::U32 => Some("u32"),
+        NLType::U64 => Some("u64"),
+        NLType::F32 => Some("f32"),
+        NLType::F64 => Some("f64"),
+        _ => None,
+    }
+}
+
+fn wire_fields<'a>(nl_struct: &'a NLStruct<'a>) -> Result<'a, Vec<WireField<'a>>> {
+    let mut fields = Vec::with_capacity(nl_struct.get_variables().len());
+
+    for variable in nl_struct.get_variables() {
+        let nl_type = variable.get_type();
+        let (number_type, byte_order) = match nl_type {
+            NLType::Endian(byte_order, number_type) => (&**number_type, *byte_order),
+            _ => (nl_type, ByteOrder::Little),
+        };
+
+        let rust_type = match number_type {
+            NLType::Boolean => Some("bool"),
+            _ => rust_number_type(number_type),
+        };
+
+        match (rust_type, number_type.size_of()) {
+            (Some(rust_type), Some(size)) => fields.push(WireField {
+                name: variable.get_name(),
+                rust_type,
+                byte_order,
+                size,
+            }),
+            _ => {
+                return Err(SerdeError::UnsupportedField(
+                    nl_struct.get_name(),
+                    variable.get_name(),
+                ))
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+fn write_struct(
+    output: &mut String,
+    nl_struct: &NLStruct,
+    fields: &[WireField],
+) -> std::fmt::Result {
+    let name = nl_struct.get_name();
+
+    writeln!(output, "#[derive(Debug, Clone, Copy, PartialEq)]")?;
+    writeln!(output, "pub struct {} {{", name)?;
+    for field in fields {
+        writeln!(output, "    pub {}: {},", field.name, field.rust_type)?;
+    }
+    writeln!(output, "}}")?;
+    writeln!(output)?;
+
+    let size: usize = fields.iter().map(|field| field.size).sum();
+
+    writeln!(output, "impl {} {{", name)?;
+    writeln!(output, "    pub const ENCODED_SIZE: usize = {};", size)?;
+    writeln!(output)?;
+
+    writeln!(output, "    pub fn encode(&self, output: &mut Vec<u8>) {{")?;
+    for field in fields {
+        match (field.rust_type, field.byte_order) {
+            ("bool", _) => writeln!(output, "        output.push(self.{} as u8);", field.name)?,
+            (_, ByteOrder::Little) => writeln!(
+                output,
+                "        output.extend_from_slice(&self.{}.to_le_bytes());",
+                field.name
+            )?,
+            (_, ByteOrder::Big) => writeln!(
+                output,
+                "        output.extend_from_slice(&self.{}.to_be_bytes());",
+                field.name
+            )?,
+        }
+    }
+    writeln!(output, "    }}")?;
+    writeln!(output)?;
+
+    writeln!(output, "    pub fn decode(input: &[u8]) -> Option<Self> {{")?;
+    writeln!(output, "        if input.len() < Self::ENCODED_SIZE {{")?;
+    writeln!(output, "            return None;")?;
+    writeln!(output, "        }}")?;
+    writeln!(output)?;
+    writeln!(output, "        Some({} {{", name)?;
+
+    let mut offset = 0;
+    for field in fields {
+        let bytes: Vec<String> = (offset..offset + field.size)
+            .map(|index| format!("input[{}]", index))
+            .collect();
+
+        match (field.rust_type, field.byte_order) {
+            ("bool", _) => writeln!(
+                output,
+                "            {}: input[{}] != 0,",
+                field.name, offset
+            )?,
+            (rust_type, ByteOrder::Little) => writeln!(
+                output,
+                "            {}: {}::from_le_bytes([{}]),",
+                field.name,
+                rust_type,
+                bytes.join(", ")
+            )?,
+            (rust_type, ByteOrder::Big) => writeln!(
+                output,
+                "            {}: {}::from_be_bytes([{}]),",
+                field.name,
+                rust_type,
+                bytes.join(", ")
+            )?,
+        }
+
+        offset += field.size;
+    }
+
+    writeln!(output, "        }})")?;
+    writeln!(output, "    }}")?;
+    writeln!(output, "}}")
+}
+
+/// Generates Rust source for every serializable struct in the file: a plain struct with the same fields, and
+/// `encode`/`decode` functions for its binary format. Structs without `#[serializable]` are skipped.
+pub fn generate_rust<'a>(file: &'a NLFile<'a>) -> Result<'a, String> {
+    let mut output = String::new();
+
+    for nl_struct in file.get_structs() {
+        match nl_struct.get_serialization() {
+            Some(SerialFormat::Binary) => {
+                let fields = wire_fields(nl_struct)?;
+
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+
+                // Writing into a string can't fail.
+                write_struct(&mut output, nl_struct, &fields).unwrap();
+            }
+            None => {}
+        }
+    }
+
+    Ok(output)
+}
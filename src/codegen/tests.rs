@@ -0,0 +1,90 @@
+use crate::parsing::parse_string;
+
+mod serde_nl {
+    use super::*;
+    use crate::codegen::serde_nl::*;
+
+    #[test]
+    fn binary_struct() {
+        let file = parse_string(
+            "#[serializable(format = \"binary\")]\nstruct Header { ready: bool, length: u16_be, id: i32 }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        let code = generate_rust(&file).unwrap();
+        assert_eq!(
+            code,
+            "#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Header {
+    pub ready: bool,
+    pub length: u16,
+    pub id: i32,
+}
+
+impl Header {
+    pub const ENCODED_SIZE: usize = 7;
+
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        output.push(self.ready as u8);
+        output.extend_from_slice(&self.length.to_be_bytes());
+        output.extend_from_slice(&self.id.to_le_bytes());
+    }
+
+    pub fn decode(input: &[u8]) -> Option<Self> {
+        if input.len() < Self::ENCODED_SIZE {
+            return None;
+        }
+
+        Some(Header {
+            ready: input[0] != 0,
+            length: u16::from_be_bytes([input[1], input[2]]),
+            id: i32::from_le_bytes([input[3], input[4], input[5], input[6]]),
+        })
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn unmarked_structs_skipped() {
+        let file = parse_string("struct Header { id: i32 }", "test_file.nl").unwrap();
+
+        assert_eq!(generate_rust(&file).unwrap(), "");
+    }
+
+    #[test]
+    fn unsupported_field() {
+        let file = parse_string(
+            "#[serializable(format = \"binary\")] struct Header { name: &str }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        match generate_rust(&file) {
+            Err(SerdeError::UnsupportedField(structure, field)) => {
+                assert_eq!(structure, "Header");
+                assert_eq!(field, "name");
+            }
+            result => panic!("Expected an unsupported field, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unknown_format() {
+        let message = parse_string(
+            "#[serializable(format = \"json\")] struct Header {}",
+            "test_file.nl",
+        )
+        .err()
+        .expect("No error when one was expected.")
+        .to_string();
+
+        assert!(
+            message.contains("the only serialization format is \"binary\""),
+            "{}",
+            message
+        );
+    }
+}
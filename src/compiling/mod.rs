@@ -13,13 +13,30 @@ mod tests;
 // Arguments are proveded by leaving them on the stack.
 // Values are returned by leaving them on the stack.
 
-enum CompileError<'a> {
+#[derive(Debug)]
+pub enum CompileError<'a> {
     VariableUndefined(&'a str), // String is the name of the variable.
     TypeUnspecified,            // We do not yet support type derive. The type must be specified.
+    NoEntryPoint,               // There is no `main` function to start the program from.
+    MultipleEntryPoints(usize), // The number of `main` functions that were found.
 }
 
 type Result<'a, T> = std::result::Result<T, CompileError<'a>>;
 
+/// Finds the `main` function a program starts from. There must be exactly one of them.
+pub fn find_entry_point<'a>(file: &'a NLFile<'a>) -> Result<'a, &'a NLFunction<'a>> {
+    let mut mains = file
+        .get_functions()
+        .iter()
+        .filter(|function| function.get_name() == "main");
+
+    match (mains.next(), mains.count()) {
+        (Some(main), 0) => Ok(main),
+        (Some(_), others) => Err(CompileError::MultipleEntryPoints(others + 1)),
+        (None, _) => Err(CompileError::NoEntryPoint),
+    }
+}
+
 pub struct Compiler {
     builder_context: FunctionBuilderContext,
     module: JITModule,
@@ -85,6 +102,9 @@ impl<'a> StackScope<'a> {
 
 impl Compiler {
     fn compile_function(&mut self, function: NLFunction) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("compile_function", name = function.get_name()).entered();
+
         let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_context);
 
         // Adding the arguments.
@@ -175,9 +195,21 @@ impl Compiler {
                 NLOperation::Tuple(_operations) => {
                     unimplemented!()
                 }
+                NLOperation::List(_items) => {
+                    unimplemented!()
+                }
                 NLOperation::Operator(_operator) => {
                     unimplemented!()
                 }
+                NLOperation::Index(_index) => {
+                    unimplemented!()
+                }
+                NLOperation::FieldAccess(_access) => {
+                    unimplemented!()
+                }
+                NLOperation::MethodCall(_method_call) => {
+                    unimplemented!()
+                }
                 NLOperation::If(_if_statement) => {
                     unimplemented!()
                 }
@@ -190,15 +222,42 @@ impl Compiler {
                 NLOperation::ForLoop(_for_loop) => {
                     unimplemented!()
                 }
-                NLOperation::Break => {
+                NLOperation::Break(_value) => {
+                    unimplemented!()
+                }
+                NLOperation::Continue => {
                     unimplemented!()
                 }
+                NLOperation::Return(_value) => {
+                    unimplemented!()
+                }
+                NLOperation::Catch(_catch_block) => {
+                    unimplemented!()
+                }
+                NLOperation::Unsafe(_unsafe_block) => {
+                    unimplemented!()
+                }
+                NLOperation::SizeOf(_) | NLOperation::AlignOf(_) => {
+                    unreachable!("type sizes are replaced with constants while parsing")
+                }
+                NLOperation::NameOf(_) | NLOperation::FieldsOf(_) | NLOperation::VariantsOf(_) => {
+                    unreachable!("reflection is replaced with constants while parsing")
+                }
                 NLOperation::Match(_match_statement) => {
                     unimplemented!()
                 }
+                NLOperation::TypeMatch(_type_match) => {
+                    unimplemented!()
+                }
+                NLOperation::LetPattern(_let_pattern) => {
+                    unimplemented!()
+                }
                 NLOperation::FunctionCall(_function_call) => {
                     unimplemented!()
                 }
+                NLOperation::StructConstruction(_construction) => {
+                    unimplemented!()
+                }
             }
         }
 
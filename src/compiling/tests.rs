@@ -2,3 +2,37 @@ use super::*;
 
 use unwrap_to::unwrap_to;
 
+mod entry_point {
+    use super::*;
+
+    #[test]
+    fn single_main() {
+        let file = parse_string("fn other() {} fn main() {}", "main.nl").unwrap();
+        let main = find_entry_point(&file).unwrap();
+
+        assert_eq!(main.get_name(), "main");
+    }
+
+    #[test]
+    fn no_main() {
+        let file = parse_string("fn other() {}", "main.nl").unwrap();
+
+        match find_entry_point(&file) {
+            Err(CompileError::NoEntryPoint) => {}
+            result => panic!("Expected no entry point, got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn multiple_mains() {
+        let file = parse_string("fn main() {} fn main() {}", "main.nl").unwrap();
+
+        match find_entry_point(&file) {
+            Err(CompileError::MultipleEntryPoints(count)) => assert_eq!(count, 2),
+            result => panic!(
+                "Expected multiple entry points, got {:?}",
+                result.map(|_| ())
+            ),
+        }
+    }
+}
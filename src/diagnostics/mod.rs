@@ -0,0 +1,243 @@
+use std::fmt::Formatter;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// Replaces `length` bytes of the source text, starting `offset` bytes in, with `text`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TextEdit {
+    offset: usize,
+    length: usize,
+    text: String,
+}
+
+impl TextEdit {
+    pub fn new(offset: usize, length: usize, text: String) -> TextEdit {
+        TextEdit {
+            offset,
+            length,
+            text,
+        }
+    }
+
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.length
+    }
+
+    // Two insertions at the same place overlap too, since which goes first would change the result.
+    fn overlaps(&self, other: &TextEdit) -> bool {
+        (self.offset < other.end() && other.offset < self.end()) || self.offset == other.offset
+    }
+}
+
+// Makes edits that don't overlap to `text`.
+pub(crate) fn apply_edits<'e>(text: &str, edits: impl IntoIterator<Item = &'e TextEdit>) -> String {
+    let mut edits: Vec<&TextEdit> = edits.into_iter().collect();
+
+    // Working from the end of the text back keeps the offsets of the edits still to be made the same.
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.offset));
+    let mut text = text.to_string();
+    for edit in edits {
+        text.replace_range(edit.offset..edit.end(), &edit.text);
+    }
+
+    text
+}
+
+/// Makes the fixes of all of `diagnostics` to `source`, the text they were reported for. Offsets count from after any
+/// byte order mark, which is kept. A fix that would overlap one that's already been made, or that doesn't fit the
+/// text, is left out whole, so it's safe to apply everything a file reported at once and then check it again.
+pub fn apply_fixes(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let (byte_order_mark, text) = match source.strip_prefix('\u{feff}') {
+        Some(text) => ("\u{feff}", text),
+        None => ("", source),
+    };
+
+    let mut edits: Vec<&TextEdit> = vec![];
+    for diagnostic in diagnostics {
+        let fits = diagnostic.fix.iter().all(|edit| {
+            text.get(edit.offset..edit.end()).is_some()
+                && !edits.iter().any(|made| made.overlaps(edit))
+        });
+        if fits {
+            edits.extend(&diagnostic.fix);
+        }
+    }
+
+    format!("{}{}", byte_order_mark, apply_edits(text, edits))
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    // The offset and length of what it's about in the source text, when that's known.
+    span: Option<(usize, usize)>,
+    // Edits that together make the problem go away, so that editors and tools can make them without asking.
+    fix: Vec<TextEdit>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            span: None,
+            fix: vec![],
+        }
+    }
+
+    pub fn warning(message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span: None,
+            fix: vec![],
+        }
+    }
+
+    pub fn note(message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Note,
+            message,
+            span: None,
+            fix: vec![],
+        }
+    }
+
+    pub fn with_fix(mut self, fix: Vec<TextEdit>) -> Diagnostic {
+        self.fix = fix;
+        self
+    }
+
+    pub fn with_span(mut self, offset: usize, length: usize) -> Diagnostic {
+        self.span = Some((offset, length));
+        self
+    }
+
+    // The same diagnostic with its message rewritten by `map`.
+    pub(crate) fn map_message(mut self, map: impl FnOnce(&str) -> String) -> Diagnostic {
+        self.message = map(&self.message);
+        self
+    }
+
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+    /// The offset and length of what's reported in the source text. Offsets count from after any byte order mark, the
+    /// same as those of fixes.
+    pub fn get_span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+    /// The edits that fix what's reported, all of which have to be made. Empty if it can't be fixed automatically.
+    pub fn get_fix(&self) -> &[TextEdit] {
+        &self.fix
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Note => write!(f, "note: {}", self.message),
+        }
+    }
+}
+
+// Every phase of compilation reports what it finds through one of these, so the library user can decide where it ends
+// up: collected for later, printed, or passed on to an editor.
+pub trait DiagnosticSink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+// Collects diagnostics so they can be inspected afterwards.
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+
+// Prints diagnostics to stderr as they come in.
+pub struct StderrSink;
+
+impl DiagnosticSink for StderrSink {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        eprintln!("{}", diagnostic);
+    }
+}
+
+// The number of single character insertions, deletions, substitutions and swaps of neighbouring characters it takes
+// to turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between the first i characters of a and the first j characters of b.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// Picks the candidate closest to a name that couldn't be found, for "did you mean" suggestions. Candidates that
+/// would need more than a third of the name changing aren't suggested.
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The end of a message about a name that couldn't be found, suggesting the closest of `candidates`, like
+/// ", did you mean `width`?". Empty if none of them is close enough.
+pub fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match closest_match(name, candidates) {
+        Some(candidate) => format!(", did you mean `{}`?", candidate),
+        None => String::new(),
+    }
+}
@@ -4,4 +4,9 @@
 extern crate unwrap_to;
 
 pub mod parsing;
+pub mod checking;
 pub mod compiling;
+pub mod diagnostics;
+pub mod codegen;
+pub mod schema;
+pub mod api;
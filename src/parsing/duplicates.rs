@@ -0,0 +1,217 @@
+// Finds blocks of code that were copied around a project, so that they can be pulled out into one function. Function
+// bodies are compared, and so are the blocks inside them: those of `if`s, loops, and catch and unsafe blocks.
+//
+// Two blocks are duplicates when they do the same thing to different variables or values. Variables are told apart
+// by where they're first used rather than by name, so `a = b + b` matches `x = y + y` but not `x = y + z`. Constants
+// of the same type all match each other. Anything else, like the functions called, the fields read and the types
+// named, has to be the same.
+
+use super::*;
+use std::collections::{BTreeMap, HashSet};
+
+/// Blocks smaller than this are too common to be worth reporting.
+pub const DEFAULT_MIN_OPERATIONS: usize = 10;
+
+/// Where one of the copies is.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Location {
+    file: String,
+    body: String,
+    span: Option<(usize, usize)>,
+}
+
+impl Location {
+    pub fn get_file(&self) -> &str {
+        &self.file
+    }
+    /// The function, method, getter or setter the block is in.
+    pub fn get_body(&self) -> &str {
+        &self.body
+    }
+    /// The offset and length of the block in the file's text. There are no positions in the tree, so it stretches
+    /// from the first name written in the block to the end of the last, starting at the name of the function when the
+    /// block is its whole body. A block that writes no names has no span.
+    pub fn get_span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+}
+
+/// Blocks that are the same apart from the names of their variables and their constants.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Duplicate {
+    operations: usize,
+    locations: Vec<Location>,
+}
+
+impl Duplicate {
+    /// The number of operations in each of the copies.
+    pub fn get_operations(&self) -> usize {
+        self.operations
+    }
+    /// The copies, in the order the files were given.
+    pub fn get_locations(&self) -> &[Location] {
+        &self.locations
+    }
+}
+
+// A block with its variables renamed and its constants zeroed, along with the order the variables were first used in.
+type Normalized<'a> = (NLBlock<'a>, Vec<usize>);
+
+fn normalize<'a>(block: &NLBlock<'a>) -> Normalized<'a> {
+    let mut names: Vec<&'a str> = vec![];
+    let mut order = vec![];
+    let mut rename = |name: &mut &'a str| {
+        let index = match names.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                names.push(name);
+                names.len() - 1
+            }
+        };
+        order.push(index);
+        *name = "_";
+    };
+
+    let mut normalized = block.clone();
+    normalized.walk_mut(&mut |operation| match operation {
+        // Paths like `Color::Red` aren't variables.
+        NLOperation::VariableAccess(variable) if !variable.name.contains("::") => {
+            rename(&mut variable.name)
+        }
+        NLOperation::Assign(assignment) => {
+            for variable in &mut assignment.to_assign {
+                rename(&mut variable.name);
+            }
+        }
+        NLOperation::LetPattern(let_pattern) => {
+            for name in let_pattern.pattern.variables_mut() {
+                rename(name);
+            }
+        }
+        NLOperation::ForLoop(for_loop) => rename(&mut for_loop.variable.name),
+        NLOperation::Constant(constant) => {
+            *constant = match constant {
+                OpConstant::Boolean(_) => OpConstant::Boolean(false),
+                OpConstant::Unsigned(_, nl_type) => OpConstant::Unsigned(0, nl_type.clone()),
+                OpConstant::Signed(_, nl_type) => OpConstant::Signed(0, nl_type.clone()),
+                OpConstant::Float32(_) => OpConstant::Float32(0.0),
+                OpConstant::Float64(_) => OpConstant::Float64(0.0),
+                OpConstant::Bytes(_) => OpConstant::Bytes(vec![]),
+                OpConstant::String(_) => OpConstant::String(String::new()),
+                OpConstant::Char(_) => OpConstant::Char('\0'),
+            }
+        }
+        _ => {}
+    });
+
+    (normalized, order)
+}
+
+// The blocks inside of a block, not counting the block itself.
+fn inner_blocks<'b, 'a>(block: &'b NLBlock<'a>) -> Vec<&'b NLBlock<'a>> {
+    let mut blocks = vec![];
+    block.walk(&mut |operation| match operation {
+        NLOperation::Block(block)
+        | NLOperation::Loop(block)
+        | NLOperation::Catch(block)
+        | NLOperation::Unsafe(block) => blocks.push(block),
+        NLOperation::If(statement) => {
+            blocks.push(&statement.true_block);
+            blocks.push(&statement.false_block);
+        }
+        NLOperation::WhileLoop(while_loop) => blocks.push(&while_loop.block),
+        NLOperation::ForLoop(for_loop) => blocks.push(&for_loop.block),
+        NLOperation::LetPattern(let_pattern) => blocks.extend(&let_pattern.else_block),
+        _ => {}
+    });
+
+    blocks
+}
+
+struct Candidate<'b, 'a> {
+    block: &'b NLBlock<'a>,
+    operations: usize,
+    location: Location,
+}
+
+/// Finds the blocks with at least `min_operations` operations that are repeated anywhere in the files, which are
+/// given as their names, the text they were parsed from and what was parsed. A block inside of copies that were
+/// already reported is only reported again if it was copied somewhere else too.
+pub fn find_duplicates(files: &[(&str, &str, &NLFile)], min_operations: usize) -> Vec<Duplicate> {
+    let mut buckets: BTreeMap<u64, Vec<(Normalized, Vec<Candidate>)>> = BTreeMap::new();
+
+    for (file_name, source, file) in files {
+        for (body, body_block) in file.bodies() {
+            let blocks = std::iter::once(body_block).chain(inner_blocks(body_block));
+            for block in blocks {
+                let mut operations = 0;
+                block.walk(&mut |_| operations += 1);
+                if operations < min_operations {
+                    continue;
+                }
+
+                let names = block.written_names();
+                let location = Location {
+                    file: file_name.to_string(),
+                    body: body.to_string(),
+                    span: if std::ptr::eq(block, body_block) {
+                        span(source, std::iter::once(body).chain(names))
+                    } else {
+                        span(source, names)
+                    },
+                };
+                let candidate = Candidate {
+                    block,
+                    operations,
+                    location,
+                };
+
+                let normalized = normalize(block);
+                let mut hasher = StableHasher::new();
+                normalized.hash(&mut hasher);
+                let bucket = buckets.entry(hasher.finish()).or_default();
+                match bucket.iter_mut().find(|(known, _)| *known == normalized) {
+                    Some((_, candidates)) => candidates.push(candidate),
+                    None => bucket.push((normalized, vec![candidate])),
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<Candidate>> = buckets
+        .into_values()
+        .flatten()
+        .map(|(_, candidates)| candidates)
+        .filter(|candidates| candidates.len() > 1)
+        .collect();
+    // The biggest first, so that what's inside of them can be left out.
+    groups.sort_by_key(|candidates| std::cmp::Reverse(candidates[0].operations));
+
+    let mut covered: HashSet<*const NLBlock> = HashSet::new();
+    let mut duplicates = vec![];
+    for candidates in groups {
+        if candidates
+            .iter()
+            .all(|candidate| covered.contains(&(candidate.block as *const NLBlock)))
+        {
+            continue;
+        }
+        for candidate in &candidates {
+            covered.extend(
+                inner_blocks(candidate.block)
+                    .into_iter()
+                    .map(|block| block as *const NLBlock),
+            );
+        }
+
+        duplicates.push(Duplicate {
+            operations: candidates[0].operations,
+            locations: candidates
+                .into_iter()
+                .map(|candidate| candidate.location)
+                .collect(),
+        });
+    }
+
+    duplicates
+}
@@ -0,0 +1,135 @@
+// Enums are written the same way as owned structs when they're used as a type, so `light: Light` is read as a struct
+// until the whole file has been read. This turns every owned struct type naming a declared enum into an enum type,
+// wherever the type is written: in signatures, fields, variant payloads, `let` types and type queries, and inside other
+// types.
+//
+// An error is an enum declared with `error`. A variant of one holding nothing but another error is how `?` converts
+// between them.
+
+use super::*;
+
+struct Resolver {
+    enums: Vec<String>,
+}
+
+impl Resolver {
+    fn resolve_type(&self, nl_type: &mut NLType) {
+        match nl_type {
+            NLType::OwnedStruct(name) if self.enums.iter().any(|nl_enum| nl_enum == name) => {
+                *nl_type = NLType::Enum(name)
+            }
+            NLType::Tuple(items) => {
+                for item in items {
+                    self.resolve_type(item);
+                }
+            }
+            NLType::Map(key, value) => {
+                self.resolve_type(key);
+                self.resolve_type(value);
+            }
+            NLType::List(nl_type)
+            | NLType::Pointer(nl_type)
+            | NLType::MutablePointer(nl_type)
+            | NLType::Shared(nl_type)
+            | NLType::Weak(nl_type)
+            | NLType::Endian(_, nl_type) => self.resolve_type(nl_type),
+            _ => {}
+        }
+    }
+
+    fn resolve_arguments(&self, arguments: &mut [NLArgument]) {
+        for argument in arguments {
+            self.resolve_type(&mut argument.nl_type);
+        }
+    }
+
+    fn resolve_block(&self, block: &mut NLBlock) {
+        block.walk_mut(&mut |operation| match operation {
+            NLOperation::Assign(assignment) => {
+                for nl_type in &mut assignment.type_assignments {
+                    self.resolve_type(nl_type);
+                }
+            }
+            NLOperation::SizeOf(nl_type) | NLOperation::AlignOf(nl_type) => {
+                self.resolve_type(nl_type)
+            }
+            _ => {}
+        });
+    }
+
+    fn resolve_function(&self, function: &mut NLFunction) {
+        self.resolve_arguments(&mut function.arguments);
+        self.resolve_type(&mut function.return_type);
+        if let Some(block) = &mut function.block {
+            self.resolve_block(block);
+        }
+    }
+
+    fn resolve_implementors(&self, implementors: &mut [NLImplementor]) {
+        for implementor in implementors {
+            let (arguments, block) = match implementor {
+                NLImplementor::Method(method) => {
+                    self.resolve_function(method);
+                    continue;
+                }
+                NLImplementor::Getter(getter) => {
+                    self.resolve_type(&mut getter.nl_type);
+                    (&mut getter.args, &mut getter.block)
+                }
+                NLImplementor::Setter(setter) => (&mut setter.args, &mut setter.block),
+            };
+
+            self.resolve_arguments(arguments);
+            if let NLEncapsulationBlock::Some(block) = block {
+                self.resolve_block(block);
+            }
+        }
+    }
+}
+
+pub(super) fn resolve_enum_types(file: &mut NLFile) {
+    let resolver = Resolver {
+        enums: file
+            .enums
+            .iter()
+            .map(|nl_enum| nl_enum.name.to_string())
+            .collect(),
+    };
+    if resolver.enums.is_empty() {
+        return;
+    }
+
+    for nl_enum in &mut file.enums {
+        for variant in &mut nl_enum.variants {
+            resolver.resolve_arguments(&mut variant.arguments);
+            for nl_type in &mut variant.payload {
+                resolver.resolve_type(nl_type);
+            }
+        }
+    }
+    for function in &mut file.functions {
+        resolver.resolve_function(function);
+    }
+    for nl_trait in &mut file.traits {
+        resolver.resolve_implementors(&mut nl_trait.implementors);
+    }
+    for nl_struct in &mut file.structs {
+        for variable in &mut nl_struct.variables {
+            resolver.resolve_type(&mut variable.my_type);
+        }
+        for implementation in &mut nl_struct.implementations {
+            resolver.resolve_implementors(&mut implementation.implementors);
+        }
+    }
+    for implementation in &mut file.implementations {
+        resolver.resolve_implementors(&mut implementation.implementors);
+    }
+}
+
+// The error a variant holds on its own, if that's all it holds.
+pub(crate) fn wraps<'a>(variant: &EnumVariant<'a>) -> Option<&'a str> {
+    match variant.get_payload().as_slice() {
+        [NLType::Enum(name)] => Some(name),
+        _ => None,
+    }
+}
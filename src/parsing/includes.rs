@@ -0,0 +1,278 @@
+// `include "common/types.nl";` on a line of its own is replaced with the contents of that file before the result is
+// parsed, so declarations can be shared between files. The path is looked up next to the file doing the including
+// first and then in each of the include paths in order. A file that's included more than once only appears the first
+// time, and a file that ends up including itself is an error.
+//
+// A prelude is a list of files merged into every file the same way, straight after its pragmas, so that what they
+// declare doesn't have to be included everywhere. A file opts out with `#![no_prelude]`.
+//
+// The files are merged into one before parsing, so a map is kept from each line of the merged text back to the file
+// and line it came from. Parse errors are given with the line of the file it points to, and the includes that brought
+// that file in.
+
+use super::*;
+
+#[derive(Debug)]
+pub struct IncludeError {
+    message: String,
+}
+
+impl std::error::Error for IncludeError {}
+
+impl std::fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn read_include(line: &str) -> ParserResult<String> {
+    let (input, _) = tuple((blank, keyword("include"), blank))(line)?;
+    let (input, path) = match read_string_constant(input) {
+        Ok((input, OpConstant::String(path))) => (input, path),
+        _ => {
+            return Err(verbose_error(
+                input,
+                "expected the path to include in quotes",
+            ))
+        }
+    };
+    let (input, _) = tuple((blank, char(';')))(input)?;
+
+    Ok((input, path))
+}
+
+/// Where each line of text merged from several files came from.
+#[derive(Debug, Default)]
+pub struct LineMap {
+    // Where each file lines came from is, like " in `b.nl`, included from `a.nl` line 1". The file that was read comes
+    // first, and is left empty, since errors are already reported as being in it.
+    sources: Vec<String>,
+    // For each line of the merged text, which of the sources it's from and its line there.
+    lines: Vec<(usize, usize)>,
+}
+
+impl LineMap {
+    /// Where line `line` of the merged text came from, like "line 2 in `b.nl`, included from `a.nl` line 1". Lines are
+    /// counted from 1. Lines past the end, like the empty one after a last newline, are counted on from the last line.
+    pub fn locate(&self, line: usize) -> String {
+        let index = line.saturating_sub(1);
+        let found = match (self.lines.get(index), self.lines.last()) {
+            (Some(found), _) => Some(*found),
+            (None, Some((source, last))) => Some((*source, last + index + 1 - self.lines.len())),
+            (None, None) => None,
+        };
+
+        match found {
+            Some((source, line)) => format!("line {}{}", line, self.sources[source]),
+            None => format!("line {}", line),
+        }
+    }
+
+    /// `message` with every "at line N" in it pointing at the line it's about in the file it came from.
+    pub fn locate_in(&self, message: &str) -> String {
+        let mut located = String::new();
+        let mut rest = message;
+        while let Some(start) = rest.find("at line ") {
+            let after = &rest[start + "at line ".len()..];
+            let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            located.push_str(&rest[..start]);
+            match after[..digits].parse() {
+                Ok(line) => located.push_str(&format!("at {}", self.locate(line))),
+                Err(_) => located.push_str("at line "),
+            }
+            rest = &after[digits..];
+        }
+        located.push_str(rest);
+
+        located
+    }
+}
+
+struct Includer<'p> {
+    include_paths: &'p [PathBuf],
+    included: Vec<PathBuf>,
+
+    // The files being read, as they were named, with the line of the include being followed in each.
+    stack: Vec<(String, PathBuf, usize)>,
+    lines: LineMap,
+}
+
+impl<'p> Includer<'p> {
+    // Where the include being followed is, like " in `b.nl` line 2, included from `a.nl` line 1".
+    fn chain(&self) -> String {
+        let mut chain = String::new();
+        for (index, (name, _, line)) in self.stack.iter().rev().enumerate() {
+            if index == 0 {
+                chain.push_str(&format!(" in `{}` line {}", name, line));
+            } else {
+                chain.push_str(&format!(", included from `{}` line {}", name, line));
+            }
+        }
+
+        chain
+    }
+
+    fn error(&self, message: String) -> IncludeError {
+        IncludeError {
+            message: format!("{}{}", message, self.chain()),
+        }
+    }
+
+    fn find(&self, name: &str, directory: &Path) -> Option<PathBuf> {
+        std::iter::once(directory)
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .map(|directory| directory.join(name))
+            .find(|path| path.is_file())
+    }
+
+    fn include(
+        &mut self,
+        name: String,
+        path: &Path,
+        output: &mut String,
+    ) -> Result<(), IncludeError> {
+        let read_error = |includer: &Self, error: std::io::Error| {
+            includer.error(format!("couldn't read `{}`: {}", name, error))
+        };
+
+        let canonical = path
+            .canonicalize()
+            .map_err(|error| read_error(self, error))?;
+
+        if let Some(start) = self
+            .stack
+            .iter()
+            .position(|(_, included, _)| *included == canonical)
+        {
+            let mut cycle: Vec<&str> = self.stack[start..]
+                .iter()
+                .map(|(name, _, _)| name.as_str())
+                .collect();
+            cycle.push(&name);
+
+            return Err(self.error(format!(
+                "`{}` includes itself through {}",
+                name,
+                cycle.join(" -> ")
+            )));
+        }
+        if self.included.contains(&canonical) {
+            return Ok(());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|error| read_error(self, error))?;
+        self.included.push(canonical.clone());
+        let mut location = String::new();
+        if !self.lines.sources.is_empty() {
+            location.push_str(&format!(" in `{}`", name));
+            for (including, _, line) in self.stack.iter().rev() {
+                location.push_str(&format!(", included from `{}` line {}", including, line));
+            }
+        }
+        self.lines.sources.push(location);
+        self.stack.push((name, canonical, 0));
+
+        let source = self.lines.sources.len() - 1;
+
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+        for (index, line) in contents.split_inclusive('\n').enumerate() {
+            let included_name = match read_include(line) {
+                Ok((rest, included_name)) if rest.trim().is_empty() => included_name,
+                _ => {
+                    output.push_str(line);
+                    self.lines.lines.push((source, index + 1));
+                    continue;
+                }
+            };
+
+            if let Some((_, _, current_line)) = self.stack.last_mut() {
+                *current_line = index + 1;
+            }
+
+            let included_path = match self.find(&included_name, directory) {
+                Some(included_path) => included_path,
+                None => return Err(self.error(format!("couldn't find `{}`", included_name))),
+            };
+            self.include(included_name, &included_path, output)?;
+
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Reads the file at `path` with every file it includes merged in. Includes are looked for next to the file that
+/// includes them, and then in each of `include_paths`.
+pub fn read_with_includes(path: &Path, include_paths: &[PathBuf]) -> Result<String, IncludeError> {
+    read_with_prelude(path, include_paths, &[])
+}
+
+/// Reads the file at `path` like `read_with_includes`, with each of the `prelude` files merged in after its pragmas
+/// unless it has `#![no_prelude]`. A prelude file the file already includes isn't merged in again.
+pub fn read_with_prelude(
+    path: &Path,
+    include_paths: &[PathBuf],
+    prelude: &[PathBuf],
+) -> Result<String, IncludeError> {
+    read_with_line_map(path, include_paths, prelude).map(|(merged, _)| merged)
+}
+
+/// Reads the file at `path` like `read_with_prelude`, along with where each line of what was read came from.
+pub fn read_with_line_map(
+    path: &Path,
+    include_paths: &[PathBuf],
+    prelude: &[PathBuf],
+) -> Result<(String, LineMap), IncludeError> {
+    let mut includer = Includer {
+        include_paths,
+        included: vec![],
+        stack: vec![],
+        lines: LineMap::default(),
+    };
+
+    let mut output = String::new();
+    includer.include(path.display().to_string(), path, &mut output)?;
+    if prelude.is_empty() {
+        return Ok((output, includer.lines));
+    }
+
+    // Pragmas that can't be read are left for parsing to report. The prelude goes on the line after the last one.
+    let pragmas_end = match pragmas::read_pragmas(&output) {
+        Ok((rest, pragmas)) if !pragmas.no_prelude => match rest.find('\n') {
+            Some(newline) if rest[..newline].trim().is_empty() => {
+                output.len() - rest.len() + newline + 1
+            }
+            _ => output.len() - rest.len(),
+        },
+        _ => return Ok((output, includer.lines)),
+    };
+
+    let directory = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut merged = output[..pragmas_end].to_string();
+    // The lines of the file after its pragmas are moved to after the prelude. If the pragmas end part way through a
+    // line, the rest of it goes on a line of its own.
+    let mut after = includer.lines.lines.split_off(merged.matches('\n').count());
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+        includer.lines.lines.extend(after.first().copied());
+    }
+    for name in prelude {
+        let name = name.display().to_string();
+        let prelude_path = match includer.find(&name, directory) {
+            Some(prelude_path) => prelude_path,
+            None => return Err(includer.error(format!("couldn't find prelude file `{}`", name))),
+        };
+        includer.include(name, &prelude_path, &mut merged)?;
+        if !merged.is_empty() && !merged.ends_with('\n') {
+            merged.push('\n');
+        }
+    }
+    merged.push_str(&output[pragmas_end..]);
+    includer.lines.lines.append(&mut after);
+
+    Ok((merged, includer.lines))
+}
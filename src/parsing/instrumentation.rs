@@ -0,0 +1,155 @@
+// Instrumentation for the parser. Rule traces are recorded at runtime when asked for through `ParseOptions::trace`,
+// while tracing spans and counters compile down to nothing unless the `tracing` feature is enabled.
+
+use super::ParserResult;
+use std::cell::RefCell;
+
+#[cfg(feature = "tracing")]
+use std::cell::Cell;
+
+struct TraceEntry {
+    group: &'static str,
+    remaining: usize, // How much input was left when the rule was attempted. Lets us find the position later.
+    depth: usize,
+    matched: bool,
+}
+
+struct Trace {
+    entries: Vec<TraceEntry>,
+    depth: usize,
+}
+
+// Everything is kept per thread so that files being parsed on different threads don't record into each other.
+thread_local! {
+    static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "tracing")]
+thread_local! {
+    static NODES: Cell<usize> = const { Cell::new(0) };
+    static BACKTRACKS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn enter(group: &'static str, input: &str) -> Option<usize> {
+    TRACE.with(|trace| {
+        trace.borrow_mut().as_mut().map(|trace| {
+            trace.entries.push(TraceEntry {
+                group,
+                remaining: input.len(),
+                depth: trace.depth,
+                matched: false,
+            });
+            trace.depth += 1;
+
+            trace.entries.len() - 1
+        })
+    })
+}
+
+fn exit(index: Option<usize>, matched: bool) {
+    if let Some(index) = index {
+        TRACE.with(|trace| {
+            if let Some(trace) = trace.borrow_mut().as_mut() {
+                trace.entries[index].matched = matched;
+                trace.depth -= 1;
+            }
+        });
+    }
+}
+
+// Wraps a group of parser rules so that attempts at it show up in rule traces and tracing spans.
+pub fn traced<'a, O>(
+    group: &'static str,
+    mut parser: impl FnMut(&'a str) -> ParserResult<'a, O>,
+) -> impl FnMut(&'a str) -> ParserResult<'a, O> {
+    move |input: &'a str| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("parse", group, remaining = input.len()).entered();
+
+        let index = enter(group, input);
+        let result = parser(input);
+        exit(index, result.is_ok());
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(_) => NODES.with(|nodes| nodes.set(nodes.get() + 1)),
+            Err(nom::Err::Error(_)) => {
+                BACKTRACKS.with(|backtracks| backtracks.set(backtracks.get() + 1))
+            }
+            Err(_) => {} // Failures end the parse, so they aren't a backtrack.
+        }
+
+        result
+    }
+}
+
+// Runs a whole file parse. If `trace` is set, the rules attempted are rendered and handed back alongside the result.
+pub fn parse_file<T>(
+    input: &str,
+    file_name: &str,
+    trace: bool,
+    function: impl FnOnce() -> T,
+) -> (T, Option<String>) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("parse_file", file_name).entered();
+    #[cfg(not(feature = "tracing"))]
+    let _ = file_name;
+
+    #[cfg(feature = "tracing")]
+    {
+        NODES.with(|nodes| nodes.set(0));
+        BACKTRACKS.with(|backtracks| backtracks.set(0));
+    }
+
+    // Always reset, in case an earlier parse on this thread was interrupted by a panic.
+    TRACE.with(|recorded| {
+        *recorded.borrow_mut() = if trace {
+            Some(Trace {
+                entries: Vec::new(),
+                depth: 0,
+            })
+        } else {
+            None
+        }
+    });
+
+    let result = function();
+
+    #[cfg(feature = "tracing")]
+    {
+        let nodes = NODES.with(|nodes| nodes.get());
+        let backtracks = BACKTRACKS.with(|backtracks| backtracks.get());
+        tracing::debug!(nodes, backtracks, "finished parsing");
+    }
+
+    let trace = TRACE
+        .with(|trace| trace.borrow_mut().take())
+        .map(|trace| render_trace(input, &trace.entries));
+
+    (result, trace)
+}
+
+fn render_trace(input: &str, entries: &[TraceEntry]) -> String {
+    let mut rendered = String::from("rules attempted:");
+
+    for entry in entries {
+        let consumed = &input[..input.len() - entry.remaining];
+        let line = consumed.matches('\n').count() + 1;
+        let column = match consumed.rfind('\n') {
+            Some(newline) => consumed[newline + 1..].chars().count() + 1,
+            None => consumed.chars().count() + 1,
+        };
+
+        rendered.push_str(&format!(
+            "\n{:indent$}{} at line {}, column {}: {}",
+            "",
+            entry.group,
+            line,
+            column,
+            if entry.matched { "matched" } else { "failed" },
+            indent = entry.depth * 2
+        ));
+    }
+
+    rendered
+}
@@ -0,0 +1,344 @@
+// Memory layout of structs, for handing them to C code and to backends that need to know where each field lives.
+// Only fields whose size is known without looking at other declarations can be laid out for now. Owned structs,
+// traits, enums, strings and collections can't.
+//
+// `sizeof(T)` and `alignof(T)` are replaced with the numbers they stand for once the whole file has been read, so they
+// can also size structs that hold other structs declared in it. Enums, traits, strings and collections still can't be
+// sized, and asking for one is an error.
+
+use super::{NLFile, NLOperation, NLStruct, NLType, OpConstant};
+use crate::api::type_text;
+
+/// How a struct's fields are arranged in memory, chosen with a `#[repr(...)]` attribute.
+#[derive(PartialEq, Debug, Hash, Clone, Copy)]
+pub enum Repr {
+    /// Fields are ordered by alignment, largest first, so that as little padding as possible is needed.
+    Default,
+    /// `#[repr(c)]`: fields are kept in the order they were declared and padded like a C compiler would.
+    C,
+    /// `#[repr(packed)]`: fields are kept in the order they were declared with no padding at all.
+    Packed,
+}
+
+/// The order the bytes of a number are stored in.
+#[derive(PartialOrd, PartialEq, Debug, Hash, Clone, Copy)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+#[derive(Debug)]
+pub enum LayoutError<'a> {
+    UnsizedField(&'a str), // String is the name of the field.
+}
+
+#[derive(PartialEq, Debug)]
+pub struct FieldLayout<'a> {
+    name: &'a str,
+    offset: usize,
+    size: usize,
+    alignment: usize,
+    byte_order: Option<ByteOrder>, // `None` for fields that use whatever the target uses.
+}
+
+impl<'a> FieldLayout<'a> {
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+    pub fn get_alignment(&self) -> usize {
+        self.alignment
+    }
+    pub fn get_byte_order(&self) -> Option<ByteOrder> {
+        self.byte_order
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct StructLayout<'a> {
+    size: usize,
+    alignment: usize,
+    fields: Vec<FieldLayout<'a>>, // In the order they were declared, not the order they are in memory.
+}
+
+impl<'a> StructLayout<'a> {
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+    pub fn get_alignment(&self) -> usize {
+        self.alignment
+    }
+    pub fn get_fields(&self) -> &Vec<FieldLayout<'a>> {
+        &self.fields
+    }
+}
+
+const POINTER_SIZE: usize = 8;
+
+// The size and alignment of a type in bytes, or why it doesn't have them.
+type Size<E> = Result<(usize, usize), E>;
+
+fn align_to(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+impl<'a> NLType<'a> {
+    /// The size of the type in bytes, if it can be known without looking at other declarations.
+    pub fn size_of(&self) -> Option<usize> {
+        size_and_alignment(self).map(|(size, _)| size)
+    }
+
+    /// The alignment of the type in bytes, if it can be known without looking at other declarations.
+    pub fn align_of(&self) -> Option<usize> {
+        size_and_alignment(self).map(|(_, alignment)| alignment)
+    }
+}
+
+fn size_and_alignment(nl_type: &NLType) -> Option<(usize, usize)> {
+    size_and_alignment_with(nl_type, &mut |_| Err(())).ok()
+}
+
+// Sizes the types that can be sized on their own, and asks `other` about anything else, including what's inside
+// tuples and numbers with a byte order.
+fn size_and_alignment_with<'a, E>(
+    nl_type: &NLType<'a>,
+    other: &mut dyn FnMut(&NLType<'a>) -> Size<E>,
+) -> Size<E> {
+    match nl_type {
+        NLType::Boolean | NLType::I8 | NLType::U8 => Ok((1, 1)),
+        NLType::I16 | NLType::U16 => Ok((2, 2)),
+        NLType::I32 | NLType::U32 | NLType::F32 | NLType::Char => Ok((4, 4)),
+        NLType::I64 | NLType::U64 | NLType::F64 => Ok((8, 8)),
+        NLType::ReferencedStruct(_)
+        | NLType::MutableReferencedStruct(_)
+        | NLType::ReferencedTrait(_)
+        | NLType::MutableReferencedTrait(_)
+        | NLType::SelfReference
+        | NLType::MutableSelfReference
+        | NLType::BorrowedString
+        | NLType::Pointer(_)
+        | NLType::MutablePointer(_)
+        | NLType::Shared(_)
+        | NLType::Weak(_) => Ok((POINTER_SIZE, POINTER_SIZE)),
+        NLType::Endian(_, nl_type) => size_and_alignment_with(nl_type, other), // Byte order doesn't change the size.
+        NLType::Tuple(items) => {
+            // Tuples are laid out like a C struct.
+            let mut offset = 0;
+            let mut alignment = 1;
+            for item in items {
+                let (item_size, item_alignment) = size_and_alignment_with(item, other)?;
+                offset = align_to(offset, item_alignment) + item_size;
+                alignment = alignment.max(item_alignment);
+            }
+
+            Ok((align_to(offset, alignment), alignment))
+        }
+        _ => other(nl_type),
+    }
+}
+
+impl<'a> NLStruct<'a> {
+    pub fn get_repr(&self) -> Repr {
+        self.repr
+    }
+
+    /// Works out where each field of the struct lives in memory.
+    pub fn layout(&self) -> Result<StructLayout<'a>, LayoutError<'a>> {
+        self.lay_out(&mut |nl_type| size_and_alignment(nl_type).ok_or(()))
+            .map_err(|(name, ())| LayoutError::UnsizedField(name))
+    }
+
+    // Lays out the fields with the sizes and alignments `size_of_field` gives them. Gives back the first field it
+    // couldn't size instead, with why not.
+    fn lay_out<E>(
+        &self,
+        size_of_field: &mut dyn FnMut(&NLType<'a>) -> Size<E>,
+    ) -> Result<StructLayout<'a>, (&'a str, E)> {
+        let mut fields = Vec::with_capacity(self.variables.len());
+        for variable in &self.variables {
+            match size_of_field(&variable.my_type) {
+                Ok((size, alignment)) => fields.push(FieldLayout {
+                    name: variable.name,
+                    offset: 0,
+                    size,
+                    alignment,
+                    byte_order: match &variable.my_type {
+                        NLType::Endian(byte_order, _) => Some(*byte_order),
+                        _ => None,
+                    },
+                }),
+                Err(error) => return Err((variable.name, error)),
+            }
+        }
+
+        let mut order: Vec<usize> = (0..fields.len()).collect();
+        if self.repr == Repr::Default {
+            order.sort_by(|a, b| fields[*b].alignment.cmp(&fields[*a].alignment));
+        }
+
+        let mut offset = 0;
+        let mut alignment = 1;
+        for index in order {
+            let field = &mut fields[index];
+            if self.repr != Repr::Packed {
+                offset = align_to(offset, field.alignment);
+                alignment = alignment.max(field.alignment);
+            }
+
+            field.offset = offset;
+            offset += field.size;
+        }
+
+        Ok(StructLayout {
+            size: align_to(offset, alignment),
+            alignment,
+            fields,
+        })
+    }
+}
+
+// Why a type couldn't be sized.
+struct Unsized {
+    // The type that couldn't, and what's wrong with it, like "has no fixed size".
+    nl_type: String,
+    reason: &'static str,
+    // The innermost struct and field it was found in, if it was inside one.
+    field: Option<(String, String)>,
+}
+
+impl Unsized {
+    fn new(nl_type: &NLType, reason: &'static str) -> Unsized {
+        Unsized {
+            nl_type: type_text(nl_type),
+            reason,
+            field: None,
+        }
+    }
+}
+
+// Sizes types with the structs declared in a file, so that structs can hold other structs.
+struct Sizer<'f, 'a> {
+    structs: &'f [NLStruct<'a>],
+    // The structs being sized, innermost last, to catch one that holds itself.
+    sizing: Vec<&'a str>,
+}
+
+impl<'f, 'a> Sizer<'f, 'a> {
+    fn size_and_alignment(&mut self, nl_type: &NLType<'a>) -> Size<Unsized> {
+        size_and_alignment_with(nl_type, &mut |nl_type| self.size_of_declared(nl_type))
+    }
+
+    fn size_of_declared(&mut self, nl_type: &NLType<'a>) -> Size<Unsized> {
+        let name = match nl_type {
+            NLType::OwnedStruct(name) => *name,
+            NLType::Enum(_) => {
+                return Err(Unsized::new(
+                    nl_type,
+                    "is an enum, and enums don't have a layout yet",
+                ))
+            }
+            NLType::OwnedTrait(_) => {
+                return Err(Unsized::new(
+                    nl_type,
+                    "is a trait object, whose size depends on the struct behind it",
+                ))
+            }
+            _ => return Err(Unsized::new(nl_type, "has no fixed size")),
+        };
+
+        let structs = self.structs;
+        let nl_struct = match structs.iter().find(|nl_struct| nl_struct.name == name) {
+            Some(nl_struct) => nl_struct,
+            None => return Err(Unsized::new(nl_type, "isn't a declared struct")),
+        };
+        if self.sizing.contains(&name) {
+            return Err(Unsized::new(nl_type, "holds itself"));
+        }
+
+        self.sizing.push(name);
+        let layout = nl_struct.lay_out(&mut |field| self.size_and_alignment(field));
+        self.sizing.pop();
+
+        match layout {
+            Ok(layout) => Ok((layout.size, layout.alignment)),
+            Err((field, mut found)) => {
+                if found.field.is_none() {
+                    found.field = Some((name.to_string(), field.to_string()));
+                }
+                Err(found)
+            }
+        }
+    }
+}
+
+// What `sizeof(T)` or `alignof(T)` asks for, if the operation is one of them.
+fn type_query<'o, 'a>(operation: &'o NLOperation<'a>) -> Option<(&'static str, &'o NLType<'a>)> {
+    match operation {
+        NLOperation::SizeOf(nl_type) => Some(("sizeof", nl_type)),
+        NLOperation::AlignOf(nl_type) => Some(("alignof", nl_type)),
+        _ => None,
+    }
+}
+
+// Replaces every `sizeof(T)` and `alignof(T)` in the file with the number it stands for. Gives back an error for each
+// one asking about a type that can't be sized.
+pub(super) fn expand_type_sizes(file: &mut NLFile) -> Vec<String> {
+    // The structs can't be looked at while the bodies are being changed, so the answers are worked out first.
+    let mut sizes: Vec<(NLType, (usize, usize))> = vec![];
+    let mut errors = vec![];
+    let mut sizer = Sizer {
+        structs: &file.structs,
+        sizing: vec![],
+    };
+    for (body, block) in file.bodies() {
+        block.walk(&mut |operation| {
+            let (query, nl_type) = match type_query(operation) {
+                Some(query) => query,
+                None => return,
+            };
+            match sizer.size_and_alignment(nl_type) {
+                Ok(size) => sizes.push((nl_type.clone(), size)),
+                Err(found) => errors.push(match found.field {
+                    Some((structure, field)) => format!(
+                        "{} in `{}` can't size `{}`, since field `{}` of `{}` is `{}`, which {}",
+                        query,
+                        body,
+                        type_text(nl_type),
+                        field,
+                        structure,
+                        found.nl_type,
+                        found.reason
+                    ),
+                    None => format!(
+                        "{} in `{}` can't size `{}`, which {}",
+                        query, body, found.nl_type, found.reason
+                    ),
+                }),
+            }
+        });
+    }
+
+    let answer = |operation: &NLOperation| {
+        let (query, nl_type) = type_query(operation)?;
+        let (size, alignment) = sizes.iter().find(|(sized, _)| sized == nl_type)?.1;
+        let answer = if query == "sizeof" { size } else { alignment };
+        Some(NLOperation::Constant(OpConstant::Unsigned(
+            answer as u64,
+            NLType::U64,
+        )))
+    };
+    for (_, block) in file.bodies_mut() {
+        block.walk_mut(&mut |operation| {
+            if let Some(constant) = answer(operation) {
+                *operation = constant;
+            }
+        });
+    }
+
+    errors
+}
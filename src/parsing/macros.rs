@@ -0,0 +1,254 @@
+// Expands macros once the whole file has been read. A call to a macro is replaced with a copy of its block, with the
+// variables given in the call swapped in for its parameters. Macros can call other macros, but not themselves. A macro
+// can't have the same name as a function, since a call couldn't say which of them it meant.
+//
+// Macros are kept hygienic by what they're allowed to do, not by renaming anything. A macro's block can only use its
+// parameters and the variables it declares itself, so what it does can't depend on the variables around the call. It
+// can only be given variables, and not one with the same name as a variable it declares, so swapping them in can't
+// mix them up with its own. Nothing is renamed out of the way: code that breaks these rules is an error.
+
+use super::*;
+
+// The variables a block declares for itself: `let` variables, including those given values by a pattern, for loop
+// variables and those bound by match branches.
+fn declared_names<'a>(block: &NLBlock<'a>) -> Vec<&'a str> {
+    let mut names = vec![];
+    block.walk(&mut |operation| match operation {
+        NLOperation::Assign(assignment) if assignment.is_new => {
+            names.extend(assignment.to_assign.iter().map(|variable| variable.name))
+        }
+        NLOperation::LetPattern(let_pattern) => names.extend(let_pattern.pattern.variables()),
+        NLOperation::ForLoop(for_loop) => names.push(for_loop.variable.name),
+        NLOperation::Match(nl_match) => {
+            for (branch, _) in &nl_match.branches {
+                names.extend(branch.variables());
+            }
+        }
+        NLOperation::TypeMatch(type_match) => {
+            for (branch, _) in &type_match.branches {
+                if let TypeMatchBranch::Struct(_, Some(variable)) = branch {
+                    names.push(variable);
+                }
+            }
+        }
+        _ => {}
+    });
+
+    names
+}
+
+// The variables a block reads or assigns to, including those it passes to functions.
+fn used_names<'a>(block: &NLBlock<'a>) -> Vec<&'a str> {
+    let mut names = vec![];
+    block.walk(&mut |operation| match operation {
+        NLOperation::VariableAccess(variable) => names.push(variable.name),
+        NLOperation::Assign(assignment) if !assignment.is_new => {
+            names.extend(assignment.to_assign.iter().map(|variable| variable.name))
+        }
+        _ => {}
+    });
+
+    names
+}
+
+fn check_macro(nl_macro: &NLMacro, errors: &mut Vec<String>) {
+    let declared = declared_names(&nl_macro.block);
+    let mut reported = vec![];
+
+    for name in used_names(&nl_macro.block) {
+        if !nl_macro.parameters.contains(&name)
+            && !declared.contains(&name)
+            && !reported.contains(&name)
+        {
+            reported.push(name);
+            errors.push(format!(
+                "macro `{}` uses `{}`, which isn't one of its parameters or variables",
+                nl_macro.name, name
+            ));
+        }
+    }
+}
+
+// Swaps each variable named on the left of `names` for the one on the right.
+fn rename<'a>(block: &mut NLBlock<'a>, names: &[(&'a str, &'a str)]) {
+    let renamed = |name: &mut &'a str| {
+        if let Some((_, new_name)) = names.iter().find(|(old_name, _)| old_name == name) {
+            *name = new_name;
+        }
+    };
+
+    block.walk_mut(&mut |operation| match operation {
+        NLOperation::VariableAccess(variable) => renamed(&mut variable.name),
+        NLOperation::Assign(assignment) => {
+            for variable in &mut assignment.to_assign {
+                renamed(&mut variable.name);
+            }
+        }
+        NLOperation::LetPattern(let_pattern) => {
+            for name in let_pattern.pattern.variables_mut() {
+                renamed(name);
+            }
+        }
+        NLOperation::ForLoop(for_loop) => renamed(&mut for_loop.variable.name),
+        _ => {}
+    });
+}
+
+// Where an expansion is happening, like ", while expanding `inner` from `outer` in `main`". The first entry in the
+// stack is the body the expansion started in, and the rest the macros being expanded, outermost first.
+fn backtrace(stack: &[&str]) -> String {
+    match stack.split_first() {
+        Some((body, [])) => format!(" in `{}`", body),
+        Some((body, macros)) => {
+            let macros: Vec<String> = macros
+                .iter()
+                .rev()
+                .map(|name| format!("`{}`", name))
+                .collect();
+            format!(", while expanding {} in `{}`", macros.join(" from "), body)
+        }
+        None => String::new(),
+    }
+}
+
+struct Expander<'m, 'a> {
+    macros: &'m [NLMacro<'a>],
+    errors: Vec<String>,
+}
+
+impl<'m, 'a> Expander<'m, 'a> {
+    // What a call to a macro is replaced with, or nothing if it isn't a call to one. A call that's wrong is replaced
+    // with an empty block, so that it isn't reported again when the code around it is walked.
+    fn expand_call<'s>(
+        &mut self,
+        call: &FunctionCall<'a>,
+        stack: &mut Vec<&'s str>,
+    ) -> Option<NLOperation<'a>>
+    where
+        'a: 's,
+    {
+        let nl_macro = self
+            .macros
+            .iter()
+            .find(|nl_macro| nl_macro.name == call.path)?;
+
+        if stack[1..].contains(&nl_macro.name) {
+            self.errors.push(format!(
+                "macro `{}` expands to itself{}",
+                nl_macro.name,
+                backtrace(stack)
+            ));
+            return Some(NLOperation::Block(NLBlock { operations: vec![] }));
+        }
+
+        if call.arguments.len() != nl_macro.parameters.len() {
+            let plural = if nl_macro.parameters.len() == 1 {
+                ""
+            } else {
+                "s"
+            };
+            self.errors.push(format!(
+                "macro `{}` takes {} argument{} but was given {}{}",
+                nl_macro.name,
+                nl_macro.parameters.len(),
+                plural,
+                call.arguments.len(),
+                backtrace(stack)
+            ));
+            return Some(NLOperation::Block(NLBlock { operations: vec![] }));
+        }
+
+        // Parameters are swapped for the variables given, so there's nowhere to put anything else.
+        let mut arguments = vec![];
+        for argument in &call.arguments {
+            match argument {
+                NLOperation::VariableAccess(variable) => arguments.push(variable.name),
+                _ => {
+                    self.errors.push(format!(
+                        "macro `{}` can only be given variables{}",
+                        nl_macro.name,
+                        backtrace(stack)
+                    ));
+                    return Some(NLOperation::Block(NLBlock { operations: vec![] }));
+                }
+            }
+        }
+
+        // Swapping the argument in would make the macro's own variable of the same name stand for both.
+        let declared = declared_names(&nl_macro.block);
+        for argument in &arguments {
+            if declared.contains(argument) && !nl_macro.parameters.contains(argument) {
+                self.errors.push(format!(
+                    "`{}` can't be given to macro `{}`, which has a variable of its own with that name{}",
+                    argument,
+                    nl_macro.name,
+                    backtrace(stack)
+                ));
+                return Some(NLOperation::Block(NLBlock { operations: vec![] }));
+            }
+        }
+
+        let names: Vec<(&str, &str)> = nl_macro
+            .parameters
+            .iter()
+            .copied()
+            .zip(arguments.iter().copied())
+            .collect();
+        let mut block = nl_macro.block.clone();
+        rename(&mut block, &names);
+
+        stack.push(nl_macro.name);
+        self.expand_block(&mut block, stack);
+        stack.pop();
+
+        Some(NLOperation::Block(block))
+    }
+
+    fn expand_block<'s>(&mut self, block: &mut NLBlock<'a>, stack: &mut Vec<&'s str>)
+    where
+        'a: 's,
+    {
+        block.walk_mut(&mut |operation| {
+            if let NLOperation::FunctionCall(call) = operation {
+                if let Some(expanded) = self.expand_call(call, stack) {
+                    *operation = expanded;
+                }
+            }
+        });
+    }
+}
+
+// Expands every call to a macro in the file. Gives back an error for each macro that uses variables from around the
+// call or shares its name with a function, and for each call that can't be expanded.
+pub(super) fn expand_macros(file: &mut NLFile) -> Vec<String> {
+    let macros = std::mem::take(&mut file.macros);
+
+    let mut expander = Expander {
+        macros: &macros,
+        errors: vec![],
+    };
+    for nl_macro in &macros {
+        check_macro(nl_macro, &mut expander.errors);
+        if file
+            .functions
+            .iter()
+            .any(|function| function.name == nl_macro.name)
+        {
+            expander.errors.push(format!(
+                "macro `{}` has the same name as a function, so calls to it can't say which they mean",
+                nl_macro.name
+            ));
+        }
+    }
+
+    if !macros.is_empty() {
+        for (body, block) in file.bodies_mut() {
+            expander.expand_block(block, &mut vec![body]);
+        }
+    }
+
+    let errors = expander.errors;
+    file.macros = macros;
+
+    errors
+}
@@ -0,0 +1,266 @@
+// Measures how complicated each function, method, getter and setter in a file is, so that teams can keep their code
+// within a budget. Things without a body aren't measured.
+//
+// Complexity is one more than the number of decisions in the body: each `if`, loop, `?`, `&&` and `||`, `let` with an
+// `else` block, and each branch of a match or type match after the first. Nesting is how deeply the `if`s, loops,
+// matches and catch blocks go inside each other.
+
+use super::*;
+
+/// What was measured about one function.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FunctionMetrics {
+    name: String,
+    arguments: usize,
+    complexity: usize,
+    nesting: usize,
+    operations: usize,
+}
+
+impl FunctionMetrics {
+    /// Methods, getters and setters are named with the struct or trait they belong to, like `Circle::area`.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn get_arguments(&self) -> usize {
+        self.arguments
+    }
+    pub fn get_complexity(&self) -> usize {
+        self.complexity
+    }
+    pub fn get_nesting(&self) -> usize {
+        self.nesting
+    }
+    /// Every operation in the body, including those inside of other operations.
+    pub fn get_operations(&self) -> usize {
+        self.operations
+    }
+}
+
+/// What was measured about a file, in the order its functions were declared.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FileMetrics {
+    functions: Vec<FunctionMetrics>,
+}
+
+impl FileMetrics {
+    pub fn get_functions(&self) -> &[FunctionMetrics] {
+        &self.functions
+    }
+    pub fn get_total_complexity(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|function| function.complexity)
+            .sum()
+    }
+    pub fn get_max_complexity(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|function| function.complexity)
+            .max()
+            .unwrap_or(0)
+    }
+    pub fn get_max_nesting(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|function| function.nesting)
+            .max()
+            .unwrap_or(0)
+    }
+    pub fn get_total_operations(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|function| function.operations)
+            .sum()
+    }
+
+    /// The metrics as a JSON object, with a `functions` list and the `totals` for the file.
+    pub fn to_json(&self) -> String {
+        let functions: Vec<String> = self
+            .functions
+            .iter()
+            .map(|function| {
+                format!(
+                    "{{\"name\":\"{}\",\"arguments\":{},\"complexity\":{},\"nesting\":{},\"operations\":{}}}",
+                    function.name.replace('\\', "\\\\").replace('"', "\\\""),
+                    function.arguments,
+                    function.complexity,
+                    function.nesting,
+                    function.operations
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"functions\":[{}],\"totals\":{{\"functions\":{},\"complexity\":{},\"max_complexity\":{},\"max_nesting\":{},\"operations\":{}}}}}",
+            functions.join(","),
+            self.functions.len(),
+            self.get_total_complexity(),
+            self.get_max_complexity(),
+            self.get_max_nesting(),
+            self.get_total_operations()
+        )
+    }
+
+    /// The metrics as a table to be read in a terminal, one function to a line with the totals at the bottom.
+    pub fn to_table(&self) -> String {
+        let total = format!("total ({})", self.functions.len());
+        let width = self
+            .functions
+            .iter()
+            .map(|function| function.name.chars().count())
+            .chain(std::iter::once(total.len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut table = format!(
+            "{:width$}  arguments  complexity  nesting  operations\n",
+            "function",
+            width = width
+        );
+        for function in &self.functions {
+            table.push_str(&format!(
+                "{:width$}  {:>9}  {:>10}  {:>7}  {:>10}\n",
+                function.name,
+                function.arguments,
+                function.complexity,
+                function.nesting,
+                function.operations,
+                width = width
+            ));
+        }
+        // The nesting of a file is its deepest function rather than a sum.
+        table.push_str(&format!(
+            "{:width$}  {:>9}  {:>10}  {:>7}  {:>10}\n",
+            total,
+            "",
+            self.get_total_complexity(),
+            self.get_max_nesting(),
+            self.get_total_operations(),
+            width = width
+        ));
+
+        table
+    }
+}
+
+fn nests(operation: &NLOperation) -> bool {
+    matches!(
+        operation,
+        NLOperation::If(_)
+            | NLOperation::Loop(_)
+            | NLOperation::WhileLoop(_)
+            | NLOperation::ForLoop(_)
+            | NLOperation::Match(_)
+            | NLOperation::TypeMatch(_)
+            | NLOperation::Catch(_)
+    )
+}
+
+// How deeply the operations that nest go inside this one, counting it if it nests too.
+fn nesting(operation: &NLOperation) -> usize {
+    let mut deepest = 0;
+    let mut is_self = true;
+    operation.walk(&mut |inner| {
+        if is_self {
+            is_self = false;
+        } else if nests(inner) {
+            deepest = deepest.max(nesting(inner));
+        }
+    });
+
+    deepest + nests(operation) as usize
+}
+
+fn decisions(operation: &NLOperation) -> usize {
+    match operation {
+        NLOperation::If(_)
+        | NLOperation::Loop(_)
+        | NLOperation::WhileLoop(_)
+        | NLOperation::ForLoop(_)
+        | NLOperation::Operator(OpOperator::LogicalAnd(_))
+        | NLOperation::Operator(OpOperator::LogicalOr(_))
+        | NLOperation::Operator(OpOperator::PropError(_)) => 1,
+        NLOperation::LetPattern(let_pattern) if let_pattern.else_block.is_some() => 1,
+        NLOperation::Match(nl_match) => nl_match.branches.len().saturating_sub(1),
+        NLOperation::TypeMatch(type_match) => type_match.branches.len().saturating_sub(1),
+        _ => 0,
+    }
+}
+
+fn measure_body(name: String, arguments: &[NLArgument], block: &NLBlock) -> FunctionMetrics {
+    let mut complexity = 1;
+    let mut operations = 0;
+    block.walk(&mut |operation| {
+        complexity += decisions(operation);
+        operations += 1;
+    });
+
+    FunctionMetrics {
+        name,
+        arguments: arguments.len(),
+        complexity,
+        nesting: block.operations.iter().map(nesting).max().unwrap_or(0),
+        operations,
+    }
+}
+
+fn measure_implementors(
+    owner: &str,
+    implementors: &[NLImplementor],
+    functions: &mut Vec<FunctionMetrics>,
+) {
+    for implementor in implementors {
+        let (name, arguments, block) = match implementor {
+            NLImplementor::Method(method) => {
+                (method.name, &method.arguments, method.block.as_ref())
+            }
+            NLImplementor::Getter(getter) => match &getter.block {
+                NLEncapsulationBlock::Some(block) => {
+                    (getter.name.as_str(), &getter.args, Some(block))
+                }
+                _ => continue,
+            },
+            NLImplementor::Setter(setter) => match &setter.block {
+                NLEncapsulationBlock::Some(block) => (setter.name, &setter.args, Some(block)),
+                _ => continue,
+            },
+        };
+
+        if let Some(block) = block {
+            functions.push(measure_body(
+                format!("{}::{}", owner, name),
+                arguments,
+                block,
+            ));
+        }
+    }
+}
+
+/// Measures every function, method, getter and setter in the file that has a body.
+pub fn measure(file: &NLFile) -> FileMetrics {
+    let mut functions = vec![];
+    for function in &file.functions {
+        if let Some(block) = &function.block {
+            functions.push(measure_body(
+                function.name.to_string(),
+                &function.arguments,
+                block,
+            ));
+        }
+    }
+    for nl_trait in &file.traits {
+        measure_implementors(nl_trait.name, &nl_trait.implementors, &mut functions);
+    }
+    for nl_struct in &file.structs {
+        for implementation in &nl_struct.implementations {
+            measure_implementors(nl_struct.name, &implementation.implementors, &mut functions);
+        }
+    }
+    for implementation in &file.implementations {
+        let owner = implementation.target.unwrap_or(implementation.name);
+        measure_implementors(owner, &implementation.implementors, &mut functions);
+    }
+
+    FileMetrics { functions }
+}
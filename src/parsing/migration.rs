@@ -0,0 +1,81 @@
+// Rewrites a file written for an older version of the language so that it declares the latest version and still means
+// the same thing. Changes are made to the source text itself, so everything that didn't need to change, comments and
+// layout included, is left exactly as it was.
+
+use super::pragmas::Pragmas;
+use super::*;
+use crate::diagnostics::apply_edits;
+
+/// A file rewritten for the latest version of the language.
+#[derive(Debug)]
+pub struct Migration {
+    source: String,
+    unmigrated: Vec<String>,
+}
+
+impl Migration {
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// Everything in the file that means something else in the latest version and couldn't be rewritten, so has to be
+    /// looked at by hand.
+    pub fn get_unmigrated(&self) -> &[String] {
+        &self.unmigrated
+    }
+}
+
+/// Migrates `input` to `LanguageVersion::LATEST`. Files that don't declare a version are read as
+/// `options.language_version`. A file that's already on the latest version is given back unchanged.
+pub fn migrate(
+    input: &str,
+    file_name: &str,
+    options: &ParseOptions,
+) -> Result<Migration, ParseError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+    // Anything wrong with the file is in the error, so there's nothing to report elsewhere.
+    let (file, upgrade_notes) =
+        parse_string_with_upgrade_notes(input, file_name, options, &mut Vec::new())?;
+
+    if file.get_language_version() == LanguageVersion::LATEST {
+        return Ok(Migration {
+            source: input.to_string(),
+            unmigrated: vec![],
+        });
+    }
+
+    let mut edits = vec![];
+    let mut unmigrated = vec![];
+
+    let latest = format!("\"{}\"", LanguageVersion::LATEST.get_name());
+    match pragmas::read_pragmas(input) {
+        Ok((
+            _,
+            Pragmas {
+                version_literal: Some(literal),
+                ..
+            },
+        )) => {
+            let offset = literal.as_ptr() as usize - input.as_ptr() as usize;
+            edits.push(TextEdit::new(offset, literal.len(), latest));
+        }
+        _ => edits.push(TextEdit::new(
+            0,
+            0,
+            format!("#![nl_version = {}]\n", latest),
+        )),
+    }
+
+    for note in upgrade_notes {
+        match note.fix {
+            Some((offset, text)) => edits.push(TextEdit::new(offset, 0, text.to_string())),
+            None => unmigrated.push(format!("{}: {}", file_name, note)),
+        }
+    }
+
+    Ok(Migration {
+        source: apply_edits(input, &edits),
+        unmigrated,
+    })
+}
@@ -1,29 +1,63 @@
+use crate::diagnostics::{Diagnostic, DiagnosticSink, TextEdit};
 use nom::Err as NomErr;
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_until, take_while, take_while1, is_not, take_while_m_n},
     character::{
-        complete::{alpha1, alphanumeric0, alphanumeric1, char, digit1, multispace0, one_of, multispace1},
+        complete::{
+            alpha1, alphanumeric0, alphanumeric1, char, digit1, multispace0, multispace1, none_of,
+            one_of, satisfy, space0,
+        },
         is_alphanumeric,
     },
-    combinator::{opt, recognize, value, map, verify, map_res, map_opt},
+    combinator::{opt, recognize, value, map, verify, map_res, map_opt, not, peek},
     error::{convert_error, FromExternalError, VerboseError, VerboseErrorKind},
-    multi::{many0, many0_count, many1, fold_many0},
+    multi::{many0, many0_count, many1, fold_many0, separated_list0, separated_list1},
     sequence::tuple,
     sequence::{delimited, preceded, terminated},
     IResult,
 };
-use std::{fmt::Formatter, fs::File, io::Read, path::Path, str::FromStr};
+use std::{
+    fmt::Formatter,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 // All tests are kept in their own module.
 #[cfg(test)]
 mod tests;
 
+mod instrumentation;
+use instrumentation::traced;
+
+pub mod layout;
+use layout::{ByteOrder, Repr};
+
+pub mod values;
+
+mod reflection;
+
+pub(crate) mod enums;
+
+mod macros;
+
+pub mod pragmas;
+use pragmas::{Feature, LanguageVersion, Lint, LintLevel, UpgradeNote};
+
+pub mod migration;
+
+pub mod includes;
+
+pub mod metrics;
+
+pub mod duplicates;
+
 pub type ParserResult<'a, O> = IResult<&'a str, O, VerboseError<&'a str>>;
 
 // TODO replace all the getters with reference handles and mut_handles.
 
-#[derive(PartialOrd, PartialEq, Debug, Clone)]
+#[derive(PartialOrd, PartialEq, Debug, Hash, Clone)]
 pub enum NLType<'a> {
     None,
     Boolean,
@@ -39,7 +73,10 @@ pub enum NLType<'a> {
     F64,
     OwnedString,
     BorrowedString,
+    Bytes, // `bytes`, an owned array of bytes.
     Tuple(Vec<NLType<'a>>),
+    List(Box<NLType<'a>>),
+    Map(Box<NLType<'a>>, Box<NLType<'a>>),
     OwnedStruct(&'a str),
     ReferencedStruct(&'a str),
     MutableReferencedStruct(&'a str),
@@ -49,6 +86,12 @@ pub enum NLType<'a> {
     Enum(&'a str),
     SelfReference,
     MutableSelfReference,
+    Pointer(Box<NLType<'a>>),
+    MutablePointer(Box<NLType<'a>>),
+    Shared(Box<NLType<'a>>), // `rc T`, a value with as many owners as there are copies of it.
+    Weak(Box<NLType<'a>>),   // `weak T`, refers to a shared value without keeping it alive.
+    Endian(ByteOrder, Box<NLType<'a>>), // A number stored in a fixed byte order, like `u32_be`.
+    Char,                    // `char`, a single Unicode character.
 }
 
 impl<'a> NLType<'a> {
@@ -117,9 +160,11 @@ impl<'a> NLType<'a> {
     }
 }
 
+#[derive(Hash)]
 pub struct NLStructVariable<'a> {
-    name: &'a str,
-    my_type: NLType<'a>,
+    pub(crate) name: &'a str,
+    pub(crate) my_type: NLType<'a>,
+    bit_fields: Vec<NLBitField<'a>>,
 }
 
 impl<'a> NLStructVariable<'a> {
@@ -129,12 +174,35 @@ impl<'a> NLStructVariable<'a> {
     pub fn get_type(&self) -> &NLType {
         &self.my_type
     }
+    /// The named bits this variable is split into, if it was declared like `flags: u8 { ready: 1, mode: 3 }`.
+    pub fn get_bit_fields(&self) -> &Vec<NLBitField<'a>> {
+        &self.bit_fields
+    }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
-pub struct NLArgument<'a> {
+#[derive(PartialOrd, PartialEq, Debug, Hash)]
+pub struct NLBitField<'a> {
     name: &'a str,
-    nl_type: NLType<'a>,
+    offset: u16, // Counted in bits from the least significant bit of the variable.
+    width: u16,
+}
+
+impl<'a> NLBitField<'a> {
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+    pub fn get_offset(&self) -> u16 {
+        self.offset
+    }
+    pub fn get_width(&self) -> u16 {
+        self.width
+    }
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Hash)]
+pub struct NLArgument<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) nl_type: NLType<'a>,
 }
 
 impl<'a> NLArgument<'a> {
@@ -146,9 +214,9 @@ impl<'a> NLArgument<'a> {
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct NLBlock<'a> {
-    operations: Vec<NLOperation<'a>>,
+    pub(crate) operations: Vec<NLOperation<'a>>,
 }
 
 impl<'a> NLBlock<'a> {
@@ -157,13 +225,18 @@ impl<'a> NLBlock<'a> {
     }
 }
 
+#[derive(Hash)]
 pub struct NLFunction<'a> {
-    name: &'a str,
-    arguments: Vec<NLArgument<'a>>,
-    return_type: NLType<'a>,
-    block: Option<NLBlock<'a>>,
+    pub(crate) name: &'a str,
+    pub(crate) arguments: Vec<NLArgument<'a>>,
+    pub(crate) return_type: NLType<'a>,
+    pub(crate) block: Option<NLBlock<'a>>,
+    pub(crate) is_unsafe: bool,
+    pub(crate) is_const: bool,
+    deprecation: Option<String>,
 }
 
+#[derive(Hash)]
 pub enum NLImplementor<'a> {
     Method(NLFunction<'a>),
     Getter(NLGetter<'a>),
@@ -183,20 +256,54 @@ impl<'a> NLFunction<'a> {
     pub fn get_block(&self) -> &Option<NLBlock> {
         &self.block
     }
+    /// Declared with `unsafe fn` or `unsafe met`, so its body is an unsafe context.
+    pub fn is_unsafe(&self) -> bool {
+        self.is_unsafe
+    }
+    /// Declared with `const fn` or `const met`, so it can be run while compiling.
+    pub fn is_const(&self) -> bool {
+        self.is_const
+    }
+    /// Marked `#[deprecated]`. Holds the message given with it, which is empty when there wasn't one.
+    pub fn get_deprecation(&self) -> Option<&str> {
+        self.deprecation.as_deref()
+    }
+}
+
+/// `macro name(a, b) { ... }`. A call to it is replaced with its block once the file has been read, with the
+/// variables given in the call swapped in for its parameters.
+pub struct NLMacro<'a> {
+    name: &'a str,
+    parameters: Vec<&'a str>,
+    pub(crate) block: NLBlock<'a>,
+}
+
+impl<'a> NLMacro<'a> {
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+    pub fn get_parameters(&self) -> &Vec<&'a str> {
+        &self.parameters
+    }
+    pub fn get_block(&self) -> &NLBlock<'a> {
+        &self.block
+    }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Hash)]
 pub enum NLEncapsulationBlock<'a> {
     Some(NLBlock<'a>),
     None,
     Default,
 }
 
+#[derive(Hash)]
 pub struct NLGetter<'a> {
-    name: String,
-    args: Vec<NLArgument<'a>>,
-    nl_type: NLType<'a>,
-    block: NLEncapsulationBlock<'a>,
+    pub(crate) name: String,
+    pub(crate) args: Vec<NLArgument<'a>>,
+    pub(crate) nl_type: NLType<'a>,
+    pub(crate) block: NLEncapsulationBlock<'a>,
+    deprecation: Option<String>,
 }
 
 impl<'a> NLGetter<'a> {
@@ -212,12 +319,16 @@ impl<'a> NLGetter<'a> {
     pub fn get_block(&self) -> &NLEncapsulationBlock {
         &self.block
     }
+    pub fn get_deprecation(&self) -> Option<&str> {
+        self.deprecation.as_deref()
+    }
 }
 
+#[derive(Hash)]
 pub struct NLSetter<'a> {
-    name: &'a str,
-    args: Vec<NLArgument<'a>>,
-    block: NLEncapsulationBlock<'a>,
+    pub(crate) name: &'a str,
+    pub(crate) args: Vec<NLArgument<'a>>,
+    pub(crate) block: NLEncapsulationBlock<'a>,
 }
 
 impl<'a> NLSetter<'a> {
@@ -232,10 +343,13 @@ impl<'a> NLSetter<'a> {
     }
 }
 
+#[derive(Hash)]
 pub struct NLStruct<'a> {
-    name: &'a str,
-    variables: Vec<NLStructVariable<'a>>,
-    implementations: Vec<NLImplementation<'a>>,
+    pub(crate) name: &'a str,
+    repr: Repr,
+    serialization: Option<SerialFormat>,
+    pub(crate) variables: Vec<NLStructVariable<'a>>,
+    pub(crate) implementations: Vec<NLImplementation<'a>>,
 }
 
 impl<'a> NLStruct<'a> {
@@ -248,11 +362,27 @@ impl<'a> NLStruct<'a> {
     pub fn get_implementations(&self) -> &Vec<NLImplementation> {
         &self.implementations
     }
+    /// Set with `#[serializable(format = "...")]`.
+    pub fn get_serialization(&self) -> Option<SerialFormat> {
+        self.serialization
+    }
+}
+
+/// How a struct marked `#[serializable]` is written out.
+#[derive(PartialEq, Debug, Hash, Clone, Copy)]
+pub enum SerialFormat {
+    Binary,
 }
 
+enum StructAttribute {
+    Repr(Repr),
+    Serializable(SerialFormat),
+}
+
+#[derive(Hash)]
 pub struct NLTrait<'a> {
-    name: &'a str,
-    implementors: Vec<NLImplementor<'a>>,
+    pub(crate) name: &'a str,
+    pub(crate) implementors: Vec<NLImplementor<'a>>,
 }
 
 impl<'a> NLTrait<'a> {
@@ -264,24 +394,32 @@ impl<'a> NLTrait<'a> {
     }
 }
 
+#[derive(Hash)]
 pub struct NLImplementation<'a> {
-    name: &'a str,
-    implementors: Vec<NLImplementor<'a>>,
+    pub(crate) name: &'a str,
+    pub(crate) target: Option<&'a str>,
+    pub(crate) implementors: Vec<NLImplementor<'a>>,
 }
 
 impl<'a> NLImplementation<'a> {
     pub fn get_name(&self) -> &str {
         &self.name
     }
+    /// The struct named by `impl Trait for Struct`. Implementations written directly after their struct have none.
+    pub fn get_target(&self) -> Option<&str> {
+        self.target
+    }
     pub fn get_implementors(&self) -> &Vec<NLImplementor> {
         &self.implementors
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Hash)]
 pub struct EnumVariant<'a> {
-    name: &'a str,
-    arguments: Vec<NLArgument<'a>>,
+    pub(crate) name: &'a str,
+    // A variant names what it holds like `Circle(radius: f32)`, or only gives the types like `Circle(f32)`.
+    pub(crate) arguments: Vec<NLArgument<'a>>,
+    pub(crate) payload: Vec<NLType<'a>>,
 }
 
 impl<'a> EnumVariant<'a> {
@@ -292,11 +430,25 @@ impl<'a> EnumVariant<'a> {
     pub fn get_arguments(&self) -> &Vec<NLArgument<'a>> {
         &self.arguments
     }
+
+    /// The types of the values the variant holds, in order, whether or not they were given names.
+    pub fn get_payload(&self) -> Vec<&NLType<'a>> {
+        if self.arguments.is_empty() {
+            self.payload.iter().collect()
+        } else {
+            self.arguments
+                .iter()
+                .map(|argument| &argument.nl_type)
+                .collect()
+        }
+    }
 }
 
+#[derive(Hash)]
 pub struct NLEnum<'a> {
-    name: &'a str,
-    variants: Vec<EnumVariant<'a>>,
+    pub(crate) name: &'a str,
+    pub(crate) variants: Vec<EnumVariant<'a>>,
+    pub(crate) is_error: bool,
 }
 
 impl<'a> NLEnum<'a> {
@@ -307,6 +459,11 @@ impl<'a> NLEnum<'a> {
     pub fn get_variants(&self) -> &Vec<EnumVariant> {
         &self.variants
     }
+
+    /// Declared with `error` rather than `enum`, like `error FileError { NotFound, Denied(str) }`.
+    pub fn is_error(&self) -> bool {
+        self.is_error
+    }
 }
 
 enum RootDeceleration<'a> {
@@ -314,22 +471,51 @@ enum RootDeceleration<'a> {
     Trait(NLTrait<'a>),
     Function(NLFunction<'a>),
     Enum(NLEnum<'a>),
+    Implementation(NLImplementation<'a>),
+    Macro(NLMacro<'a>),
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone)]
 pub enum OpConstant<'a> {
     Boolean(bool),
     Unsigned(u64, NLType<'a>),
     Signed(i64, NLType<'a>),
     Float32(f32),
     Float64(f64),
+    Bytes(Vec<u8>), // From `b"text"` or `x"DEADBEEF"`.
     String(String),
+    // From `'c'` or an escape like `'\n'`.
+    Char(char),
     // TODO add support for defining a constant enum.
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+impl<'a> Hash for OpConstant<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            OpConstant::Boolean(value) => value.hash(state),
+            OpConstant::Unsigned(value, nl_type) => {
+                value.hash(state);
+                nl_type.hash(state);
+            }
+            OpConstant::Signed(value, nl_type) => {
+                value.hash(state);
+                nl_type.hash(state);
+            }
+            // Floats can't be hashed directly, but their bit patterns can.
+            OpConstant::Float32(value) => value.to_bits().hash(state),
+            OpConstant::Float64(value) => value.to_bits().hash(state),
+            OpConstant::String(value) => value.hash(state),
+            OpConstant::Bytes(value) => value.hash(state),
+            OpConstant::Char(value) => value.hash(state),
+        }
+    }
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct OpVariable<'a> {
-    name: &'a str,
+    pub(crate) name: &'a str,
 }
 
 impl<'a> OpVariable<'a> {
@@ -338,12 +524,12 @@ impl<'a> OpVariable<'a> {
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct OpAssignment<'a> {
-    is_new: bool,
-    to_assign: Vec<OpVariable<'a>>,
-    type_assignments: Vec<NLType<'a>>,
-    assignment: Box<NLOperation<'a>>,
+    pub(crate) is_new: bool,
+    pub(crate) to_assign: Vec<OpVariable<'a>>,
+    pub(crate) type_assignments: Vec<NLType<'a>>,
+    pub(crate) assignment: Box<NLOperation<'a>>,
 }
 
 impl<'a> OpAssignment<'a> {
@@ -361,7 +547,54 @@ impl<'a> OpAssignment<'a> {
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub(crate) enum LetPattern<'a> {
+    Variable(&'a str),
+    // `Point { x, y: Vector { dx, dy } }`. A field written on its own gives its value to a variable of the same name.
+    Struct(&'a str, Vec<(&'a str, LetPattern<'a>)>),
+    // `Shape::Circle(radius)`.
+    Variant(&'a str, &'a str, Vec<LetPattern<'a>>),
+}
+
+impl<'a> LetPattern<'a> {
+    pub(crate) fn variables(&self) -> Vec<&'a str> {
+        match self {
+            LetPattern::Variable(name) => vec![*name],
+            LetPattern::Struct(_, fields) => fields
+                .iter()
+                .flat_map(|(_, pattern)| pattern.variables())
+                .collect(),
+            LetPattern::Variant(_, _, patterns) => patterns
+                .iter()
+                .flat_map(|pattern| pattern.variables())
+                .collect(),
+        }
+    }
+
+    fn variables_mut(&mut self) -> Vec<&mut &'a str> {
+        match self {
+            LetPattern::Variable(name) => vec![name],
+            LetPattern::Struct(_, fields) => fields
+                .iter_mut()
+                .flat_map(|(_, pattern)| pattern.variables_mut())
+                .collect(),
+            LetPattern::Variant(_, _, patterns) => patterns
+                .iter_mut()
+                .flat_map(|pattern| pattern.variables_mut())
+                .collect(),
+        }
+    }
+}
+
+// `let Point { x, y } = point`, or `let Shape::Circle(radius) = shape else { break }` when the pattern might not match.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct OpLetPattern<'a> {
+    pub(crate) pattern: LetPattern<'a>,
+    pub(crate) assignment: Box<NLOperation<'a>>,
+    pub(crate) else_block: Option<NLBlock<'a>>,
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub enum OpOperator<'a> {
     CompareEqual((Box<NLOperation<'a>>, Box<NLOperation<'a>>)),
     CompareNotEqual((Box<NLOperation<'a>>, Box<NLOperation<'a>>)),
@@ -395,84 +628,553 @@ pub enum OpOperator<'a> {
     ArithmeticDiv((Box<NLOperation<'a>>, Box<NLOperation<'a>>)),
 
     Range((Box<NLOperation<'a>>, Box<NLOperation<'a>>)),
+
+    AddressOf(Box<NLOperation<'a>>),
+    Dereference(Box<NLOperation<'a>>),
+}
+
+impl<'a> OpOperator<'a> {
+    // What the operator is applied to, left first.
+    pub(crate) fn operands(&self) -> Vec<&NLOperation<'a>> {
+        match self {
+            OpOperator::LogicalNegate(operand)
+            | OpOperator::ArithmeticNegate(operand)
+            | OpOperator::BitNegate(operand)
+            | OpOperator::PropError(operand)
+            | OpOperator::AddressOf(operand)
+            | OpOperator::Dereference(operand) => vec![operand],
+            OpOperator::CompareEqual((left, right))
+            | OpOperator::CompareNotEqual((left, right))
+            | OpOperator::CompareGreater((left, right))
+            | OpOperator::CompareLess((left, right))
+            | OpOperator::CompareGreaterEqual((left, right))
+            | OpOperator::CompareLessEqual((left, right))
+            | OpOperator::LogicalAnd((left, right))
+            | OpOperator::LogicalOr((left, right))
+            | OpOperator::LogicalXor((left, right))
+            | OpOperator::BitAnd((left, right))
+            | OpOperator::BitOr((left, right))
+            | OpOperator::BitXor((left, right))
+            | OpOperator::BitLeftShift((left, right))
+            | OpOperator::BitRightShift((left, right))
+            | OpOperator::ArithmeticMod((left, right))
+            | OpOperator::ArithmeticAdd((left, right))
+            | OpOperator::ArithmeticSub((left, right))
+            | OpOperator::ArithmeticMul((left, right))
+            | OpOperator::ArithmeticDiv((left, right))
+            | OpOperator::Range((left, right)) => vec![left, right],
+        }
+    }
+}
+
+// `collection[index]`. A slice is an index by a range, like `data[1 .. 3]`.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct OpIndex<'a> {
+    pub(crate) collection: Box<NLOperation<'a>>,
+    pub(crate) index: Box<NLOperation<'a>>,
+}
+
+// `structure.field`.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct OpFieldAccess<'a> {
+    pub(crate) structure: Box<NLOperation<'a>>,
+    pub(crate) field: &'a str,
+}
+
+// `receiver.method(arguments)`.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct OpMethodCall<'a> {
+    pub(crate) receiver: Box<NLOperation<'a>>,
+    pub(crate) method: &'a str,
+    pub(crate) arguments: Vec<NLOperation<'a>>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct IfStatement<'a> {
-    condition: Box<NLOperation<'a>>,
-    true_block: NLBlock<'a>,
-    false_block: NLBlock<'a>,
+    pub(crate) condition: Box<NLOperation<'a>>,
+    pub(crate) true_block: NLBlock<'a>,
+    pub(crate) false_block: NLBlock<'a>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct WhileLoop<'a> {
-    condition: Box<NLOperation<'a>>,
-    block: NLBlock<'a>,
+    pub(crate) condition: Box<NLOperation<'a>>,
+    pub(crate) block: NLBlock<'a>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct ForLoop<'a> {
-    variable: OpVariable<'a>,
-    iterator: Box<NLOperation<'a>>,
-    block: NLBlock<'a>,
+    pub(crate) variable: OpVariable<'a>,
+    pub(crate) iterator: Box<NLOperation<'a>>,
+    pub(crate) block: NLBlock<'a>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
-struct MatchEnumBranch<'a> {
-    nl_enum: &'a str,
-    variant: &'a str,
-    variables: Vec<&'a str>,
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub(crate) struct MatchEnumBranch<'a> {
+    pub(crate) nl_enum: &'a str,
+    pub(crate) variant: &'a str,
+    pub(crate) variables: Vec<&'a str>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
-enum MatchBranch<'a> {
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub(crate) enum MatchBranch<'a> {
     Enum(MatchEnumBranch<'a>),
     Constant(OpConstant<'a>),
     Range((i128, i128)),
-    AllOther, // TODO implement.
+    // `_`, which matches anything.
+    AllOther,
+    // `1 | 2 | 3`, which matches when any of them do.
+    Or(Vec<MatchBranch<'a>>),
+    // `name @ pattern`, which gives the whole value a name when the pattern matches.
+    Binding(&'a str, Box<MatchBranch<'a>>),
+    // `pattern if condition`, which only matches when the condition is also true.
+    Guarded(Box<MatchBranch<'a>>, Box<NLOperation<'a>>),
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+impl<'a> MatchBranch<'a> {
+    // What has to match, without any guard.
+    pub(crate) fn pattern(&self) -> &MatchBranch<'a> {
+        match self {
+            MatchBranch::Guarded(pattern, _) => pattern.pattern(),
+            pattern => pattern,
+        }
+    }
+
+    // The variables the pattern gives values to. Every pattern in an `Or` gives values to the same ones.
+    pub(crate) fn variables(&self) -> Vec<&'a str> {
+        match self {
+            MatchBranch::Enum(branch) => branch.variables.clone(),
+            MatchBranch::Or(patterns) => patterns
+                .first()
+                .map_or_else(Vec::new, |pattern| pattern.variables()),
+            MatchBranch::Binding(name, pattern) => {
+                let mut variables = vec![*name];
+                variables.extend(pattern.variables());
+                variables
+            }
+            MatchBranch::Guarded(pattern, _) => pattern.variables(),
+            MatchBranch::Constant(_) | MatchBranch::Range(_) | MatchBranch::AllOther => vec![],
+        }
+    }
+
+    pub(crate) fn guard(&self) -> Option<&NLOperation<'a>> {
+        match self {
+            MatchBranch::Guarded(_, guard) => Some(guard),
+            _ => None,
+        }
+    }
+
+    fn guard_mut(&mut self) -> Option<&mut NLOperation<'a>> {
+        match self {
+            MatchBranch::Guarded(_, guard) => Some(guard),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct Match<'a> {
-    input: Box<NLOperation<'a>>,
-    branches: Vec<(MatchBranch<'a>, NLOperation<'a>)>,
+    pub(crate) input: Box<NLOperation<'a>>,
+    pub(crate) branches: Vec<(MatchBranch<'a>, NLOperation<'a>)>,
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub(crate) enum TypeMatchBranch<'a> {
+    // `Circle(circle)`, which matches when the value is a `Circle` and gives it to `circle` as one.
+    Struct(&'a str, Option<&'a str>),
+    AllOther,
+}
+
+// `match type shape { ... }`, which picks a branch by the struct a trait value really is.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct TypeMatch<'a> {
+    pub(crate) input: Box<NLOperation<'a>>,
+    pub(crate) branches: Vec<(TypeMatchBranch<'a>, NLOperation<'a>)>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+// `Point { x: 1, y: 2 }`, with the fields in the order they were written.
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
+pub struct OpStructConstruction<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) fields: Vec<(&'a str, NLOperation<'a>)>,
+}
+
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub struct FunctionCall<'a> {
-    path: &'a str,
-    arguments: Vec<&'a str>,
+    pub(crate) path: &'a str,
+    pub(crate) arguments: Vec<NLOperation<'a>>,
 }
 
-#[derive(PartialOrd, PartialEq, Debug)]
+#[derive(PartialOrd, PartialEq, Debug, Clone, Hash)]
 pub enum NLOperation<'a> {
     Block(NLBlock<'a>),
     Constant(OpConstant<'a>),
     Assign(OpAssignment<'a>),
+    LetPattern(OpLetPattern<'a>),
     VariableAccess(OpVariable<'a>),
     Tuple(Vec<NLOperation<'a>>),
+    List(Vec<NLOperation<'a>>),
     Operator(OpOperator<'a>),
+    Index(OpIndex<'a>),
+    FieldAccess(OpFieldAccess<'a>),
+    MethodCall(OpMethodCall<'a>),
     If(IfStatement<'a>),
     Loop(NLBlock<'a>),
     WhileLoop(WhileLoop<'a>),
     ForLoop(ForLoop<'a>),
-    Break,
+    Break(Option<Box<NLOperation<'a>>>), // What the loop gives back, if anything.
+    Continue,
+    Return(Option<Box<NLOperation<'a>>>), // What the function gives back, if anything.
+    Catch(NLBlock<'a>),
+    Unsafe(NLBlock<'a>),
+    SizeOf(NLType<'a>),
+    AlignOf(NLType<'a>),
+    // These are replaced with constants once the whole file has been read.
+    NameOf(NLType<'a>),
+    FieldsOf(NLType<'a>),
+    VariantsOf(NLType<'a>),
     Match(Match<'a>),
+    TypeMatch(TypeMatch<'a>),
     FunctionCall(FunctionCall<'a>),
+    StructConstruction(OpStructConstruction<'a>),
+}
+
+impl<'a> NLOperation<'a> {
+    /// Calls `visitor` with this operation and then with every operation inside of it, parents before children.
+    pub fn walk<'b>(&'b self, visitor: &mut dyn FnMut(&'b NLOperation<'a>)) {
+        visitor(self);
+
+        match self {
+            NLOperation::Block(block)
+            | NLOperation::Loop(block)
+            | NLOperation::Catch(block)
+            | NLOperation::Unsafe(block) => block.walk(visitor),
+            NLOperation::Assign(assignment) => assignment.assignment.walk(visitor),
+            NLOperation::LetPattern(let_pattern) => {
+                let_pattern.assignment.walk(visitor);
+                if let Some(block) = &let_pattern.else_block {
+                    block.walk(visitor);
+                }
+            }
+            NLOperation::Index(index) => {
+                index.collection.walk(visitor);
+                index.index.walk(visitor);
+            }
+            NLOperation::FieldAccess(access) => access.structure.walk(visitor),
+            NLOperation::MethodCall(call) => {
+                call.receiver.walk(visitor);
+                for argument in &call.arguments {
+                    argument.walk(visitor);
+                }
+            }
+            NLOperation::Tuple(items) | NLOperation::List(items) => {
+                for item in items {
+                    item.walk(visitor);
+                }
+            }
+            NLOperation::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    argument.walk(visitor);
+                }
+            }
+            NLOperation::StructConstruction(construction) => {
+                for (_, value) in &construction.fields {
+                    value.walk(visitor);
+                }
+            }
+            NLOperation::Operator(operator) => match operator {
+                OpOperator::LogicalNegate(operand)
+                | OpOperator::ArithmeticNegate(operand)
+                | OpOperator::BitNegate(operand)
+                | OpOperator::PropError(operand)
+                | OpOperator::AddressOf(operand)
+                | OpOperator::Dereference(operand) => operand.walk(visitor),
+                OpOperator::CompareEqual((left, right))
+                | OpOperator::CompareNotEqual((left, right))
+                | OpOperator::CompareGreater((left, right))
+                | OpOperator::CompareLess((left, right))
+                | OpOperator::CompareGreaterEqual((left, right))
+                | OpOperator::CompareLessEqual((left, right))
+                | OpOperator::LogicalAnd((left, right))
+                | OpOperator::LogicalOr((left, right))
+                | OpOperator::LogicalXor((left, right))
+                | OpOperator::BitAnd((left, right))
+                | OpOperator::BitOr((left, right))
+                | OpOperator::BitXor((left, right))
+                | OpOperator::BitLeftShift((left, right))
+                | OpOperator::BitRightShift((left, right))
+                | OpOperator::ArithmeticMod((left, right))
+                | OpOperator::ArithmeticAdd((left, right))
+                | OpOperator::ArithmeticSub((left, right))
+                | OpOperator::ArithmeticMul((left, right))
+                | OpOperator::ArithmeticDiv((left, right))
+                | OpOperator::Range((left, right)) => {
+                    left.walk(visitor);
+                    right.walk(visitor);
+                }
+            },
+            NLOperation::If(statement) => {
+                statement.condition.walk(visitor);
+                statement.true_block.walk(visitor);
+                statement.false_block.walk(visitor);
+            }
+            NLOperation::WhileLoop(while_loop) => {
+                while_loop.condition.walk(visitor);
+                while_loop.block.walk(visitor);
+            }
+            NLOperation::ForLoop(for_loop) => {
+                for_loop.iterator.walk(visitor);
+                for_loop.block.walk(visitor);
+            }
+            NLOperation::Match(nl_match) => {
+                nl_match.input.walk(visitor);
+                for (pattern, branch) in &nl_match.branches {
+                    if let Some(guard) = pattern.guard() {
+                        guard.walk(visitor);
+                    }
+                    branch.walk(visitor);
+                }
+            }
+            NLOperation::TypeMatch(type_match) => {
+                type_match.input.walk(visitor);
+                for (_, branch) in &type_match.branches {
+                    branch.walk(visitor);
+                }
+            }
+            NLOperation::Break(value) | NLOperation::Return(value) => {
+                if let Some(value) = value {
+                    value.walk(visitor);
+                }
+            }
+            NLOperation::Constant(_)
+            | NLOperation::VariableAccess(_)
+            | NLOperation::Continue
+            | NLOperation::SizeOf(_)
+            | NLOperation::AlignOf(_)
+            | NLOperation::NameOf(_)
+            | NLOperation::FieldsOf(_)
+            | NLOperation::VariantsOf(_) => {}
+        }
+    }
+
+    /// Every name written in the operation, wherever it is in it. There are no positions in the tree, so these are what
+    /// say where it is in the source text.
+    pub(crate) fn written_names(&self) -> Vec<&'a str> {
+        let mut names = vec![];
+        self.walk(&mut |operation| match operation {
+            NLOperation::VariableAccess(variable) => names.push(variable.name),
+            NLOperation::FunctionCall(call) => names.push(call.path),
+            NLOperation::MethodCall(call) => names.push(call.method),
+            NLOperation::FieldAccess(access) => names.push(access.field),
+            NLOperation::StructConstruction(construction) => names.push(construction.name),
+            NLOperation::Assign(assignment) => {
+                names.extend(assignment.to_assign.iter().map(|variable| variable.name))
+            }
+            NLOperation::ForLoop(for_loop) => names.push(for_loop.variable.name),
+            _ => {}
+        });
+
+        names
+    }
+
+    // The same as `walk`, but the visitor can change or replace the operations. Whatever an operation was replaced with
+    // is walked into afterwards.
+    fn walk_mut(&mut self, visitor: &mut dyn FnMut(&mut NLOperation<'a>)) {
+        visitor(self);
+
+        match self {
+            NLOperation::Block(block)
+            | NLOperation::Loop(block)
+            | NLOperation::Catch(block)
+            | NLOperation::Unsafe(block) => block.walk_mut(visitor),
+            NLOperation::Assign(assignment) => assignment.assignment.walk_mut(visitor),
+            NLOperation::LetPattern(let_pattern) => {
+                let_pattern.assignment.walk_mut(visitor);
+                if let Some(block) = &mut let_pattern.else_block {
+                    block.walk_mut(visitor);
+                }
+            }
+            NLOperation::Index(index) => {
+                index.collection.walk_mut(visitor);
+                index.index.walk_mut(visitor);
+            }
+            NLOperation::FieldAccess(access) => access.structure.walk_mut(visitor),
+            NLOperation::MethodCall(call) => {
+                call.receiver.walk_mut(visitor);
+                for argument in &mut call.arguments {
+                    argument.walk_mut(visitor);
+                }
+            }
+            NLOperation::Tuple(items) | NLOperation::List(items) => {
+                for item in items {
+                    item.walk_mut(visitor);
+                }
+            }
+            NLOperation::FunctionCall(call) => {
+                for argument in &mut call.arguments {
+                    argument.walk_mut(visitor);
+                }
+            }
+            NLOperation::StructConstruction(construction) => {
+                for (_, value) in &mut construction.fields {
+                    value.walk_mut(visitor);
+                }
+            }
+            NLOperation::Operator(operator) => match operator {
+                OpOperator::LogicalNegate(operand)
+                | OpOperator::ArithmeticNegate(operand)
+                | OpOperator::BitNegate(operand)
+                | OpOperator::PropError(operand)
+                | OpOperator::AddressOf(operand)
+                | OpOperator::Dereference(operand) => operand.walk_mut(visitor),
+                OpOperator::CompareEqual((left, right))
+                | OpOperator::CompareNotEqual((left, right))
+                | OpOperator::CompareGreater((left, right))
+                | OpOperator::CompareLess((left, right))
+                | OpOperator::CompareGreaterEqual((left, right))
+                | OpOperator::CompareLessEqual((left, right))
+                | OpOperator::LogicalAnd((left, right))
+                | OpOperator::LogicalOr((left, right))
+                | OpOperator::LogicalXor((left, right))
+                | OpOperator::BitAnd((left, right))
+                | OpOperator::BitOr((left, right))
+                | OpOperator::BitXor((left, right))
+                | OpOperator::BitLeftShift((left, right))
+                | OpOperator::BitRightShift((left, right))
+                | OpOperator::ArithmeticMod((left, right))
+                | OpOperator::ArithmeticAdd((left, right))
+                | OpOperator::ArithmeticSub((left, right))
+                | OpOperator::ArithmeticMul((left, right))
+                | OpOperator::ArithmeticDiv((left, right))
+                | OpOperator::Range((left, right)) => {
+                    left.walk_mut(visitor);
+                    right.walk_mut(visitor);
+                }
+            },
+            NLOperation::If(statement) => {
+                statement.condition.walk_mut(visitor);
+                statement.true_block.walk_mut(visitor);
+                statement.false_block.walk_mut(visitor);
+            }
+            NLOperation::WhileLoop(while_loop) => {
+                while_loop.condition.walk_mut(visitor);
+                while_loop.block.walk_mut(visitor);
+            }
+            NLOperation::ForLoop(for_loop) => {
+                for_loop.iterator.walk_mut(visitor);
+                for_loop.block.walk_mut(visitor);
+            }
+            NLOperation::Match(nl_match) => {
+                nl_match.input.walk_mut(visitor);
+                for (pattern, branch) in &mut nl_match.branches {
+                    if let Some(guard) = pattern.guard_mut() {
+                        guard.walk_mut(visitor);
+                    }
+                    branch.walk_mut(visitor);
+                }
+            }
+            NLOperation::TypeMatch(type_match) => {
+                type_match.input.walk_mut(visitor);
+                for (_, branch) in &mut type_match.branches {
+                    branch.walk_mut(visitor);
+                }
+            }
+            NLOperation::Break(value) | NLOperation::Return(value) => {
+                if let Some(value) = value {
+                    value.walk_mut(visitor);
+                }
+            }
+            NLOperation::Constant(_)
+            | NLOperation::VariableAccess(_)
+            | NLOperation::Continue
+            | NLOperation::SizeOf(_)
+            | NLOperation::AlignOf(_)
+            | NLOperation::NameOf(_)
+            | NLOperation::FieldsOf(_)
+            | NLOperation::VariantsOf(_) => {}
+        }
+    }
+}
+
+impl<'a> NLBlock<'a> {
+    /// Walks every operation in the block. See `NLOperation::walk`.
+    pub fn walk<'b>(&'b self, visitor: &mut dyn FnMut(&'b NLOperation<'a>)) {
+        for operation in &self.operations {
+            operation.walk(visitor);
+        }
+    }
+
+    fn walk_mut(&mut self, visitor: &mut dyn FnMut(&mut NLOperation<'a>)) {
+        for operation in &mut self.operations {
+            operation.walk_mut(visitor);
+        }
+    }
+
+    /// Every name the block writes, wherever it is in the block. See `NLOperation::written_names`.
+    pub(crate) fn written_names(&self) -> Vec<&'a str> {
+        self.operations
+            .iter()
+            .flat_map(NLOperation::written_names)
+            .collect()
+    }
+}
+
+/// The offset and length in `source` of the text from the first of `names` to the end of the last. Names that aren't
+/// slices of `source` are left out, and if none are there's no span.
+pub(crate) fn span<'a>(source: &str, names: impl IntoIterator<Item = &'a str>) -> Option<(usize, usize)> {
+    let start = source.as_ptr() as usize;
+    let mut found: Option<(usize, usize)> = None;
+    for name in names {
+        let offset = match (name.as_ptr() as usize).checked_sub(start) {
+            Some(offset) if offset + name.len() <= source.len() => offset,
+            _ => continue,
+        };
+        let end = offset + name.len();
+        found = Some(match found {
+            Some((first, last)) => (first.min(offset), last.max(end)),
+            None => (offset, end),
+        });
+    }
+
+    found.map(|(first, last)| (first, last - first))
 }
 
 pub struct NLFile<'a> {
-    name: String,
-    structs: Vec<NLStruct<'a>>,
-    traits: Vec<NLTrait<'a>>,
-    functions: Vec<NLFunction<'a>>,
-    enums: Vec<NLEnum<'a>>,
+    pub(crate) name: String,
+    // The text the file was parsed from, without any byte order mark. Names in the tree are slices of it.
+    pub(crate) source: &'a str,
+    features: Vec<Feature>,
+    language_version: LanguageVersion,
+    // From `ParseOptions` and then the file's own pragmas, so later ones win.
+    lint_levels: Vec<(Lint, LintLevel)>,
+    pub(crate) structs: Vec<NLStruct<'a>>,
+    pub(crate) traits: Vec<NLTrait<'a>>,
+    pub(crate) functions: Vec<NLFunction<'a>>,
+    pub(crate) enums: Vec<NLEnum<'a>>,
+    pub(crate) implementations: Vec<NLImplementation<'a>>,
+    pub(crate) macros: Vec<NLMacro<'a>>,
 }
 
 impl<'a> NLFile<'a> {
     pub fn get_name(&self) -> &str {
         &self.name
     }
+    /// The experimental features the file switched on with `#![feature(...)]`.
+    pub fn get_features(&self) -> &Vec<Feature> {
+        &self.features
+    }
+    pub fn get_language_version(&self) -> LanguageVersion {
+        self.language_version
+    }
+    /// What's done about code in this file that the lint catches.
+    pub fn get_lint_level(&self, lint: Lint) -> LintLevel {
+        self.lint_levels
+            .iter()
+            .rev()
+            .find(|(set, _)| *set == lint)
+            .map(|(_, level)| *level)
+            .unwrap_or_else(|| lint.get_default_level())
+    }
     pub fn get_structs(&self) -> &Vec<NLStruct> {
         &self.structs
     }
@@ -485,6 +1187,185 @@ impl<'a> NLFile<'a> {
     pub fn get_enums(&self) -> &Vec<NLEnum> {
         &self.enums
     }
+    /// The variant of error `to` that `?` wraps an error `from` in when passing it on, which is the one holding just
+    /// a `from`. `error ConfigError { File(FileError) }` wraps a `FileError` in `ConfigError::File`.
+    pub fn get_error_conversion(&self, from: &str, to: &str) -> Option<&str> {
+        let to = self
+            .enums
+            .iter()
+            .find(|nl_enum| nl_enum.is_error && nl_enum.name == to)?;
+
+        to.variants
+            .iter()
+            .find(|variant| enums::wraps(variant) == Some(from))
+            .map(|variant| variant.name)
+    }
+    /// Standalone implementations whose struct is not declared in this file.
+    pub fn get_implementations(&self) -> &Vec<NLImplementation> {
+        &self.implementations
+    }
+    pub fn get_macros(&self) -> &Vec<NLMacro> {
+        &self.macros
+    }
+
+    // Every block of code in the file, with the name of the function, method, getter or setter it belongs to. These are
+    // for the passes that check or rewrite code once the whole file has been read.
+    pub(crate) fn bodies(&self) -> Vec<(&str, &NLBlock<'a>)> {
+        fn implementor_bodies<'b, 'a>(
+            implementors: &'b [NLImplementor<'a>],
+            bodies: &mut Vec<(&'b str, &'b NLBlock<'a>)>,
+        ) {
+            for implementor in implementors {
+                match implementor {
+                    NLImplementor::Method(method) => {
+                        if let Some(block) = &method.block {
+                            bodies.push((method.name, block));
+                        }
+                    }
+                    NLImplementor::Getter(getter) => {
+                        if let NLEncapsulationBlock::Some(block) = &getter.block {
+                            bodies.push((&getter.name, block));
+                        }
+                    }
+                    NLImplementor::Setter(setter) => {
+                        if let NLEncapsulationBlock::Some(block) = &setter.block {
+                            bodies.push((setter.name, block));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut bodies = vec![];
+        for function in &self.functions {
+            if let Some(block) = &function.block {
+                bodies.push((function.name, block));
+            }
+        }
+        for nl_trait in &self.traits {
+            implementor_bodies(&nl_trait.implementors, &mut bodies);
+        }
+        for nl_struct in &self.structs {
+            for implementation in &nl_struct.implementations {
+                implementor_bodies(&implementation.implementors, &mut bodies);
+            }
+        }
+        for implementation in &self.implementations {
+            implementor_bodies(&implementation.implementors, &mut bodies);
+        }
+
+        bodies
+    }
+
+    fn bodies_mut(&mut self) -> Vec<(&str, &mut NLBlock<'a>)> {
+        fn implementor_bodies<'b, 'a>(
+            implementors: &'b mut [NLImplementor<'a>],
+            bodies: &mut Vec<(&'b str, &'b mut NLBlock<'a>)>,
+        ) {
+            for implementor in implementors {
+                match implementor {
+                    NLImplementor::Method(method) => {
+                        if let Some(block) = &mut method.block {
+                            bodies.push((method.name, block));
+                        }
+                    }
+                    NLImplementor::Getter(getter) => {
+                        if let NLEncapsulationBlock::Some(block) = &mut getter.block {
+                            bodies.push((&getter.name, block));
+                        }
+                    }
+                    NLImplementor::Setter(setter) => {
+                        if let NLEncapsulationBlock::Some(block) = &mut setter.block {
+                            bodies.push((setter.name, block));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut bodies = vec![];
+        for function in &mut self.functions {
+            if let Some(block) = &mut function.block {
+                bodies.push((function.name, block));
+            }
+        }
+        for nl_trait in &mut self.traits {
+            implementor_bodies(&mut nl_trait.implementors, &mut bodies);
+        }
+        for nl_struct in &mut self.structs {
+            for implementation in &mut nl_struct.implementations {
+                implementor_bodies(&mut implementation.implementors, &mut bodies);
+            }
+        }
+        for implementation in &mut self.implementations {
+            implementor_bodies(&mut implementation.implementors, &mut bodies);
+        }
+
+        bodies
+    }
+}
+
+/// A 64 bit FNV-1a hasher. Unlike `DefaultHasher` its output is fully specified, so fingerprints stay the same across
+/// Rust releases and platforms and can be stored between builds.
+struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    fn new() -> StableHasher {
+        StableHasher {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    // The defaults use native byte order and pointer width, which would make the hash platform dependent.
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+}
+
+/// Produces a hash of the semantic content of a file. Whitespace and comments never make it into the AST, so two
+/// files that only differ in formatting get the same fingerprint. The file name is not included, but the features,
+/// language version and lint levels are, since they change what the file is allowed to contain.
+pub fn ast_fingerprint(file: &NLFile) -> u64 {
+    let mut hasher = StableHasher::new();
+
+    file.features.hash(&mut hasher);
+    file.language_version.hash(&mut hasher);
+    file.lint_levels.hash(&mut hasher);
+
+    file.structs.hash(&mut hasher);
+    file.traits.hash(&mut hasher);
+    file.functions.hash(&mut hasher);
+    file.enums.hash(&mut hasher);
+    file.implementations.hash(&mut hasher);
+
+    hasher.finish()
 }
 
 #[derive(Debug)]
@@ -514,6 +1395,53 @@ fn verbose_error<'a>(input: &'a str, message: &'static str) -> NomErr<VerboseErr
     NomErr::Error(ve)
 }
 
+// Tries each branch in turn, like `alt`. When all of them fail, `alt` would report whatever the last branch said.
+// Instead we report the branch that got furthest into the input, since that's most likely what the user was writing,
+// and note what we were expecting to find.
+fn expect_one_of<'a, O>(
+    input: &'a str,
+    expected: &'static str,
+    branches: &mut [&mut dyn FnMut(&'a str) -> ParserResult<'a, O>],
+) -> ParserResult<'a, O> {
+    fn remaining(error: &VerboseError<&str>) -> usize {
+        // The first entry is the innermost one, which is where the branch actually stopped.
+        match error.errors.first() {
+            Some((input, _)) => input.len(),
+            None => usize::MAX,
+        }
+    }
+
+    let mut furthest: Option<VerboseError<&'a str>> = None;
+    let mut is_tied = false;
+
+    for branch in branches.iter_mut() {
+        match branch(input) {
+            Err(NomErr::Error(error)) => match &furthest {
+                Some(best) if remaining(&error) > remaining(best) => {}
+                Some(best) if remaining(&error) == remaining(best) => is_tied = true,
+                _ => {
+                    furthest = Some(error);
+                    is_tied = false;
+                }
+            },
+            result => return result,
+        }
+    }
+
+    let mut error = furthest.unwrap_or(VerboseError { errors: vec![] });
+
+    if is_tied {
+        // Several branches got equally far, so no single one of their messages is the right one to show.
+        let (position, _) = error.errors[0];
+        error.errors = vec![(position, VerboseErrorKind::Context(expected))];
+    } else {
+        let context = (input, VerboseErrorKind::Context(expected));
+        error.errors.push(context);
+    }
+
+    Err(NomErr::Error(error))
+}
+
 fn read_comment(input: &str) -> ParserResult<&str> {
     alt((
         preceded(tag("//"), terminated(take_until("\n"), tag("\n"))),
@@ -529,6 +1457,14 @@ fn blank(input: &str) -> ParserResult<()> {
     value((), preceded(multispace0, read_comments))(input)
 }
 
+/// Matches `word` only when it isn't the start of a longer name, so `let` doesn't match the start of `letter`.
+fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> ParserResult<'a, &'a str> {
+    terminated(
+        tag(word),
+        not(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
+    )
+}
+
 fn is_name(c: char) -> bool {
     match c {
         '_' => true,
@@ -537,6 +1473,21 @@ fn is_name(c: char) -> bool {
     }
 }
 
+// A run of name characters, stopping before a `..` so that `start..end` is a range between two variables and not one
+// scoped name.
+fn take_name(input: &str) -> ParserResult<&str> {
+    let end = input
+        .char_indices()
+        .find(|(index, c)| !is_name(*c) || input[*index..].starts_with(".."))
+        .map_or(input.len(), |(index, _)| index);
+
+    if end > 0 {
+        Ok((&input[end..], &input[..end]))
+    } else {
+        Err(verbose_error(input, "expected a name"))
+    }
+}
+
 fn read_struct_or_trait_name(input: &str) -> ParserResult<&str> {
     delimited(blank, alphanumeric1, blank)(input)
 }
@@ -573,6 +1524,7 @@ fn read_tuple_of_variable_names(input: &str) -> ParserResult<Vec<&str>> {
 
 fn read_tuple(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
+    let start = input;
     let (input, tuple_str) = delimited(char('('), take_while(|c| c != ')'), char(')'))(input)?;
 
     let (tuple_str, mut tuple) =
@@ -580,6 +1532,20 @@ fn read_tuple(input: &str) -> ParserResult<NLOperation> {
 
     let (_, last_item) = opt(terminated(read_operation, blank))(tuple_str)?;
     match last_item {
+        Some(item) if tuple.is_empty() => {
+            // From 0.2 `(x)` is just x in brackets, and a tuple of one needs a trailing comma.
+            if pragmas::version() >= LanguageVersion::V0_2 {
+                return Ok((input, item));
+            }
+
+            let close = &start[start.len() - input.len() - 1..];
+            pragmas::note_upgrade(
+                start,
+                "`(x)` is a tuple of one, but in nl_version 0.2 it's just `x`. Write `(x,)` to keep the tuple",
+                Some((close, ",")),
+            );
+            tuple.push(item);
+        }
         Some(item) => {
             tuple.push(item);
         }
@@ -589,6 +1555,23 @@ fn read_tuple(input: &str) -> ParserResult<NLOperation> {
     Ok((input, NLOperation::Tuple(tuple)))
 }
 
+fn read_list(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, _) = char('[')(input)?;
+
+    let (input, mut items) = many0(terminated(read_operation, tuple((blank, char(',')))))(input)?;
+
+    let (input, last_item) = opt(read_operation)(input)?;
+    if let Some(item) = last_item {
+        items.push(item);
+    }
+
+    let (input, _) = blank(input)?;
+    let (input, _) = char(']')(input)?;
+
+    Ok((input, NLOperation::List(items)))
+}
+
 fn read_single_variable(input: &str) -> ParserResult<Vec<&str>> {
     let (input, name) = read_variable_name(input)?;
     Ok((input, vec![name]))
@@ -606,7 +1589,7 @@ fn read_boolean_constant(input: &str) -> ParserResult<OpConstant> {
 // TODO this is to be used for casting variable types, not constant types.
 fn read_cast(input: &str) -> ParserResult<NLType> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("as")(input)?;
+    let (input, _) = keyword("as")(input)?;
     let (input, _) = blank(input)?;
 
     read_variable_type(input)
@@ -682,7 +1665,14 @@ fn parse_float(input: &str) -> ParserResult<&str> {
             opt(one_of("+-")),
             parse_decimal,
         ))),
-        recognize(tuple((opt(char('-')), parse_decimal, char('.'), opt(parse_decimal)))),
+        // The dot can't be the start of a `..` range.
+        recognize(tuple((
+            opt(char('-')),
+            parse_decimal,
+            char('.'),
+            not(char('.')),
+            opt(parse_decimal),
+        ))),
     ))(input)
 }
 
@@ -749,6 +1739,10 @@ fn read_numerical_constant(input: &str) -> ParserResult<OpConstant> {
                     input,
                     "Cannot represent a number as a boolean.",
                 )),
+                NLType::Char => Err(verbose_error(
+                    input,
+                    "Cannot represent a number as a char, write the character in quotes like 'a'.",
+                )),
                 _ => Ok((input, nl_type)), // Okay we're good. Use the type.
             },
             Err(_) => Ok((input, NLType::I32)), // If unspecified, assume 32bit.
@@ -768,6 +1762,46 @@ fn read_numerical_constant(input: &str) -> ParserResult<OpConstant> {
     }
 }
 
+// An escape in a string or char literal, like `\n` or `\u{1F600}`. Both quotes can be escaped in either. A backslash
+// followed by anything else is a failure, since the literal can't mean anything.
+fn read_escaped_char(input: &str) -> ParserResult<char> {
+    fn parse_unicode_char(input: &str) -> ParserResult<char> {
+        let parse_hex = take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit());
+        let parse_delimited_hex = preceded(char('u'), delimited(char('{'), parse_hex, char('}')));
+        let parse_u32 = map_res(parse_delimited_hex, move |hex| u32::from_str_radix(hex, 16));
+        map_opt(parse_u32, std::char::from_u32)(input)
+    }
+
+    let (escape, _) = char('\\')(input)?;
+    let known = alt((
+        parse_unicode_char, // Try that unicode first.
+        value('\n', char('n')),
+        value('\r', char('r')),
+        value('\t', char('t')),
+        value('\u{08}', char('b')),
+        value('\u{0C}', char('f')),
+        value('\0', char('0')),
+        value('\\', char('\\')),
+        value('/', char('/')),
+        value('"', char('"')),
+        value('\'', char('\'')),
+    ))(escape);
+
+    match known {
+        Ok(result) => Ok(result),
+        Err(_) => {
+            let message = if escape.starts_with('u') {
+                "unicode escapes need one to six hex digits naming a character, like \\u{1F600}"
+            } else {
+                "unknown escape, expected one of \\n \\r \\t \\b \\f \\0 \\\\ \\/ \\\" \\' or \\u{...}"
+            };
+            Err(NomErr::Failure(VerboseError {
+                errors: vec![(input, VerboseErrorKind::Context(message))],
+            }))
+        }
+    }
+}
+
 fn read_string_constant(input: &str) -> ParserResult<OpConstant> {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     enum StringFragment<'a> {
@@ -777,33 +1811,6 @@ fn read_string_constant(input: &str) -> ParserResult<OpConstant> {
     }
 
     fn parse_fragment(input: &str) -> ParserResult<StringFragment> {
-        fn parse_unicode_char(input: &str) -> ParserResult<char> {
-            let parse_hex = take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit());
-            let parse_delimited_hex = preceded(
-                char('u'),
-                delimited(char('{'), parse_hex, char('}')),
-            );
-            let parse_u32 = map_res(parse_delimited_hex, move |hex| u32::from_str_radix(hex, 16));
-            map_opt(parse_u32, |value| std::char::from_u32(value))(input)
-        }
-        
-        fn parse_escaped_char(input: &str) -> ParserResult<char> {
-            preceded(
-                char('\\'),
-                alt((
-                  parse_unicode_char, // Try that unicode first.
-                  value('\n', char('n')),
-                  value('\r', char('r')),
-                  value('\t', char('t')),
-                  value('\u{08}', char('b')),
-                  value('\u{0C}', char('f')),
-                  value('\\', char('\\')),
-                  value('/', char('/')),
-                  value('"', char('"')),
-                )),
-              )(input)
-        }
-        
         fn parse_escaped_whitespace(input: &str) -> ParserResult<&str> {
             preceded(char('\\'), multispace1)(input)
         }
@@ -814,8 +1821,9 @@ fn read_string_constant(input: &str) -> ParserResult<OpConstant> {
 
         alt((
             map(parse_literal, StringFragment::Literal),
-            map(parse_escaped_char, StringFragment::EscapedChar),
+            // Before escaped characters, which reject a backslash followed by anything they don't know.
             value(StringFragment::EscapedWS, parse_escaped_whitespace),
+            map(read_escaped_char, StringFragment::EscapedChar),
           ))(input)
     }
 
@@ -834,12 +1842,81 @@ fn read_string_constant(input: &str) -> ParserResult<OpConstant> {
     Ok((input, OpConstant::String(string)))
 }
 
+// `'c'`, a single character, which can be an escape like `'\n'` or `'\''`.
+fn read_char_constant(input: &str) -> ParserResult<OpConstant> {
+    let (input, _) = char('\'')(input)?;
+
+    // Once there's a quote it can't be anything else, so a mistake is a failure rather than an error.
+    let mut read_character = terminated(alt((read_escaped_char, none_of("'\\\n"))), char('\''));
+    match read_character(input) {
+        Ok((input, character)) => Ok((input, OpConstant::Char(character))),
+        Err(NomErr::Failure(error)) => Err(NomErr::Failure(error)),
+        Err(_) => Err(NomErr::Failure(VerboseError {
+            errors: vec![(
+                input,
+                VerboseErrorKind::Context("char literals hold exactly one character"),
+            )],
+        })),
+    }
+}
+
+// `b"text"` for the bytes of some ASCII text, or `x"DEADBEEF"` for bytes written in hex.
+fn read_bytes_constant(input: &str) -> ParserResult<OpConstant> {
+    // Once the quote after the prefix is there it's definitely bytes, so mistakes are failures rather than letting the
+    // prefix be read as a variable.
+    fn failure<'a>(input: &'a str, message: &'static str) -> NomErr<VerboseError<&'a str>> {
+        NomErr::Failure(VerboseError {
+            errors: vec![(input, VerboseErrorKind::Context(message))],
+        })
+    }
+
+    let (input, prefix) = terminated(alt((char('b'), char('x'))), peek(char('"')))(input)?;
+    if prefix == 'b' {
+        let literal_input = input;
+        let (input, text) = match read_string_constant(input)? {
+            (input, OpConstant::String(text)) => (input, text),
+            _ => unreachable!("string constants are always strings"),
+        };
+
+        if text.is_ascii() {
+            Ok((input, OpConstant::Bytes(text.into_bytes())))
+        } else {
+            Err(failure(
+                literal_input,
+                "byte strings can only hold ASCII, write other bytes with x\"...\"",
+            ))
+        }
+    } else {
+        let (input, digits) = delimited(char('"'), take_while(|c| c != '"'), char('"'))(input)?;
+        if let Some(position) = digits.find(|c: char| !c.is_ascii_hexdigit()) {
+            return Err(failure(
+                &digits[position..],
+                "hex literals can only hold hex digits",
+            ));
+        }
+        if digits.len() % 2 != 0 {
+            return Err(failure(
+                digits,
+                "hex literals need two digits for each byte",
+            ));
+        }
+
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|start| u8::from_str_radix(&digits[start..start + 2], 16).unwrap())
+            .collect();
+        Ok((input, OpConstant::Bytes(bytes)))
+    }
+}
+
 fn read_constant_raw(input: &str) -> ParserResult<OpConstant> {
     let (input, _) = blank(input)?;
     let (input, constant) = alt((
         read_boolean_constant,
         read_numerical_constant,
+        read_bytes_constant,
         read_string_constant,
+        read_char_constant,
     ))(input)?;
     Ok((input, constant))
 }
@@ -852,7 +1929,7 @@ fn read_constant(input: &str) -> ParserResult<NLOperation> {
 fn read_assignment(input: &str) -> ParserResult<NLOperation> {
     // Are we defining?
     let (input, _) = blank(input)?;
-    let (input, is_new) = opt(tag("let"))(input)?;
+    let (input, is_new) = opt(keyword("let"))(input)?;
     let is_new = is_new.is_some();
 
     // What is our name?
@@ -875,8 +1952,10 @@ fn read_assignment(input: &str) -> ParserResult<NLOperation> {
         (input, vec![])
     } else {
         let (input, assignment) = read_variable_type(input)?;
+
+        // A tuple type is split between the variables when there's more than one of them to destructure into.
         let assignment = match assignment {
-            NLType::Tuple(tuple) => tuple,
+            NLType::Tuple(tuple) if variables.len() > 1 => tuple,
             _ => vec![assignment],
         };
         (input, assignment)
@@ -901,6 +1980,80 @@ fn read_assignment(input: &str) -> ParserResult<NLOperation> {
     Ok((input, NLOperation::Assign(assignment)))
 }
 
+fn read_let_pattern(input: &str) -> ParserResult<NLOperation> {
+    fn read_struct_pattern(input: &str) -> ParserResult<LetPattern> {
+        fn read_field(input: &str) -> ParserResult<(&str, LetPattern)> {
+            let (input, field) = read_variable_name(input)?;
+            let (input, pattern) = opt(preceded(tuple((blank, char(':'))), read_pattern))(input)?;
+            let (input, _) = blank(input)?;
+
+            Ok((
+                input,
+                (field, pattern.unwrap_or(LetPattern::Variable(field))),
+            ))
+        }
+
+        let (input, nl_struct) = read_struct_or_trait_name(input)?;
+        let (input, _) = char('{')(input)?;
+        let (input, mut fields) = many0(terminated(read_field, char(',')))(input)?;
+        let (input, last_field) = opt(read_field)(input)?;
+        if let Some(field) = last_field {
+            fields.push(field);
+        }
+        let (input, _) = tuple((blank, char('}')))(input)?;
+
+        Ok((input, LetPattern::Struct(nl_struct, fields)))
+    }
+
+    fn read_variant_pattern(input: &str) -> ParserResult<LetPattern> {
+        let (input, nl_enum) = read_variable_name(input)?;
+        let (input, _) = tuple((blank, tag("::")))(input)?;
+        let (input, variant) = read_variable_name(input)?;
+
+        let (input, patterns) = opt(delimited(
+            tuple((blank, char('('))),
+            terminated(
+                separated_list0(tuple((blank, char(','))), read_pattern),
+                opt(tuple((blank, char(',')))),
+            ),
+            tuple((blank, char(')'))),
+        ))(input)?;
+
+        Ok((
+            input,
+            LetPattern::Variant(nl_enum, variant, patterns.unwrap_or_default()),
+        ))
+    }
+
+    fn read_pattern(input: &str) -> ParserResult<LetPattern> {
+        alt((
+            read_struct_pattern,
+            read_variant_pattern,
+            map(read_variable_name, LetPattern::Variable),
+        ))(input)
+    }
+
+    let (input, _) = tuple((blank, keyword("let")))(input)?;
+    // A single variable or a tuple of them is an ordinary assignment.
+    let (input, pattern) = alt((read_struct_pattern, read_variant_pattern))(input)?;
+    let (input, _) = tuple((blank, char('='), blank))(input)?;
+    let (input, assignment) = read_operation(input)?;
+
+    let (input, else_block) = opt(preceded(
+        tuple((blank, keyword("else"))),
+        read_code_block_raw,
+    ))(input)?;
+
+    Ok((
+        input,
+        NLOperation::LetPattern(OpLetPattern {
+            pattern,
+            assignment: Box::new(assignment),
+            else_block,
+        }),
+    ))
+}
+
 fn take_operator_symbol(input: &str) -> ParserResult<&str> {
     fn is_operator_symbol(c: char) -> bool {
         match c {
@@ -935,136 +2088,230 @@ fn read_urinary_operator(input: &str) -> ParserResult<NLOperation> {
             let operator = OpOperator::ArithmeticNegate(operand);
             Ok((input, NLOperation::Operator(operator)))
         }
+        "&" => {
+            let operator = OpOperator::AddressOf(operand);
+            Ok((input, NLOperation::Operator(operator)))
+        }
+        "*" => {
+            let operator = OpOperator::Dereference(operand);
+            Ok((input, NLOperation::Operator(operator)))
+        }
 
         _ => Err(verbose_error(input, "unknown operator")),
     }
 }
 
-fn read_binary_operator(input: &str) -> ParserResult<NLOperation> {
-    let (input, _) = blank(input)?;
-    let (input, operand_a) = read_sub_operation(input)?;
-    let operand_a = Box::new(operand_a);
-
-    let (input, _) = blank(input)?;
-    let (input, operator) = take_operator_symbol(input)?;
+// How tightly each binary operator binds, or `None` when it isn't one. Operators that bind tighter have a higher
+// precedence, and operators with the same precedence are read from left to right.
+fn binary_operator_precedence(operator: &str) -> Option<u8> {
+    match operator {
+        "*" | "/" | "%" => Some(10),
+        "+" | "-" => Some(9),
+        "<<" | ">>" => Some(8),
+        "&" => Some(7),
+        "^" => Some(6),
+        "|" => Some(5),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(4),
+        "&&" => Some(3),
+        "^^" => Some(2),
+        "||" => Some(1),
+        ".." => Some(0),
+        _ => None,
+    }
+}
 
-    let (input, _) = blank(input)?;
-    let (input, operand_b) = read_sub_operation(input)?;
+fn make_binary_operator<'a>(
+    operator: &str,
+    operand_a: NLOperation<'a>,
+    operand_b: NLOperation<'a>,
+) -> NLOperation<'a> {
+    let operand_a = Box::new(operand_a);
     let operand_b = Box::new(operand_b);
 
     match operator {
         // Logical operators.
         "==" => {
             let operator = OpOperator::CompareEqual((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "!=" => {
             let operator = OpOperator::CompareNotEqual((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         // TODO create formal errors for => and =< operators to help the noobs.
         ">=" => {
             let operator = OpOperator::CompareGreaterEqual((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "<=" => {
             let operator = OpOperator::CompareLessEqual((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
 
         ">" => {
             let operator = OpOperator::CompareGreater((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "<" => {
             let operator = OpOperator::CompareLess((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "&&" => {
             let operator = OpOperator::LogicalAnd((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "||" => {
             let operator = OpOperator::LogicalOr((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "^^" => {
             let operator = OpOperator::LogicalXor((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
 
         // Bitwise operators.
         "&" => {
             let operator = OpOperator::BitAnd((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "|" => {
             let operator = OpOperator::BitOr((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "^" => {
             let operator = OpOperator::BitXor((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "<<" => {
             let operator = OpOperator::BitLeftShift((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         ">>" => {
             let operator = OpOperator::BitRightShift((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
 
         // Arithmetic operators.
         "+" => {
             let operator = OpOperator::ArithmeticAdd((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "-" => {
             let operator = OpOperator::ArithmeticSub((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "%" => {
             let operator = OpOperator::ArithmeticMod((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "/" => {
             let operator = OpOperator::ArithmeticDiv((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         "*" => {
             let operator = OpOperator::ArithmeticMul((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
         ".." => {
             let operator = OpOperator::Range((operand_a, operand_b));
-            Ok((input, NLOperation::Operator(operator)))
+            NLOperation::Operator(operator)
         }
 
-        _ => Err(verbose_error(input, "unknown operator")),
+        _ => unreachable!("only operators with a precedence are read"),
+    }
+}
+
+// An operator that takes two operands and the operand after it, like the `* c` of `a * c`.
+fn read_operator_and_operand(input: &str) -> ParserResult<(&str, NLOperation)> {
+    let (input, _) = blank(input)?;
+    let (input, operator) = verify(take_operator_symbol, |operator: &str| {
+        binary_operator_precedence(operator).is_some()
+    })(input)?;
+    let (input, _) = blank(input)?;
+    let (input, operand) = read_sub_operation(input)?;
+
+    Ok((input, (operator, operand)))
+}
+
+// Folds operators and the operands after them onto `first`, binding the operators with the highest precedence first.
+fn fold_binary_operators<'a>(
+    first: NLOperation<'a>,
+    rest: Vec<(&'a str, NLOperation<'a>)>,
+) -> NLOperation<'a> {
+    // Folds everything in `rest` that binds tighter than `min_precedence` onto `left`.
+    fn fold<'a>(
+        mut left: NLOperation<'a>,
+        rest: &mut std::iter::Peekable<std::vec::IntoIter<(&str, NLOperation<'a>)>>,
+        min_precedence: u8,
+    ) -> NLOperation<'a> {
+        while let Some(precedence) = rest
+            .peek()
+            .and_then(|(operator, _)| binary_operator_precedence(operator))
+            .filter(|precedence| *precedence >= min_precedence)
+        {
+            let (operator, mut right) = rest.next().unwrap();
+            while let Some(next) = rest
+                .peek()
+                .and_then(|(operator, _)| binary_operator_precedence(operator))
+                .filter(|next| *next > precedence)
+            {
+                right = fold(right, rest, next);
+            }
+            left = make_binary_operator(operator, left, right);
+        }
+
+        left
+    }
+
+    fold(first, &mut rest.into_iter().peekable(), 0)
+}
+
+// `a + b * c`, read as a chain of operands with operators between them. It's then folded into a tree, binding the
+// operators with the highest precedence first.
+fn read_binary_operator(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, first) = read_sub_operation(input)?;
+    let (input, rest) = many1(read_operator_and_operand)(input)?;
+
+    Ok((input, fold_binary_operators(first, rest)))
+}
+
+// Any operators after an operand that was read on its own, like the `+ 1` of `rows()[0] + 1`.
+fn read_operators_after<'a>(
+    input: &'a str,
+    operation: NLOperation<'a>,
+) -> ParserResult<'a, NLOperation<'a>> {
+    let (input, rest) = many0(read_operator_and_operand)(input)?;
+    if rest.is_empty() {
+        return Ok((input, operation));
     }
+
+    Ok((input, fold_binary_operators(operation, rest)))
 }
 
 fn read_if_statement(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("if")(input)?;
+    let (input, _) = keyword("if")(input)?;
     let (input, _) = blank(input)?;
     let (input, condition) = read_operation(input)?;
     let (input, _) = blank(input)?;
     let (input, true_block) = read_code_block(input)?;
     let (input, _) = blank(input)?;
-    let (input, else_tag) = opt(tag("else"))(input)?;
+    let (input, else_tag) = opt(keyword("else"))(input)?;
 
     let (input, false_block) = if else_tag.is_some() {
-        // We have an else block.
-        let (input, block) = read_code_block(input)?;
-
-        let block = match block {
-            NLOperation::Block(block) => block,
-            _ => panic!("Got something other than a block when it should have been a block."),
-        };
-
-        (input, block)
+        // We have an else block, or another if statement for `else if`. That one goes in a block of its own so the
+        // rest of the chain is the false block.
+        let (input, _) = blank(input)?;
+        match opt(read_if_statement)(input)? {
+            (input, Some(statement)) => (
+                input,
+                NLBlock {
+                    operations: vec![statement],
+                },
+            ),
+            (input, None) => read_code_block_raw(input)?,
+        }
     } else {
         (input, NLBlock { operations: vec![] })
     };
@@ -1086,7 +2333,7 @@ fn read_if_statement(input: &str) -> ParserResult<NLOperation> {
 
 fn read_basic_loop(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("loop")(input)?;
+    let (input, _) = keyword("loop")(input)?;
     let (input, _) = blank(input)?;
     let (input, block) = read_code_block_raw(input)?;
 
@@ -1095,7 +2342,7 @@ fn read_basic_loop(input: &str) -> ParserResult<NLOperation> {
 
 fn read_while_loop(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("while")(input)?;
+    let (input, _) = keyword("while")(input)?;
     let (input, _) = blank(input)?;
     let (input, condition) = read_operation(input)?;
     let (input, _) = blank(input)?;
@@ -1112,11 +2359,11 @@ fn read_while_loop(input: &str) -> ParserResult<NLOperation> {
 
 fn read_for_loop(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("for")(input)?;
+    let (input, _) = keyword("for")(input)?;
     let (input, _) = blank(input)?;
     let (input, variable) = read_variable_access_raw(input)?;
     let (input, _) = blank(input)?;
-    let (input, _) = tag("in")(input)?;
+    let (input, _) = keyword("in")(input)?;
     let (input, _) = blank(input)?;
     let (input, iterator) = read_operation(input)?;
     let (input, _) = blank(input)?;
@@ -1132,16 +2379,66 @@ fn read_for_loop(input: &str) -> ParserResult<NLOperation> {
     ))
 }
 
+// The value given to `break` or `return`. It has to start on the same line, or the operation after a plain `break` or
+// `return` would be taken for one.
+fn read_same_line_value(input: &str) -> ParserResult<Option<Box<NLOperation>>> {
+    let (same_line, _) = space0(input)?;
+    let ends_line = same_line.is_empty()
+        || same_line.starts_with(['\n', '\r', '}'])
+        || same_line.starts_with("//");
+    if ends_line {
+        return Ok((input, None));
+    }
+
+    let (input, value) = opt(read_operation)(same_line)?;
+    Ok((input, value.map(Box::new)))
+}
+
 fn read_break_keyword(input: &str) -> ParserResult<NLOperation> {
-    let (input, break_keyword) = opt(tag("break"))(input)?;
+    let (input, _) = blank(input)?;
+    let (input, break_keyword) = opt(keyword("break"))(input)?;
 
     if break_keyword.is_some() {
-        Ok((input, NLOperation::Break))
+        let (input, value) = read_same_line_value(input)?;
+        Ok((input, NLOperation::Break(value)))
     } else {
         Err(verbose_error(input, "This is not a break operation."))
     }
 }
 
+fn read_return_keyword(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, _) = keyword("return")(input)?;
+    let (input, value) = read_same_line_value(input)?;
+
+    Ok((input, NLOperation::Return(value)))
+}
+
+fn read_continue_keyword(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, _) = keyword("continue")(input)?;
+
+    Ok((input, NLOperation::Continue))
+}
+
+fn read_catch_block(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, _) = keyword("catch")(input)?;
+    let (input, _) = blank(input)?;
+    let (input, block) = read_code_block_raw(input)?;
+
+    Ok((input, NLOperation::Catch(block)))
+}
+
+fn read_unsafe_block(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, _) = keyword("unsafe")(input)?;
+    let (input, _) = blank(input)?;
+    let (input, block) = read_code_block_raw(input)?;
+
+    Ok((input, NLOperation::Unsafe(block)))
+}
+
 fn read_variable_access_raw(input: &str) -> ParserResult<OpVariable> {
     let (input, _) = blank(input)?;
     let (input, name) = read_variable_name(input)?;
@@ -1150,23 +2447,30 @@ fn read_variable_access_raw(input: &str) -> ParserResult<OpVariable> {
 }
 
 fn read_variable_access(input: &str) -> ParserResult<NLOperation> {
-    let (input, variable) = read_variable_access_raw(input)?;
+    let (input, _) = blank(input)?;
+    let (input, name) = read_path(input)?;
 
-    Ok((input, NLOperation::VariableAccess(variable)))
+    Ok((input, NLOperation::VariableAccess(OpVariable { name })))
 }
 
-fn read_function_call(input: &str) -> ParserResult<NLOperation> {
-    let (input, _) = blank(input)?;
-    let (input, path) = read_variable_name(input)?;
+// `(a, b + 1, c)` after the name of something being called.
+fn read_call_arguments(input: &str) -> ParserResult<Vec<NLOperation>> {
+    let (input, _) = char('(')(input)?;
+
+    let (input, arguments) = separated_list0(tuple((blank, char(','))), read_operation)(input)?;
+    let (input, _) = opt(tuple((blank, char(','))))(input)?;
+
     let (input, _) = blank(input)?;
-    let (input, arg_input) = delimited(char('('), take_while(|c| c != ')'), char(')'))(input)?;
+    let (input, _) = char(')')(input)?;
 
-    let (arg_input, mut arguments) = many0(terminated(read_variable_name, char(',')))(arg_input)?;
+    Ok((input, arguments))
+}
 
-    let (_, last_arg) = opt(read_variable_name)(arg_input)?;
-    if let Some(arg) = last_arg {
-        arguments.push(arg);
-    };
+fn read_function_call(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, path) = read_path(input)?;
+    let (input, _) = blank(input)?;
+    let (input, arguments) = read_call_arguments(input)?;
 
     Ok((
         input,
@@ -1176,7 +2480,7 @@ fn read_function_call(input: &str) -> ParserResult<NLOperation> {
 
 fn read_match(input: &str) -> ParserResult<NLOperation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("match")(input)?;
+    let (input, _) = keyword("match")(input)?;
     let (input, _) = blank(input)?;
     let (input, input_operation) = read_operation(input)?;
 
@@ -1191,7 +2495,7 @@ fn read_match(input: &str) -> ParserResult<NLOperation> {
         read_operation(input)
     }
 
-    fn read_enum_branch(input: &str) -> ParserResult<(MatchBranch, NLOperation)> {
+    fn read_enum_branch(input: &str) -> ParserResult<MatchBranch> {
         let (input, _) = blank(input)?;
         let (input, nl_enum) = read_variable_name(input)?;
         let (input, _) = blank(input)?;
@@ -1216,49 +2520,109 @@ fn read_match(input: &str) -> ParserResult<NLOperation> {
             Vec::new()
         };
 
-        let (input, operation) = read_branch_body(input)?;
-
         let match_branch = MatchBranch::Enum(MatchEnumBranch {
             nl_enum,
             variant,
             variables,
         });
 
-        Ok((input, (match_branch, operation)))
+        Ok((input, match_branch))
     }
 
-    fn read_constant_branch(input: &str) -> ParserResult<(MatchBranch, NLOperation)> {
+    fn read_constant_branch(input: &str) -> ParserResult<MatchBranch> {
         let (input, _) = blank(input)?;
         let (input, constant) = read_constant_raw(input)?;
-        let (input, _) = blank(input)?;
 
-        let (input, operation) = read_branch_body(input)?;
-
-        Ok((input, (MatchBranch::Constant(constant), operation)))
+        Ok((input, MatchBranch::Constant(constant)))
     }
 
-    fn read_range_branch(input: &str) -> ParserResult<(MatchBranch, NLOperation)> {
+    fn read_range_branch(input: &str) -> ParserResult<MatchBranch> {
+        fn read_bound(input: &str) -> ParserResult<i128> {
+            map_res(
+                recognize(tuple((opt(char('-')), digit1))),
+                str::parse::<i128>,
+            )(input)
+        }
+
         let (input, _) = blank(input)?;
-        let (input, lower) = digit1(input)?;
-        let (_, lower) = parse_integer(lower)?;
+        let (input, lower) = read_bound(input)?;
 
         let (input, _) = blank(input)?;
         let (input, _) = tag("..")(input)?;
 
         let (input, _) = blank(input)?;
-        let (input, higher) = digit1(input)?;
-        let (_, higher) = parse_integer(higher)?;
+        let (input, higher) = read_bound(input)?;
 
+        Ok((input, MatchBranch::Range((lower, higher))))
+    }
+
+    fn read_all_other_branch(input: &str) -> ParserResult<MatchBranch> {
         let (input, _) = blank(input)?;
-        let (input, operation) = read_branch_body(input)?;
+        let (input, _) = keyword("_")(input)?;
+
+        Ok((input, MatchBranch::AllOther))
+    }
+
+    fn read_single_pattern(input: &str) -> ParserResult<MatchBranch> {
+        alt((
+            read_binding_branch,
+            read_range_branch,
+            read_constant_branch,
+            read_all_other_branch,
+            read_enum_branch,
+        ))(input)
+    }
+
+    fn read_binding_branch(input: &str) -> ParserResult<MatchBranch> {
+        let (input, name) = read_variable_name(input)?;
+        let (input, _) = tuple((blank, char('@')))(input)?;
+        let (input, pattern) = read_single_pattern(input)?;
+
+        Ok((input, MatchBranch::Binding(name, Box::new(pattern))))
+    }
 
-        // TODO make work with the new implementation.
-        unimplemented!()
-        // Ok((input, (MatchBranch::Range((lower, higher)), operation)))
+    fn read_or_branch(input: &str) -> ParserResult<MatchBranch> {
+        let start = input;
+        let (input, mut patterns) =
+            separated_list1(tuple((blank, char('|'))), read_single_pattern)(input)?;
+        if patterns.len() == 1 {
+            return Ok((input, patterns.remove(0)));
+        }
+
+        // Whichever of them matched, the branch body has to be able to use the same variables.
+        let mut expected = patterns[0].variables();
+        expected.sort_unstable();
+        for pattern in &patterns[1..] {
+            let mut variables = pattern.variables();
+            variables.sort_unstable();
+            if variables != expected {
+                return Err(NomErr::Failure(VerboseError {
+                    errors: vec![(
+                        start,
+                        VerboseErrorKind::Context(
+                            "every pattern joined with `|` has to give values to the same variables",
+                        ),
+                    )],
+                }));
+            }
+        }
+
+        Ok((input, MatchBranch::Or(patterns)))
     }
 
     fn read_branch(input: &str) -> ParserResult<(MatchBranch, NLOperation)> {
-        alt((read_range_branch, read_constant_branch, read_enum_branch))(input)
+        let (input, pattern) = read_or_branch(input)?;
+
+        let (input, _) = blank(input)?;
+        let (input, guard) = opt(preceded(keyword("if"), read_operation))(input)?;
+        let pattern = match guard {
+            Some(guard) => MatchBranch::Guarded(Box::new(pattern), Box::new(guard)),
+            None => pattern,
+        };
+
+        let (input, operation) = read_branch_body(input)?;
+
+        Ok((input, (pattern, operation)))
     }
 
     let (input, _) = blank(input)?;
@@ -1284,53 +2648,273 @@ fn read_match(input: &str) -> ParserResult<NLOperation> {
     ))
 }
 
-fn read_code_block_raw(input: &str) -> ParserResult<NLBlock> {
+fn read_type_match(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = tuple((blank, keyword("match"), blank, keyword("type"), blank))(input)?;
+    let (input, input_operation) = read_operation(input)?;
+
     let (input, _) = blank(input)?;
     let (input, _) = char('{')(input)?;
 
-    let (input, operations) = many0(read_operation)(input)?;
+    fn read_struct_branch(input: &str) -> ParserResult<TypeMatchBranch> {
+        let (input, nl_struct) = read_struct_or_trait_name(input)?;
+        let (input, variable) = opt(delimited(
+            char('('),
+            terminated(read_variable_name, blank),
+            char(')'),
+        ))(input)?;
 
-    let (input, _) = blank(input)?;
-    let (input, _) = char('}')(input)?;
+        Ok((input, TypeMatchBranch::Struct(nl_struct, variable)))
+    }
 
-    Ok((input, NLBlock { operations }))
-}
+    fn read_branch(input: &str) -> ParserResult<(TypeMatchBranch, NLOperation)> {
+        let (input, _) = blank(input)?;
+        let (input, branch) = alt((
+            value(TypeMatchBranch::AllOther, keyword("_")),
+            read_struct_branch,
+        ))(input)?;
 
-fn read_code_block(input: &str) -> ParserResult<NLOperation> {
-    let (input, block) = read_code_block_raw(input)?;
+        let (input, _) = tuple((blank, tag("=>"), blank))(input)?;
+        let (input, operation) = read_operation(input)?;
 
-    Ok((input, NLOperation::Block(block)))
-}
+        Ok((input, (branch, operation)))
+    }
 
-fn read_sub_operation(input: &str) -> ParserResult<NLOperation> {
-    alt((
-        read_code_block,
-        read_tuple,
-        read_function_call,
-        read_assignment,
-        read_constant,
-        read_urinary_operator,
-        read_variable_access,
-    ))(input)
+    let (input, mut branches) = many0(terminated(read_branch, char(',')))(input)?;
+    let (input, last_branch) = opt(read_branch)(input)?;
+    if let Some(branch) = last_branch {
+        branches.push(branch);
+    }
+
+    let (input, _) = tuple((blank, char('}')))(input)?;
+
+    Ok((
+        input,
+        NLOperation::TypeMatch(TypeMatch {
+            input: Box::new(input_operation),
+            branches,
+        }),
+    ))
 }
 
-fn read_operation(input: &str) -> ParserResult<NLOperation> {
-    alt((
+fn read_code_block_raw(input: &str) -> ParserResult<NLBlock> {
+    let (input, _) = blank(input)?;
+    let (input, _) = char('{')(input)?;
+
+    let (input, operations) = many0(read_operation)(input)?;
+
+    let (input, _) = blank(input)?;
+    let (input, closing) = opt(char('}'))(input)?;
+
+    if closing.is_some() {
+        Ok((input, NLBlock { operations }))
+    } else {
+        // Whatever is in the way isn't an operation. Reading it as one again tells the user why not.
+        read_operation(input)?;
+        Err(verbose_error(input, "expected '}' at the end of the block"))
+    }
+}
+
+fn read_code_block(input: &str) -> ParserResult<NLOperation> {
+    let (input, block) = read_code_block_raw(input)?;
+
+    Ok((input, NLOperation::Block(block)))
+}
+
+// `Point { x: 1, y: 2 }`. A name followed by a block would look the same, like the condition and block of
+// `if ready { x }`, so it's only a struct if the name starts with a capital letter, as struct names do, and the braces
+// are empty or start with `field:`.
+fn read_struct_construction(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, name) = verify(alphanumeric1, |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_uppercase())
+    })(input)?;
+    let (input, _) = blank(input)?;
+    let (input, _) = char('{')(input)?;
+    peek(alt((
+        recognize(tuple((blank, char('}')))),
+        recognize(tuple((read_variable_name, blank, char(':')))),
+    )))(input)?;
+
+    fn read_field(input: &str) -> ParserResult<(&str, NLOperation)> {
+        let (input, name) = read_variable_name(input)?;
+        let (input, _) = tuple((blank, char(':')))(input)?;
+        let (input, value) = read_operation(input)?;
+
+        Ok((input, (name, value)))
+    }
+
+    let (input, fields) = separated_list0(tuple((blank, char(','))), read_field)(input)?;
+    let (input, _) = opt(tuple((blank, char(','))))(input)?;
+    let (input, _) = tuple((blank, char('}')))(input)?;
+
+    Ok((
+        input,
+        NLOperation::StructConstruction(OpStructConstruction { name, fields }),
+    ))
+}
+
+// `sizeof(T)` and `alignof(T)`, which are answered from the type's layout at compile time, and `name_of(T)`,
+// `fields_of(T)` and `variants_of(T)`, which are answered once the whole file has been read.
+fn read_type_query(input: &str) -> ParserResult<NLOperation> {
+    let (input, _) = blank(input)?;
+    let (input, query) = alt((
+        keyword("sizeof"),
+        keyword("alignof"),
+        keyword("name_of"),
+        keyword("fields_of"),
+        keyword("variants_of"),
+    ))(input)?;
+    let (input, _) = tuple((blank, char('('), blank))(input)?;
+    let (input, nl_type) = read_variable_type(input)?;
+    let (input, _) = tuple((blank, char(')')))(input)?;
+
+    match query {
+        "sizeof" => Ok((input, NLOperation::SizeOf(nl_type))),
+        "alignof" => Ok((input, NLOperation::AlignOf(nl_type))),
+        "name_of" => Ok((input, NLOperation::NameOf(nl_type))),
+        "fields_of" => Ok((input, NLOperation::FieldsOf(nl_type))),
+        _ => Ok((input, NLOperation::VariantsOf(nl_type))),
+    }
+}
+
+// Indexes, field accesses and method calls straight after an operation, which chain like `a.b[0].c().d`. The bracket
+// of an index has to come straight after the collection, since `a [b]` is `a` followed by a list.
+fn read_postfix<'a>(
+    input: &'a str,
+    operation: NLOperation<'a>,
+) -> ParserResult<'a, NLOperation<'a>> {
+    let mut operation = operation;
+    let mut input = input;
+    loop {
+        if let (remaining, Some(_)) = opt(char('['))(input)? {
+            let (remaining, index) = read_operation(remaining)?;
+            let (remaining, _) = tuple((blank, char(']')))(remaining)?;
+
+            operation = NLOperation::Index(OpIndex {
+                collection: Box::new(operation),
+                index: Box::new(index),
+            });
+            input = remaining;
+            continue;
+        }
+
+        // `..` is a range rather than a field.
+        let (remaining, name) = opt(preceded(
+            terminated(char('.'), not(char('.'))),
+            take_while1(is_method_char),
+        ))(input)?;
+        let name = match name {
+            Some(name) => name,
+            None => break,
+        };
+
+        let (remaining, arguments) = opt(read_call_arguments)(remaining)?;
+        operation = match arguments {
+            Some(arguments) => NLOperation::MethodCall(OpMethodCall {
+                receiver: Box::new(operation),
+                method: name,
+                arguments,
+            }),
+            None => NLOperation::FieldAccess(OpFieldAccess {
+                structure: Box::new(operation),
+                field: name,
+            }),
+        };
+        input = remaining;
+    }
+
+    Ok((input, operation))
+}
+
+// `operation?`, which returns early when the operation gives an error. Still experimental.
+fn read_error_propagation<'a>(
+    input: &'a str,
+    operation: NLOperation<'a>,
+) -> ParserResult<'a, NLOperation<'a>> {
+    match opt(char('?'))(input)? {
+        (remaining, Some(_)) => {
+            pragmas::require_feature(input, Feature::ErrorPropagation)?;
+            let operation = NLOperation::Operator(OpOperator::PropError(Box::new(operation)));
+            Ok((remaining, operation))
+        }
+        (input, None) => Ok((input, operation)),
+    }
+}
+
+fn read_sub_operation(input: &str) -> ParserResult<NLOperation> {
+    let (input, operation) = alt((
         read_code_block,
-        read_if_statement,
-        read_match,
-        read_break_keyword,
-        read_basic_loop,
-        read_while_loop,
-        read_for_loop,
         read_tuple,
+        read_list,
+        read_type_query,
+        read_struct_construction,
         read_function_call,
         read_assignment,
-        read_binary_operator,
         read_constant,
         read_urinary_operator,
         read_variable_access,
-    ))(input)
+    ))(input)?;
+
+    let (input, operation) = read_postfix(input, operation)?;
+    read_error_propagation(input, operation)
+}
+
+fn read_operation(input: &str) -> ParserResult<NLOperation> {
+    traced("operation", |input| {
+        let (input, operation) = expect_one_of(
+            input,
+            "expected an operation",
+            &mut [
+                &mut read_code_block,
+                &mut read_if_statement,
+                &mut read_match,
+                &mut read_type_match,
+                &mut read_catch_block,
+                &mut read_unsafe_block,
+                &mut read_break_keyword,
+                &mut read_continue_keyword,
+                &mut read_return_keyword,
+                &mut read_basic_loop,
+                &mut read_while_loop,
+                &mut read_for_loop,
+                &mut read_tuple,
+                &mut read_list,
+                &mut read_type_query,
+                &mut read_struct_construction,
+                &mut read_function_call,
+                &mut read_let_pattern,
+                &mut read_assignment,
+                &mut read_binary_operator,
+                &mut read_constant,
+                &mut read_urinary_operator,
+                &mut read_variable_access,
+            ],
+        )?;
+
+        let (input, operation) = read_postfix(input, operation)?;
+        let (input, operation) = read_error_propagation(input, operation)?;
+
+        // Calls, tuples, lists and struct literals are read before `read_binary_operator` gets a chance, which would
+        // only read them again, so any operators after them are read here.
+        match operation {
+            NLOperation::FunctionCall(_)
+            | NLOperation::Tuple(_)
+            | NLOperation::List(_)
+            | NLOperation::StructConstruction(_)
+            | NLOperation::SizeOf(_)
+            | NLOperation::AlignOf(_)
+            | NLOperation::NameOf(_)
+            | NLOperation::FieldsOf(_)
+            | NLOperation::VariantsOf(_)
+            | NLOperation::Index(_)
+            | NLOperation::FieldAccess(_)
+            | NLOperation::MethodCall(_)
+            | NLOperation::Operator(OpOperator::PropError(_)) => {
+                read_operators_after(input, operation)
+            }
+            _ => Ok((input, operation)),
+        }
+    })(input)
 }
 
 fn read_argument_declaration(input: &str) -> ParserResult<NLArgument> {
@@ -1357,7 +2941,7 @@ fn read_argument_declaration(input: &str) -> ParserResult<NLArgument> {
                 let input = post_input;
 
                 let (input, _) = blank(input)?;
-                let (input, tagged) = opt(tag("self"))(input)?;
+                let (input, tagged) = opt(keyword("self"))(input)?;
                 if tagged.is_some() {
                     let arg = NLArgument {
                         name: "self",
@@ -1367,10 +2951,10 @@ fn read_argument_declaration(input: &str) -> ParserResult<NLArgument> {
                     return Ok((input, arg));
                 }
 
-                let (input, tagged) = opt(tag("mut"))(input)?;
+                let (input, tagged) = opt(keyword("mut"))(input)?;
                 if tagged.is_some() {
                     let (input, _) = blank(input)?;
-                    let (input, _) = tag("self")(input)?;
+                    let (input, _) = keyword("self")(input)?;
 
                     let arg = NLArgument {
                         name: "self",
@@ -1394,12 +2978,12 @@ fn read_argument_declaration(input: &str) -> ParserResult<NLArgument> {
 }
 
 fn read_argument_deceleration_list(input: &str) -> ParserResult<Vec<NLArgument>> {
-    let (input, arg_input) = delimited(char('('), take_while(|c| c != ')'), char(')'))(input)?;
+    // Argument types can have brackets of their own, so we can't just look for the first `)`.
+    let (input, _) = char('(')(input)?;
 
-    let (arg_input, mut arguments) =
-        many0(terminated(read_argument_declaration, char(',')))(arg_input)?;
+    let (input, mut arguments) = many0(terminated(read_argument_declaration, char(',')))(input)?;
 
-    let (_, last_arg) = opt(terminated(read_argument_declaration, blank))(arg_input)?;
+    let (input, last_arg) = opt(terminated(read_argument_declaration, blank))(input)?;
     match last_arg {
         Some(arg) => {
             arguments.push(arg);
@@ -1407,6 +2991,9 @@ fn read_argument_deceleration_list(input: &str) -> ParserResult<Vec<NLArgument>>
         _ => {} // Do nothing if there was no argument.
     }
 
+    let (input, _) = blank(input)?;
+    let (input, _) = char(')')(input)?;
+
     Ok((input, arguments))
 }
 
@@ -1425,81 +3012,147 @@ fn read_return_type(input: &str) -> ParserResult<NLType> {
     }
 }
 
+// `#[deprecated]` or `#[deprecated("use something else")]` in front of a function, method or getter.
+fn read_deprecation(input: &str) -> ParserResult<Option<String>> {
+    let (input, _) = blank(input)?;
+    let (input, attribute) = opt(tuple((
+        char('#'),
+        blank,
+        char('['),
+        blank,
+        keyword("deprecated"),
+    )))(input)?;
+    if attribute.is_none() {
+        return Ok((input, None));
+    }
+
+    let (input, _) = blank(input)?;
+    let (input, message) = match opt(char('('))(input)? {
+        (input, Some(_)) => {
+            let (input, _) = blank(input)?;
+            let (input, message) = match read_string_constant(input) {
+                Ok((input, OpConstant::String(message))) => (input, message),
+                _ => {
+                    return Err(verbose_error(
+                        input,
+                        "expected the deprecation message in quotes",
+                    ))
+                }
+            };
+            let (input, _) = blank(input)?;
+            let (input, _) = char(')')(input)?;
+            (input, message)
+        }
+        (input, None) => (input, String::new()),
+    };
+
+    let (input, _) = blank(input)?;
+    let (input, _) = char(']')(input)?;
+
+    Ok((input, Some(message)))
+}
+
+fn read_const_modifier(input: &str) -> ParserResult<bool> {
+    let (input, is_const) = opt(terminated(keyword("const"), blank))(input)?;
+
+    Ok((input, is_const.is_some()))
+}
+
+fn read_unsafe_modifier(input: &str) -> ParserResult<bool> {
+    let (input, is_unsafe) = opt(terminated(keyword("unsafe"), blank))(input)?;
+
+    Ok((input, is_unsafe.is_some()))
+}
+
 fn read_method(input: &str) -> ParserResult<NLImplementor> {
+    let (input, deprecation) = read_deprecation(input)?;
     let (input, _) = blank(input)?;
-    let (input, _) = tag("met")(input)?;
+    let (input, is_const) = read_const_modifier(input)?;
+    let (input, is_unsafe) = read_unsafe_modifier(input)?;
+    let (input, _) = keyword("met")(input)?;
     let (input, _) = blank(input)?;
     let (input, name) = read_method_name(input)?;
     let (input, _) = blank(input)?;
     let (input, args) = read_argument_deceleration_list(input)?;
     let (input, _) = blank(input)?;
     let (input, return_type) = read_return_type(input)?;
-    let (input, _) = blank(input)?;
-    let (input, block) = opt(read_code_block)(input)?;
-    let block = match block {
-        Some(block) => match block {
-            NLOperation::Block(block) => Some(block),
-            _ => None,
-        },
-        _ => None,
-    };
+    let (input, block) = read_function_body(input)?;
 
     let method = NLFunction {
         name,
         arguments: args,
         return_type,
         block,
+        is_unsafe,
+        is_const,
+        deprecation,
     };
 
-    // No block, we expect a semicolon.
-    if method.block.is_none() {
-        let (input, _) = char(';')(input)?;
+    Ok((input, NLImplementor::Method(method)))
+}
+
+// Reads the body of a function, which is either a block or a `;` if the function is only being declared.
+fn read_function_body(input: &str) -> ParserResult<Option<NLBlock>> {
+    let (input, _) = blank(input)?;
+    let (input, semicolon) = opt(char(';'))(input)?;
 
-        Ok((input, NLImplementor::Method(method)))
+    if semicolon.is_some() {
+        Ok((input, None))
     } else {
-        Ok((input, NLImplementor::Method(method)))
+        let (input, block) = read_code_block_raw(input)?;
+        Ok((input, Some(block)))
     }
 }
 
 fn read_function(input: &str) -> ParserResult<RootDeceleration> {
+    let (input, deprecation) = read_deprecation(input)?;
     let (input, _) = blank(input)?;
-    let (input, _) = tag("fn")(input)?;
+    let (input, is_const) = read_const_modifier(input)?;
+    let (input, is_unsafe) = read_unsafe_modifier(input)?;
+    let (input, _) = keyword("fn")(input)?;
     let (input, _) = blank(input)?;
     let (input, name) = read_method_name(input)?;
     let (input, _) = blank(input)?;
     let (input, args) = read_argument_deceleration_list(input)?;
     let (input, _) = blank(input)?;
     let (input, return_type) = read_return_type(input)?;
-    let (input, _) = blank(input)?;
-    let (input, block) = opt(read_code_block)(input)?;
-    let block = match block {
-        Some(block) => match block {
-            NLOperation::Block(block) => Some(block),
-            _ => None,
-        },
-        _ => None,
-    };
+    let (input, block) = read_function_body(input)?;
 
     let function = NLFunction {
         name,
         arguments: args,
         return_type,
         block,
+        is_unsafe,
+        is_const,
+        deprecation,
     };
 
-    // No block, we expect a semicolon.
-    if function.block.is_none() {
-        let (input, _) = char(';')(input)?;
+    Ok((input, RootDeceleration::Function(function)))
+}
 
-        Ok((input, RootDeceleration::Function(function)))
-    } else {
-        Ok((input, RootDeceleration::Function(function)))
-    }
+fn read_macro(input: &str) -> ParserResult<RootDeceleration> {
+    let (input, _) = blank(input)?;
+    let (input, _) = keyword("macro")(input)?;
+    let (input, name) = read_method_name(input)?;
+    let (input, parameters) = read_tuple_of_variable_names(input)?;
+    let (input, _) = blank(input)?;
+    let (input, block) = read_code_block_raw(input)?;
+
+    Ok((
+        input,
+        RootDeceleration::Macro(NLMacro {
+            name,
+            parameters,
+            block,
+        }),
+    ))
 }
 
 fn read_variant_enum(input: &str) -> ParserResult<RootDeceleration> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("enum")(input)?;
+    // An error is an enum that `?` knows how to wrap in other errors.
+    let (input, declared_as) = alt((keyword("enum"), keyword("error")))(input)?;
     let (input, _) = blank(input)?;
     let (input, name) = read_method_name(input)?;
 
@@ -1519,7 +3172,29 @@ fn read_variant_enum(input: &str) -> ParserResult<RootDeceleration> {
             Vec::new()
         };
 
-        Ok((input, EnumVariant { name, arguments }))
+        let (input, payload) = if arguments.is_empty() {
+            opt(delimited(
+                char('('),
+                terminated(
+                    separated_list1(tuple((blank, char(','))), read_variable_type),
+                    opt(tuple((blank, char(',')))),
+                ),
+                tuple((blank, char(')'))),
+            ))(input)?
+        } else {
+            (input, None)
+        };
+
+        let (input, _) = blank(input)?;
+
+        Ok((
+            input,
+            EnumVariant {
+                name,
+                arguments,
+                payload: payload.unwrap_or_default(),
+            },
+        ))
     }
 
     let (input, _) = blank(input)?;
@@ -1533,15 +3208,23 @@ fn read_variant_enum(input: &str) -> ParserResult<RootDeceleration> {
     let (input, _) = blank(input)?;
     let (input, _) = char('}')(input)?;
 
-    Ok((input, RootDeceleration::Enum(NLEnum { name, variants })))
+    Ok((
+        input,
+        RootDeceleration::Enum(NLEnum {
+            name,
+            variants,
+            is_error: declared_as == "error",
+        }),
+    ))
 }
 
 fn read_getter(input: &str) -> ParserResult<NLImplementor> {
+    let (input, deprecation) = read_deprecation(input)?;
     let (input, _) = blank(input)?;
-    let (input, _) = tag("get")(input)?;
+    let (input, _) = keyword("get")(input)?;
     let (input, name) = read_method_name(input)?;
     let (input, _) = blank(input)?;
-    let (input, is_default) = opt(tuple((char(':'), blank, tag("default"), blank)))(input)?;
+    let (input, is_default) = opt(tuple((char(':'), blank, keyword("default"), blank)))(input)?;
 
     if is_default.is_some() {
         let (input, nl_type) = read_return_type(input)?;
@@ -1552,56 +3235,57 @@ fn read_getter(input: &str) -> ParserResult<NLImplementor> {
             args: vec![],
             nl_type,
             block: NLEncapsulationBlock::Default,
+            deprecation,
         };
 
         Ok((input, NLImplementor::Getter(getter)))
     } else {
+        let args_input = input;
         let (input, args) = read_argument_deceleration_list(input)?;
+
+        // Reading a property shouldn't change anything, so the only argument a getter gets is `&self`.
+        match args.as_slice() {
+            [arg] if arg.nl_type == NLType::SelfReference => {}
+            _ => {
+                return Err(verbose_error(
+                    args_input,
+                    "a getter takes exactly one argument, `&self`",
+                ))
+            }
+        }
+
         let (input, nl_type) = read_return_type(input)?;
-        let (input, block) = opt(read_code_block)(input)?;
+        let (input, block) = read_function_body(input)?;
 
         let block = match block {
-            Some(block) => match block {
-                NLOperation::Block(block) => Some(block),
-                _ => None,
-            },
-            _ => None,
+            Some(block) => NLEncapsulationBlock::Some(block),
+            None => NLEncapsulationBlock::None,
         };
 
-        match block {
-            Some(block) => {
-                let getter = NLGetter {
-                    name: String::from(name),
-                    args,
-                    nl_type,
-                    block: NLEncapsulationBlock::Some(block),
-                };
-
-                Ok((input, NLImplementor::Getter(getter)))
-            }
-            None => {
-                let (input, _) = char(';')(input)?;
-
-                let getter = NLGetter {
-                    name: String::from(name),
-                    args,
-                    nl_type,
-                    block: NLEncapsulationBlock::None,
-                };
+        let getter = NLGetter {
+            name: String::from(name),
+            args,
+            nl_type,
+            block,
+            deprecation,
+        };
 
-                Ok((input, NLImplementor::Getter(getter)))
-            }
-        }
+        Ok((input, NLImplementor::Getter(getter)))
     }
 }
 
 fn read_setter(input: &str) -> ParserResult<NLImplementor> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("set")(input)?;
+    let (input, _) = keyword("set")(input)?;
     let (input, name) = read_method_name(input)?;
     let (input, _) = blank(input)?;
-    let (input, is_default) =
-        opt(tuple((char(':'), blank, tag("default"), blank, char(';'))))(input)?;
+    let (input, is_default) = opt(tuple((
+        char(':'),
+        blank,
+        keyword("default"),
+        blank,
+        char(';'),
+    )))(input)?;
 
     if is_default.is_some() {
         let setter = NLSetter {
@@ -1613,56 +3297,58 @@ fn read_setter(input: &str) -> ParserResult<NLImplementor> {
         Ok((input, NLImplementor::Setter(setter)))
     } else {
         let (input, args) = read_argument_deceleration_list(input)?;
-        let (input, _) = blank(input)?;
-        let (input, block) = opt(read_code_block)(input)?;
+        let (input, block) = read_function_body(input)?;
+
         let block = match block {
-            Some(block) => match block {
-                NLOperation::Block(block) => Some(block),
-                _ => None,
-            },
-            _ => None,
+            Some(block) => NLEncapsulationBlock::Some(block),
+            None => NLEncapsulationBlock::None,
         };
 
-        match block {
-            Some(block) => {
-                let setter = NLSetter {
-                    name,
-                    args,
-                    block: NLEncapsulationBlock::Some(block),
-                };
+        let setter = NLSetter { name, args, block };
 
-                Ok((input, NLImplementor::Setter(setter)))
-            }
-            None => {
-                let (input, _) = char(';')(input)?;
+        Ok((input, NLImplementor::Setter(setter)))
+    }
+}
+
+// TODO make it so you can specify required traits.
+fn read_implementor(input: &str) -> ParserResult<NLImplementor> {
+    expect_one_of(
+        input,
+        "expected `met`, `get`, or `set`",
+        &mut [
+            &mut traced("method", read_method),
+            &mut traced("getter", read_getter),
+            &mut traced("setter", read_setter),
+        ],
+    )
+}
 
-                let setter = NLSetter {
-                    name,
-                    args,
-                    block: NLEncapsulationBlock::None,
-                };
+/// Reads the members of a trait or implementation, up to and including the closing brace.
+fn read_implementors(input: &str) -> ParserResult<Vec<NLImplementor>> {
+    let (input, _) = blank(input)?;
+    let (input, implementors) = many0(read_implementor)(input)?;
 
-                Ok((input, NLImplementor::Setter(setter)))
-            }
-        }
+    let (input, _) = blank(input)?;
+    let (input, closing) = opt(char('}'))(input)?;
+
+    if closing.is_some() {
+        Ok((input, implementors))
+    } else {
+        // Whatever is in the way isn't a member. Reading it as one again tells the user why not.
+        read_implementor(input)?;
+        Err(verbose_error(input, "expected '}' after the last member"))
     }
 }
 
-// TODO make it so you can specify required traits.
 fn read_trait(input: &str) -> ParserResult<RootDeceleration> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("trait")(input)?;
+    let (input, _) = keyword("trait")(input)?;
     let (input, _) = blank(input)?;
     let (input, name) = read_struct_or_trait_name(input)?;
 
     let (input, _) = blank(input)?;
     let (input, _) = char('{')(input)?;
-    let (input, _) = blank(input)?;
-
-    let (input, implementors) = many0(alt((read_method, read_getter, read_setter)))(input)?;
-
-    let (input, _) = blank(input)?;
-    let (input, _) = char('}')(input)?;
+    let (input, implementors) = read_implementors(input)?;
 
     let new_trait = NLTrait { name, implementors };
 
@@ -1671,7 +3357,26 @@ fn read_trait(input: &str) -> ParserResult<RootDeceleration> {
 
 fn read_variable_name(input: &str) -> ParserResult<&str> {
     let (input, _) = blank(input)?;
-    take_while1(is_name)(input)
+    take_name(input)
+}
+
+// A name that can be qualified with what it belongs to, like `Circle::new` or `Color::Red`. The parts can't have `.`s
+// in them, since after a name that's a field access or a method call.
+fn read_path(input: &str) -> ParserResult<&str> {
+    fn take_part(input: &str) -> ParserResult<&str> {
+        let (rest, name) = take_name(input)?;
+        match name.find('.') {
+            Some(0) => Err(verbose_error(input, "expected a name")),
+            Some(end) => Ok((&input[end..], &name[..end])),
+            None => Ok((rest, name)),
+        }
+    }
+
+    let (input, _) = blank(input)?;
+    recognize(tuple((
+        take_part,
+        many0_count(tuple((tag("::"), take_part))),
+    )))(input)
 }
 
 fn identify_struct_or_trait_type(input: &str) -> ParserResult<NLType> {
@@ -1681,7 +3386,7 @@ fn identify_struct_or_trait_type(input: &str) -> ParserResult<NLType> {
     let (input, _) = blank(input)?;
 
     let (input, is_mutable) = if is_reference {
-        let (input, is_mutable) = opt(tag("mut"))(input)?;
+        let (input, is_mutable) = opt(keyword("mut"))(input)?;
         let is_mutable = is_mutable.is_some();
 
         let (input, _) = blank(input)?;
@@ -1692,7 +3397,7 @@ fn identify_struct_or_trait_type(input: &str) -> ParserResult<NLType> {
         (input, false)
     };
 
-    let (input, is_struct) = opt(tag("dyn"))(input)?;
+    let (input, is_struct) = opt(keyword("dyn"))(input)?;
     let is_struct = is_struct.is_none();
 
     let (input, name) = read_struct_or_trait_name(input)?;
@@ -1737,6 +3442,7 @@ fn read_variable_type_primitive_no_whitespace(input: &str) -> ParserResult<NLTyp
         "f32" => Ok((input, NLType::F32)),
         "f64" => Ok((input, NLType::F64)),
         "bool" => Ok((input, NLType::Boolean)),
+        "char" => Ok((input, NLType::Char)),
 
         _ => Err(verbose_error(
             input,
@@ -1745,6 +3451,131 @@ fn read_variable_type_primitive_no_whitespace(input: &str) -> ParserResult<NLTyp
     }
 }
 
+fn read_collection_type(input: &str) -> ParserResult<NLType> {
+    fn read_type_arguments(input: &str) -> ParserResult<Vec<NLType>> {
+        let (input, _) = blank(input)?;
+        let (input, _) = char('<')(input)?;
+
+        let (input, mut arguments) =
+            many0(terminated(read_variable_type, tuple((blank, char(',')))))(input)?;
+
+        let (input, last_argument) = opt(read_variable_type)(input)?;
+        if let Some(argument) = last_argument {
+            arguments.push(argument);
+        }
+
+        let (input, _) = blank(input)?;
+        let (input, _) = char('>')(input)?;
+
+        Ok((input, arguments))
+    }
+
+    let (input, name) = alphanumeric1(input)?;
+
+    match name {
+        "List" => {
+            let (input, mut arguments) = read_type_arguments(input)?;
+            if arguments.len() == 1 {
+                let item = arguments.remove(0);
+                Ok((input, NLType::List(Box::new(item))))
+            } else {
+                Err(verbose_error(input, "List takes exactly one type: List<T>"))
+            }
+        }
+        "Map" => {
+            let (input, mut arguments) = read_type_arguments(input)?;
+            if arguments.len() == 2 {
+                let value = arguments.remove(1);
+                let key = arguments.remove(0);
+                Ok((input, NLType::Map(Box::new(key), Box::new(value))))
+            } else {
+                Err(verbose_error(
+                    input,
+                    "Map takes exactly two types: Map<K, V>",
+                ))
+            }
+        }
+        _ => Err(verbose_error(input, "not a collection type")),
+    }
+}
+
+fn read_primitive_type(input: &str) -> ParserResult<NLType> {
+    let (input, nl_type) = read_variable_type_primitive_no_whitespace(input)?;
+    let suffix_input = input;
+    let (input, byte_order) = opt(alt((
+        value(ByteOrder::Little, keyword("_le")),
+        value(ByteOrder::Big, keyword("_be")),
+    )))(input)?;
+
+    match byte_order {
+        None => Ok((input, nl_type)),
+        Some(byte_order) if nl_type.is_integer() || nl_type.is_float() => {
+            Ok((input, NLType::Endian(byte_order, Box::new(nl_type))))
+        }
+        // A failure rather than an error, or `bool_le` would be read again as a struct named `bool`.
+        Some(_) => Err(NomErr::Failure(VerboseError {
+            errors: vec![(
+                suffix_input,
+                VerboseErrorKind::Context("only numbers have a byte order"),
+            )],
+        })),
+    }
+}
+
+fn read_pointer_type(input: &str) -> ParserResult<NLType> {
+    let (input, _) = char('*')(input)?;
+    let (input, _) = blank(input)?;
+    let (input, mutability) = opt(alt((keyword("const"), keyword("mut"))))(input)?;
+
+    let (input, pointee) = match mutability {
+        Some(_) => read_variable_type(input)?,
+        None => {
+            return Err(verbose_error(
+                input,
+                "pointers must be `*const T` or `*mut T`",
+            ))
+        }
+    };
+    let pointee = Box::new(pointee);
+
+    match mutability {
+        Some("mut") => Ok((input, NLType::MutablePointer(pointee))),
+        _ => Ok((input, NLType::Pointer(pointee))),
+    }
+}
+
+fn read_shared_type(input: &str) -> ParserResult<NLType> {
+    let (input, kind) = alt((keyword("rc"), keyword("weak")))(input)?;
+    let (input, value) = read_variable_type(input)?;
+    let value = Box::new(value);
+
+    match kind {
+        "rc" => Ok((input, NLType::Shared(value))),
+        _ => Ok((input, NLType::Weak(value))),
+    }
+}
+
+fn read_tuple_type(input: &str) -> ParserResult<NLType> {
+    let (input, _) = char('(')(input)?;
+
+    let (input, mut items) =
+        many0(terminated(read_variable_type, tuple((blank, char(',')))))(input)?;
+    let (input, last_item) = opt(read_variable_type)(input)?;
+
+    let (input, _) = blank(input)?;
+    let (input, _) = char(')')(input)?;
+
+    // `(T)` is just T in brackets. A tuple of one needs a trailing comma: `(T,)`.
+    match last_item {
+        Some(item) if items.is_empty() => Ok((input, item)),
+        Some(item) => {
+            items.push(item);
+            Ok((input, NLType::Tuple(items)))
+        }
+        None => Ok((input, NLType::Tuple(items))),
+    }
+}
+
 fn read_variable_type_no_whitespace(input: &str) -> ParserResult<NLType> {
     fn read_advanced_types(input: &str) -> ParserResult<NLType> {
         // Could it be a referenced string?
@@ -1752,17 +3583,27 @@ fn read_variable_type_no_whitespace(input: &str) -> ParserResult<NLType> {
         let (input, is_referenced_string) = opt(preceded(blank, tag("str")))(input)?;
         let is_referenced_string = is_referenced_string.is_some();
         if is_referenced_string {
-            return Ok((input, NLType::BorrowedString));
+            Ok((input, NLType::BorrowedString))
+        } else if let (input, Some(_)) = opt(keyword("bytes"))(input)? {
+            Ok((input, NLType::Bytes))
         } else {
             // Okay so we ether have Struct or Trait. Could even be a reference.
-            return identify_struct_or_trait_type(input);
+            identify_struct_or_trait_type(input)
         }
     }
 
-    alt((
-        read_variable_type_primitive_no_whitespace,
-        read_advanced_types,
-    ))(input)
+    expect_one_of(
+        input,
+        "expected a type",
+        &mut [
+            &mut read_primitive_type,
+            &mut read_pointer_type,
+            &mut read_shared_type,
+            &mut read_tuple_type,
+            &mut read_collection_type,
+            &mut read_advanced_types,
+        ],
+    )
 }
 
 fn read_variable_type(input: &str) -> ParserResult<NLType> {
@@ -1770,6 +3611,65 @@ fn read_variable_type(input: &str) -> ParserResult<NLType> {
     read_variable_type_no_whitespace(input)
 }
 
+fn read_bit_fields<'a>(input: &'a str, backing: &NLType) -> ParserResult<'a, Vec<NLBitField<'a>>> {
+    if !backing.is_integer() {
+        return Err(verbose_error(
+            input,
+            "bit fields need an integer to live in",
+        ));
+    }
+
+    let (input, _) = char('{')(input)?;
+
+    let mut bit_fields = vec![];
+    let mut offset: u32 = 0;
+    let mut input = input;
+    loop {
+        let (remaining, _) = blank(input)?;
+        if let (remaining, Some(_)) = opt(char('}'))(remaining)? {
+            input = remaining;
+            break;
+        }
+
+        let (remaining, name) = read_variable_name(remaining)?;
+        let (remaining, _) = tuple((blank, char(':'), blank))(remaining)?;
+        let width_input = remaining;
+        let (remaining, width) = map_res(digit1, u16::from_str)(remaining)?;
+
+        if width == 0 {
+            return Err(verbose_error(
+                width_input,
+                "a bit field must be at least one bit wide",
+            ));
+        }
+        if offset + width as u32 > backing.num_bits() as u32 {
+            return Err(verbose_error(
+                width_input,
+                "bit fields don't fit in their backing integer",
+            ));
+        }
+
+        bit_fields.push(NLBitField {
+            name,
+            offset: offset as u16,
+            width,
+        });
+        offset += width as u32;
+
+        let (remaining, _) = blank(remaining)?;
+        let (remaining, separator) = opt(alt((char(','), char('}'))))(remaining)?;
+        input = remaining;
+
+        match separator {
+            Some(',') => {}
+            Some(_) => break,
+            None => return Err(verbose_error(input, "expected `,` or `}` after bit field")),
+        }
+    }
+
+    Ok((input, bit_fields))
+}
+
 fn read_struct_variable(input: &str) -> ParserResult<NLStructVariable> {
     let (input, _) = blank(input)?;
     let (input, name) = read_variable_name(input)?;
@@ -1779,9 +3679,17 @@ fn read_struct_variable(input: &str) -> ParserResult<NLStructVariable> {
     let (input, _) = blank(input)?;
     let (input, nl_type) = read_variable_type(input)?;
 
+    let (input, _) = blank(input)?;
+    let (input, bit_fields) = if input.starts_with('{') {
+        read_bit_fields(input, &nl_type)?
+    } else {
+        (input, vec![])
+    };
+
     let var = NLStructVariable {
         name,
         my_type: nl_type,
+        bit_fields,
     };
 
     Ok((input, var))
@@ -1789,49 +3697,157 @@ fn read_struct_variable(input: &str) -> ParserResult<NLStructVariable> {
 
 fn read_implementation(input: &str) -> ParserResult<NLImplementation> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("impl")(input)?;
+    let (input, _) = keyword("impl")(input)?;
     let (input, name) = read_struct_or_trait_name(input)?;
-    let (input, _) = char('{')(input)?;
-    let (input, _) = blank(input)?;
-    let (input, methods) = many0(alt((read_method, read_getter, read_setter)))(input)?;
+
+    read_implementation_body(input, name, None)
+}
+
+fn read_standalone_implementation(input: &str) -> ParserResult<RootDeceleration> {
     let (input, _) = blank(input)?;
-    let (input, _) = char('}')(input)?;
+    let (input, _) = keyword("impl")(input)?;
+
+    // There are no type parameters yet, so `impl<T: Printable> Printable for List<T>` can't be read as anything else.
+    if let (_, Some(_)) = opt(peek(tuple((blank, char('<')))))(input)? {
+        return Err(NomErr::Failure(VerboseError {
+            errors: vec![(
+                input,
+                VerboseErrorKind::Context(
+                    "implementations can't have type parameters, there are no generics yet",
+                ),
+            )],
+        }));
+    }
+    let (input, name) = read_struct_or_trait_name(input)?;
+    let (input, _) = keyword("for")(input)?;
+    let (input, target) = read_struct_or_trait_name(input)?;
+
+    let (input, implementation) = read_implementation_body(input, name, Some(target))?;
+
+    Ok((input, RootDeceleration::Implementation(implementation)))
+}
+
+fn read_implementation_body<'a>(
+    input: &'a str,
+    name: &'a str,
+    target: Option<&'a str>,
+) -> ParserResult<'a, NLImplementation<'a>> {
+    let (input, _) = char('{')(input)?;
+    let (input, methods) = read_implementors(input)?;
 
     let implementation = NLImplementation {
         name,
+        target,
         implementors: methods,
     };
 
     Ok((input, implementation))
 }
 
+fn read_repr_attribute(input: &str) -> ParserResult<StructAttribute> {
+    let (input, _) = keyword("repr")(input)?;
+    let (input, _) = tuple((blank, char('('), blank))(input)?;
+
+    let (input, repr) = opt(alt((
+        value(Repr::C, keyword("c")),
+        value(Repr::Packed, keyword("packed")),
+    )))(input)?;
+    let repr = match repr {
+        Some(repr) => repr,
+        None => return Err(verbose_error(input, "expected `c` or `packed`")),
+    };
+
+    let (input, _) = tuple((blank, char(')')))(input)?;
+
+    Ok((input, StructAttribute::Repr(repr)))
+}
+
+fn read_serializable_attribute(input: &str) -> ParserResult<StructAttribute> {
+    let (input, _) = keyword("serializable")(input)?;
+    let (input, _) = tuple((blank, char('('), blank))(input)?;
+    let (input, _) = keyword("format")(input)?;
+    let (input, _) = tuple((blank, char('='), blank))(input)?;
+
+    let (input, format) = opt(value(SerialFormat::Binary, tag("\"binary\"")))(input)?;
+    let format = match format {
+        Some(format) => format,
+        None => {
+            return Err(verbose_error(
+                input,
+                "the only serialization format is \"binary\"",
+            ))
+        }
+    };
+
+    let (input, _) = tuple((blank, char(')')))(input)?;
+
+    Ok((input, StructAttribute::Serializable(format)))
+}
+
+fn read_struct_attribute(input: &str) -> ParserResult<StructAttribute> {
+    let (input, _) = tuple((char('#'), blank, char('['), blank))(input)?;
+    let (input, attribute) = expect_one_of(
+        input,
+        "expected `repr` or `serializable`",
+        &mut [&mut read_repr_attribute, &mut read_serializable_attribute],
+    )?;
+    let (input, _) = tuple((blank, char(']'), blank))(input)?;
+
+    Ok((input, attribute))
+}
+
 fn read_struct(input: &str) -> ParserResult<RootDeceleration> {
     let (input, _) = blank(input)?;
-    let (input, _) = tag("struct")(input)?;
+
+    let mut repr = Repr::Default;
+    let mut serialization = None;
+    let mut input = input;
+    while input.starts_with('#') {
+        let (remaining, attribute) = read_struct_attribute(input)?;
+        match attribute {
+            StructAttribute::Repr(new_repr) => repr = new_repr,
+            StructAttribute::Serializable(format) => serialization = Some(format),
+        }
+        input = remaining;
+    }
+
+    let (input, _) = keyword("struct")(input)?;
     let (input, _) = blank(input)?;
     let (input, name) = read_struct_or_trait_name(input)?;
     let (input, _) = blank(input)?;
     let (input, _) = char('{')(input)?;
     let (input, _) = blank(input)?;
-    let (input, mut variables) =
-        many0(terminated(read_struct_variable, tuple((blank, char(',')))))(input)?;
-    let (input, _) = blank(input)?;
 
-    // Need to read the last struct.
-    let (input, last_var) = opt(read_struct_variable)(input)?;
-    match last_var {
-        Some(var) => {
-            variables.push(var);
+    let mut variables = vec![];
+    let mut input = input;
+    loop {
+        // An empty struct, or a trailing comma after the last field.
+        if let (remaining, Some(_)) = opt(char('}'))(input)? {
+            input = remaining;
+            break;
+        }
+
+        let (remaining, var) = read_struct_variable(input)?;
+        variables.push(var);
+
+        let (remaining, _) = blank(remaining)?;
+        let (remaining, separator) = opt(alt((char(','), char('}'))))(remaining)?;
+        let (remaining, _) = blank(remaining)?;
+        input = remaining;
+
+        match separator {
+            Some(',') => {}
+            Some(_) => break,
+            None => return Err(verbose_error(input, "expected `,` or `}` after field")),
         }
-        _ => {} // Do nothing if we didn't have a last one.
     }
 
-    let (input, _) = blank(input)?;
-    let (input, _) = char('}')(input)?;
-    let (input, implementations) = many0(read_implementation)(input)?;
+    let (input, implementations) = many0(traced("implementation", read_implementation))(input)?;
 
     let nl_struct = NLStruct {
         name,
+        repr,
+        serialization,
         variables,
         implementations,
     };
@@ -1839,72 +3855,246 @@ fn read_struct(input: &str) -> ParserResult<RootDeceleration> {
     Ok((input, RootDeceleration::Struct(nl_struct)))
 }
 
-fn parse_file_root(input: &str) -> ParserResult<NLFile> {
+fn read_root_deceleration(input: &str) -> ParserResult<RootDeceleration> {
+    expect_one_of(
+        input,
+        "expected struct, trait, fn, or enum",
+        &mut [
+            &mut traced("struct", read_struct),
+            &mut traced("trait", read_trait),
+            &mut traced("function", read_function),
+            &mut traced("enum", read_variant_enum),
+            &mut traced("implementation", read_standalone_implementation),
+            &mut traced("macro", read_macro),
+        ],
+    )
+}
+
+/// Moves standalone implementations onto their struct once the whole file has been read, so an `impl` can come before
+/// the struct it's for. Implementations for structs in other files are left on the file.
+fn link_implementations(file: &mut NLFile) {
+    let implementations = std::mem::take(&mut file.implementations);
+
+    for implementation in implementations {
+        let target = implementation.target;
+        match file
+            .structs
+            .iter_mut()
+            .find(|nl_struct| Some(nl_struct.name) == target)
+        {
+            Some(nl_struct) => nl_struct.implementations.push(implementation),
+            None => file.implementations.push(implementation),
+        }
+    }
+}
+
+fn read_root_declarations(input: &str) -> ParserResult<Vec<RootDeceleration>> {
+    let (input, root_defs) = many0(read_root_deceleration)(input)?;
+    let (input, _) = blank(input)?;
+
+    if !input.is_empty() {
+        // Reading the leftover as a declaration again explains why it isn't one.
+        read_root_deceleration(input)?;
+        return Err(verbose_error(
+            input,
+            "unexpected input after the last declaration",
+        ));
+    }
+
+    Ok((input, root_defs))
+}
+
+// Also gives back the upgrade notes for syntax that means something else in a later version of the language.
+fn parse_file_root(
+    input: &str,
+    default_version: LanguageVersion,
+) -> ParserResult<(NLFile, Vec<UpgradeNote>)> {
+    let source = input;
+    let (input, pragmas) = pragmas::read_pragmas(input)?;
+    let language_version = pragmas.version.unwrap_or(default_version);
+
+    let (input, root_defs, upgrade_notes) =
+        pragmas::with_pragmas(&pragmas.features, language_version, || {
+            read_root_declarations(input)
+                .map(|(input, root_defs)| (input, root_defs, pragmas::take_upgrade_notes(source)))
+        })?;
+
     let mut file = NLFile {
         name: String::new(),
+        source,
+        features: pragmas.features,
+        language_version,
+        lint_levels: pragmas.lint_levels,
         structs: vec![],
         traits: vec![],
         functions: vec![],
         enums: vec![],
+        implementations: vec![],
+        macros: vec![],
     };
 
-    if !input.is_empty() {
-        let (input, root_defs) = many1(alt((
-            read_struct,
-            read_trait,
-            read_function,
-            read_variant_enum,
-        )))(input)?;
-
-        for root_def in root_defs {
-            match root_def {
-                RootDeceleration::Struct(nl_struct) => {
-                    file.structs.push(nl_struct);
-                }
-                RootDeceleration::Trait(nl_trait) => {
-                    file.traits.push(nl_trait);
-                }
-                RootDeceleration::Function(nl_func) => {
-                    file.functions.push(nl_func);
-                }
-                RootDeceleration::Enum(nl_enum) => {
-                    file.enums.push(nl_enum);
-                }
+    for root_def in root_defs {
+        match root_def {
+            RootDeceleration::Struct(nl_struct) => {
+                file.structs.push(nl_struct);
+            }
+            RootDeceleration::Trait(nl_trait) => {
+                file.traits.push(nl_trait);
+            }
+            RootDeceleration::Function(nl_func) => {
+                file.functions.push(nl_func);
+            }
+            RootDeceleration::Enum(nl_enum) => {
+                file.enums.push(nl_enum);
+            }
+            RootDeceleration::Implementation(implementation) => {
+                file.implementations.push(implementation);
+            }
+            RootDeceleration::Macro(nl_macro) => {
+                file.macros.push(nl_macro);
             }
         }
-
-        Ok((input, file))
-    } else {
-        Ok((input, file))
     }
+
+    link_implementations(&mut file);
+
+    Ok((input, (file, upgrade_notes)))
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ParseOptions {
+    // Record the parser rules attempted and report them as a note when parsing fails.
+    pub trace: bool,
+    // The version to read files in when they don't declare one with `#![nl_version = "..."]`.
+    pub language_version: LanguageVersion,
+    // Where to look for included files that aren't next to the file including them.
+    pub include_paths: Vec<PathBuf>,
+    // What to do about code each lint catches, unless the file says otherwise with a pragma.
+    pub lint_levels: Vec<(Lint, LintLevel)>,
+    // Files merged into every file read from disk, after its pragmas, unless it has `#![no_prelude]`. They're looked
+    // for like includes, so a path can be relative to one of the include paths.
+    pub prelude: Vec<PathBuf>,
 }
 
 pub fn parse_string<'a>(input: &'a str, file_name: &str) -> Result<NLFile<'a>, ParseError> {
-    let file = parse_file_root(input);
+    // The error is returned to the caller anyway, so there's nothing else to report to.
+    parse_string_with_sink(input, file_name, &mut Vec::new())
+}
+
+pub fn parse_string_with_sink<'a>(
+    input: &'a str,
+    file_name: &str,
+    sink: &mut dyn DiagnosticSink,
+) -> Result<NLFile<'a>, ParseError> {
+    parse_string_with_options(input, file_name, &ParseOptions::default(), sink)
+}
+
+/// Parses a file and expands what stands for other code in it: macros, reflection and type sizes. What's parsed isn't
+/// checked any further than that, which `checking::check_file` does.
+pub fn parse_string_with_options<'a>(
+    input: &'a str,
+    file_name: &str,
+    options: &ParseOptions,
+    sink: &mut dyn DiagnosticSink,
+) -> Result<NLFile<'a>, ParseError> {
+    // A byte order mark is not part of the source text.
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+    let (mut file, upgrade_notes) =
+        parse_string_with_upgrade_notes(input, file_name, options, sink)?;
+
+    for note in upgrade_notes {
+        let fix = note
+            .fix
+            .iter()
+            .map(|(offset, text)| TextEdit::new(*offset, 0, text.to_string()))
+            .collect();
+        sink.report(Diagnostic::note(format!("{}: {}", file_name, note)).with_fix(fix));
+    }
+
+    // Macros are expanded first, so that everything after sees the code they stand for.
+    let mut errors = macros::expand_macros(&mut file);
+    errors.append(&mut reflection::expand_reflection(&mut file));
+    enums::resolve_enum_types(&mut file);
+    errors.append(&mut layout::expand_type_sizes(&mut file));
+
+    file.lint_levels
+        .splice(0..0, options.lint_levels.iter().copied());
+
+    if !errors.is_empty() {
+        let mut messages = vec![];
+        for error in errors {
+            let message = format!("{}: {}", file_name, error);
+            sink.report(Diagnostic::error(message.clone()));
+            messages.push(message);
+        }
+        return Err(ParseError {
+            message: messages.join("\n"),
+        });
+    }
+
+    Ok(file)
+}
+
+// The fix for a parse error, for the mistakes that are easy to be sure about. That's a missing comma between the
+// fields of a struct, which goes straight after the field before it unless there's a comment in the way.
+fn parse_error_fix(input: &str, error: &VerboseError<&str>) -> Vec<TextEdit> {
+    let (at, message) = match error.errors.first() {
+        Some((at, VerboseErrorKind::Context(message))) => (at, *message),
+        _ => return vec![],
+    };
+    let missing_comma = [
+        "expected `,` or `}` after field",
+        "expected `,` or `}` after bit field",
+    ];
+    if !missing_comma.contains(&message) {
+        return vec![];
+    }
+
+    let mut offset = input.len() - at.len();
+    let before = input[..offset].trim_end();
+    let last_line = before.rsplit('\n').next().unwrap_or(before);
+    if !last_line.contains("//") && !before.ends_with("*/") {
+        offset = before.len();
+    }
+
+    vec![TextEdit::new(offset, 0, ",".to_string())]
+}
+
+// Parses the file without reporting its upgrade notes, which are given back instead. Any byte order mark should already
+// have been removed.
+fn parse_string_with_upgrade_notes<'a>(
+    input: &'a str,
+    file_name: &str,
+    options: &ParseOptions,
+    sink: &mut dyn DiagnosticSink,
+) -> Result<(NLFile<'a>, Vec<UpgradeNote>), ParseError> {
+    let (file, trace) = instrumentation::parse_file(input, file_name, options.trace, || {
+        parse_file_root(input, options.language_version)
+    });
 
     match file {
         Result::Err(err) => {
-            match err {
+            let (message, fix) = match err {
                 nom::Err::Error(e) | nom::Err::Failure(e) => {
-                    let message = convert_error(input, e);
-
-                    // Makes our error messages more readable when running tests.
-                    #[cfg(test)]
-                    println!("{}", message);
-
-                    Err(ParseError { message })
+                    let fix = parse_error_fix(input, &e);
+                    (convert_error(input, e), fix)
                 }
-                nom::Err::Incomplete(_) => Err(ParseError {
-                    message: "Unexpected end of file.".to_string(),
-                }),
+                nom::Err::Incomplete(_) => ("Unexpected end of file.".to_string(), vec![]),
+            };
+
+            sink.report(Diagnostic::error(message.clone()).with_fix(fix));
+            if let Some(trace) = trace {
+                sink.report(Diagnostic::note(trace));
             }
+
+            Err(ParseError { message })
         }
         Result::Ok(result) => {
-            let (_, mut file) = result;
-
+            let (_, (mut file, upgrade_notes)) = result;
             file.name = file_name.to_string();
 
-            Ok(file)
+            Ok((file, upgrade_notes))
         }
     }
 }
@@ -1913,16 +4103,36 @@ pub fn parse_file<T>(
     path: &Path,
     function: &dyn Fn(&NLFile) -> T,
 ) -> Result<T, Box<dyn std::error::Error>> {
-    let mut input_file = File::open(&path)?;
+    parse_file_with_options(path, &ParseOptions::default(), &mut Vec::new(), function)
+}
 
-    let mut contents = String::new();
-    input_file.read_to_string(&mut contents)?;
+/// Reads the file at `path` with what it includes merged in, and parses it. Lines in what's reported are lines of the
+/// file they're in, but spans and fixes are offsets into the merged text.
+pub fn parse_file_with_options<T>(
+    path: &Path,
+    options: &ParseOptions,
+    sink: &mut dyn DiagnosticSink,
+    function: &dyn Fn(&NLFile) -> T,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let (contents, lines) =
+        includes::read_with_line_map(path, &options.include_paths, &options.prelude)?;
 
     // This should *always* have a name since we shouldn't have been able to get to this point if it wasn't actually a file.
-    let result = parse_string(&contents, &path.file_name().unwrap().to_str().unwrap());
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let result = parse_string_with_options(
+        &contents,
+        &path.file_name().unwrap().to_str().unwrap(),
+        options,
+        &mut diagnostics,
+    );
+    for diagnostic in diagnostics {
+        sink.report(diagnostic.map_message(|message| lines.locate_in(message)));
+    }
 
     match result {
         Ok(result) => Ok(function(&result)),
-        Err(error) => Err(Box::new(error)),
+        Err(error) => Err(Box::new(ParseError {
+            message: lines.locate_in(&error.message),
+        })),
     }
 }
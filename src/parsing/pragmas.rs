@@ -0,0 +1,391 @@
+// Pragmas are `#![...]` lines at the top of a file that change how the rest of it is read. `#![feature(name)]`
+// switches on experimental syntax, which is otherwise rejected, and `#![nl_version = "0.2"]` picks which version of
+// the language the file is written in. Both exist so that files written before a change to the grammar keep meaning
+// what they did. `#![allow(lint)]`, `#![warn(lint)]` and `#![deny(lint)]` pick what's done about code a lint
+// catches, overriding whatever `ParseOptions` asked for. `#![no_prelude]` leaves out the prelude `ParseOptions` would
+// otherwise merge into the file.
+//
+// The parser rules are plain functions, so the pragmas of the file being read are kept per thread while it's parsed,
+// the same way rule traces are.
+
+use super::*;
+use nom::combinator::cut;
+use std::cell::{Cell, RefCell};
+
+/// Experimental syntax that a file has to ask for.
+#[derive(PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Feature {
+    /// `operation?` to return early with an error. Named `error_propagation`.
+    ErrorPropagation,
+}
+
+impl Feature {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Feature::ErrorPropagation => "error_propagation",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Feature> {
+        match name {
+            "error_propagation" => Some(Feature::ErrorPropagation),
+            _ => None,
+        }
+    }
+}
+
+/// Something a file is allowed to do that not everyone wants done.
+#[derive(PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Lint {
+    /// Declaring a variable with the same name as one that's still in scope. Named `shadowing`.
+    Shadowing,
+    /// A struct, enum or trait whose name isn't written in PascalCase. Named `type_names`.
+    TypeNames,
+    /// A function or method whose name isn't written in snake_case. Named `function_names`.
+    FunctionNames,
+}
+
+impl Lint {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Lint::Shadowing => "shadowing",
+            Lint::TypeNames => "type_names",
+            Lint::FunctionNames => "function_names",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Lint> {
+        match name {
+            "shadowing" => Some(Lint::Shadowing),
+            "type_names" => Some(Lint::TypeNames),
+            "function_names" => Some(Lint::FunctionNames),
+            _ => None,
+        }
+    }
+
+    /// What's done when neither the file nor `ParseOptions` say.
+    pub fn get_default_level(&self) -> LintLevel {
+        match self {
+            Lint::Shadowing => LintLevel::Allow,
+            Lint::TypeNames | Lint::FunctionNames => LintLevel::Warn,
+        }
+    }
+}
+
+/// What's done about code a lint catches.
+#[derive(PartialEq, Debug, Clone, Copy, Hash)]
+pub enum LintLevel {
+    Allow,
+    /// Reported as a warning.
+    Warn,
+    /// Reported as an error, so the file fails to parse.
+    Deny,
+}
+
+/// The version of the language a file is written in. Files that don't say are read as whatever `ParseOptions`
+/// asks for, which is 0.1 unless changed, since that's what files were written in before versions existed.
+#[derive(PartialEq, PartialOrd, Debug, Clone, Copy, Hash, Default)]
+pub enum LanguageVersion {
+    /// The original syntax.
+    #[default]
+    V0_1,
+    /// `(x)` is `x` in brackets rather than a tuple of one, the same as it is for types. A tuple of one is `(x,)`.
+    V0_2,
+}
+
+impl LanguageVersion {
+    pub const LATEST: LanguageVersion = LanguageVersion::V0_2;
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            LanguageVersion::V0_1 => "0.1",
+            LanguageVersion::V0_2 => "0.2",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<LanguageVersion> {
+        match name {
+            "0.1" => Some(LanguageVersion::V0_1),
+            "0.2" => Some(LanguageVersion::V0_2),
+            _ => None,
+        }
+    }
+}
+
+// Everything the pragmas at the top of a file asked for.
+pub(super) struct Pragmas<'a> {
+    pub(super) features: Vec<Feature>,
+    pub(super) version: Option<LanguageVersion>,
+    // Where the version was written, quotes included, so that it can be changed.
+    pub(super) version_literal: Option<&'a str>,
+    // In the order they were written, so later ones win.
+    pub(super) lint_levels: Vec<(Lint, LintLevel)>,
+    pub(super) no_prelude: bool,
+}
+
+// Something in a file that means something else in a later version of the language.
+pub(super) struct UpgradeNote {
+    pub(super) line: usize,
+    pub(super) note: &'static str,
+    // Text to insert, and the offset in the file to insert it at, so that it keeps meaning the same thing.
+    pub(super) fix: Option<(usize, &'static str)>,
+}
+
+impl std::fmt::Display for UpgradeNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.note)
+    }
+}
+
+// An upgrade note as it's recorded during parsing, where places are known by how much input was left after them.
+struct RecordedNote {
+    remaining: usize,
+    note: &'static str,
+    fix: Option<(usize, &'static str)>,
+}
+
+thread_local! {
+    static ENABLED_FEATURES: RefCell<Vec<Feature>> = const { RefCell::new(Vec::new()) };
+    static VERSION: Cell<LanguageVersion> = const { Cell::new(LanguageVersion::V0_1) };
+
+    // Places where the file relies on syntax that changes in a later version. Kept by how much input was left so
+    // that a rule attempted more than once at the same place is only noted once.
+    static UPGRADE_NOTES: RefCell<Vec<RecordedNote>> = const { RefCell::new(Vec::new()) };
+}
+
+// Parses the rest of the file with the given features and version. Whatever was set before is put back afterwards.
+pub(super) fn with_pragmas<T>(
+    features: &[Feature],
+    version: LanguageVersion,
+    parse: impl FnOnce() -> T,
+) -> T {
+    let outer_features = ENABLED_FEATURES.with(|enabled| enabled.replace(features.to_vec()));
+    let outer_version = VERSION.with(|current| current.replace(version));
+    let outer_notes = UPGRADE_NOTES.with(|notes| notes.replace(Vec::new()));
+
+    let result = parse();
+
+    ENABLED_FEATURES.with(|enabled| enabled.replace(outer_features));
+    VERSION.with(|current| current.set(outer_version));
+    UPGRADE_NOTES.with(|notes| notes.replace(outer_notes));
+
+    result
+}
+
+pub(super) fn is_enabled(feature: Feature) -> bool {
+    ENABLED_FEATURES.with(|enabled| enabled.borrow().contains(&feature))
+}
+
+pub(super) fn version() -> LanguageVersion {
+    VERSION.with(|current| current.get())
+}
+
+// Records that the syntax at `input` means something else in a later version of the language. If inserting some text
+// at the start of `fix` would keep it meaning the same thing in both, that's recorded too.
+pub(super) fn note_upgrade(input: &str, note: &'static str, fix: Option<(&str, &'static str)>) {
+    UPGRADE_NOTES.with(|notes| {
+        let mut notes = notes.borrow_mut();
+        if !notes
+            .iter()
+            .any(|recorded| recorded.remaining == input.len())
+        {
+            notes.push(RecordedNote {
+                remaining: input.len(),
+                note,
+                fix: fix.map(|(at, text)| (at.len(), text)),
+            });
+        }
+    });
+}
+
+// Takes the upgrade notes recorded while parsing `input`, in the order they appear in it.
+pub(super) fn take_upgrade_notes(input: &str) -> Vec<UpgradeNote> {
+    let mut notes = UPGRADE_NOTES.with(|notes| notes.replace(Vec::new()));
+    notes.sort_by_key(|recorded| std::cmp::Reverse(recorded.remaining));
+
+    notes
+        .into_iter()
+        .map(|recorded| {
+            let offset = input.len() - recorded.remaining;
+            UpgradeNote {
+                line: input[..offset].matches('\n').count() + 1,
+                note: recorded.note,
+                fix: recorded
+                    .fix
+                    .map(|(remaining, text)| (input.len() - remaining, text)),
+            }
+        })
+        .collect()
+}
+
+fn read_name_list<'a, T>(
+    input: &'a str,
+    from_name: impl Fn(&str) -> Option<T>,
+    expected: &'static str,
+    unknown: &'static str,
+) -> ParserResult<'a, Vec<T>> {
+    let (mut input, _) = tuple((blank, char('(')))(input)?;
+
+    let mut names = vec![];
+    loop {
+        let (remaining, _) = blank(input)?;
+        let (remaining, name) = match read_variable_name(remaining) {
+            Ok(result) => result,
+            Err(_) => return Err(verbose_error(remaining, expected)),
+        };
+        match from_name(name) {
+            Some(found) => names.push(found),
+            None => return Err(verbose_error(remaining, unknown)),
+        }
+
+        let (remaining, _) = blank(remaining)?;
+        match opt(char(','))(remaining)? {
+            (remaining, Some(_)) => input = remaining,
+            (remaining, None) => {
+                input = remaining;
+                break;
+            }
+        }
+    }
+
+    let (input, _) = tuple((blank, char(')'), blank, char(']')))(input)?;
+
+    Ok((input, names))
+}
+
+fn read_feature_list(input: &str) -> ParserResult<Vec<Feature>> {
+    read_name_list(
+        input,
+        Feature::from_name,
+        "expected the name of a feature",
+        "unknown feature",
+    )
+}
+
+fn read_lint_list(input: &str) -> ParserResult<Vec<Lint>> {
+    read_name_list(
+        input,
+        Lint::from_name,
+        "expected the name of a lint",
+        "unknown lint",
+    )
+}
+
+// Also gives back the version as it was written.
+fn read_version(input: &str) -> ParserResult<(LanguageVersion, &str)> {
+    let (input, _) = tuple((blank, char('='), blank))(input)?;
+    let version_input = input;
+    let (input, version) = match read_string_constant(input) {
+        Ok((input, OpConstant::String(version))) => (input, version),
+        _ => return Err(verbose_error(input, "expected the version in quotes")),
+    };
+    let literal = &version_input[..version_input.len() - input.len()];
+
+    match LanguageVersion::from_name(&version) {
+        Some(version) => {
+            let (input, _) = tuple((blank, char(']')))(input)?;
+            Ok((input, (version, literal)))
+        }
+        None => Err(verbose_error(
+            version_input,
+            "unknown language version, expected \"0.1\" or \"0.2\"",
+        )),
+    }
+}
+
+enum Pragma<'a> {
+    Features(Vec<Feature>),
+    Version(LanguageVersion, &'a str),
+    Lints(LintLevel, Vec<Lint>),
+    NoPrelude,
+}
+
+fn read_pragma(input: &str) -> ParserResult<Pragma> {
+    let (input, _) = tuple((blank, char('#'), char('!'), char('['), blank))(input)?;
+
+    // Once the name has been read this is definitely a pragma, so a mistake in the rest is reported rather than left
+    // for the root to trip over.
+    let names = (
+        keyword("feature"),
+        keyword("nl_version"),
+        keyword("allow"),
+        keyword("warn"),
+        keyword("deny"),
+        keyword("no_prelude"),
+    );
+    match alt(names)(input)? {
+        (input, "feature") => {
+            let (input, features) = cut(read_feature_list)(input)?;
+            Ok((input, Pragma::Features(features)))
+        }
+        (input, "nl_version") => {
+            let (input, (version, literal)) = cut(read_version)(input)?;
+            Ok((input, Pragma::Version(version, literal)))
+        }
+        (input, "no_prelude") => {
+            let (input, _) = cut(tuple((blank, char(']'))))(input)?;
+            Ok((input, Pragma::NoPrelude))
+        }
+        (input, level) => {
+            let level = match level {
+                "allow" => LintLevel::Allow,
+                "warn" => LintLevel::Warn,
+                _ => LintLevel::Deny,
+            };
+            let (input, lints) = cut(read_lint_list)(input)?;
+            Ok((input, Pragma::Lints(level, lints)))
+        }
+    }
+}
+
+// Reads every pragma at the start of the file.
+pub(super) fn read_pragmas(input: &str) -> ParserResult<Pragmas> {
+    let (input, pragmas) = many0(read_pragma)(input)?;
+
+    let mut features = vec![];
+    let mut version = None;
+    let mut version_literal = None;
+    let mut lint_levels = vec![];
+    let mut no_prelude = false;
+    for pragma in pragmas {
+        match pragma {
+            Pragma::Features(list) => features.extend(list),
+            Pragma::Version(declared, literal) => {
+                version = Some(declared);
+                version_literal = Some(literal);
+            }
+            Pragma::Lints(level, lints) => {
+                lint_levels.extend(lints.into_iter().map(|lint| (lint, level)))
+            }
+            Pragma::NoPrelude => no_prelude = true,
+        }
+    }
+
+    Ok((
+        input,
+        Pragmas {
+            features,
+            version,
+            version_literal,
+            lint_levels,
+            no_prelude,
+        },
+    ))
+}
+
+// Reported when a file uses syntax behind a feature it didn't enable.
+pub(super) fn require_feature(input: &str, feature: Feature) -> ParserResult<()> {
+    if is_enabled(feature) {
+        Ok((input, ()))
+    } else {
+        let message = match feature {
+            Feature::ErrorPropagation => {
+                "`?` is experimental, enable it with `#![feature(error_propagation)]`"
+            }
+        };
+
+        Err(NomErr::Failure(VerboseError {
+            errors: vec![(input, VerboseErrorKind::Context(message))],
+        }))
+    }
+}
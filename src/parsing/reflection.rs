@@ -0,0 +1,159 @@
+// Replaces `name_of(T)`, `fields_of(T)` and `variants_of(T)` with what they stand for once the whole file has been
+// read, since the type they ask about can be declared anywhere in it. `name_of` gives the name of a struct, enum or
+// trait as a string, `fields_of` a list of a struct's field names and `variants_of` a list of an enum's variant names,
+// all in the order they were declared.
+
+use super::*;
+use crate::diagnostics::did_you_mean;
+
+struct Declarations {
+    structs: Vec<(String, Vec<String>)>,
+    enums: Vec<(String, Vec<String>)>,
+    traits: Vec<String>,
+}
+
+impl Declarations {
+    // `asked` says which query it was and where, like "name_of in `main`".
+    fn unknown(&self, name: &str, asked: &str) -> String {
+        let names = self
+            .structs
+            .iter()
+            .chain(&self.enums)
+            .map(|(name, _)| name.as_str())
+            .chain(self.traits.iter().map(String::as_str));
+
+        format!(
+            "unknown type `{}` given to {}{}",
+            name,
+            asked,
+            did_you_mean(name, names)
+        )
+    }
+
+    fn kind_of(&self, name: &str) -> Option<&'static str> {
+        if self.structs.iter().any(|(declared, _)| declared == name) {
+            Some("a struct")
+        } else if self.enums.iter().any(|(declared, _)| declared == name) {
+            Some("an enum")
+        } else if self.traits.iter().any(|declared| declared == name) {
+            Some("a trait")
+        } else {
+            None
+        }
+    }
+
+    fn expand<'a>(
+        &self,
+        body: &str,
+        operation: &NLOperation<'a>,
+    ) -> Result<Option<NLOperation<'a>>, String> {
+        fn names<'a>(names: &[String]) -> NLOperation<'a> {
+            NLOperation::List(
+                names
+                    .iter()
+                    .map(|name| NLOperation::Constant(OpConstant::String(name.clone())))
+                    .collect(),
+            )
+        }
+
+        let (query, nl_type) = match operation {
+            NLOperation::NameOf(nl_type) => ("name_of", nl_type),
+            NLOperation::FieldsOf(nl_type) => ("fields_of", nl_type),
+            NLOperation::VariantsOf(nl_type) => ("variants_of", nl_type),
+            _ => return Ok(None),
+        };
+        let asked = format!("{} in `{}`", query, body);
+
+        // Structs, enums and traits are all written the same way when they're used as a type.
+        let name = match nl_type {
+            NLType::OwnedStruct(name) | NLType::OwnedTrait(name) | NLType::Enum(name) => *name,
+            _ => {
+                return Err(format!(
+                    "{} needs the name of a struct, enum or trait",
+                    asked
+                ))
+            }
+        };
+        let kind = match self.kind_of(name) {
+            Some(kind) => kind,
+            None => return Err(self.unknown(name, &asked)),
+        };
+
+        let found = match query {
+            "name_of" => Some(NLOperation::Constant(OpConstant::String(name.to_string()))),
+            "fields_of" => self
+                .structs
+                .iter()
+                .find(|(declared, _)| declared == name)
+                .map(|(_, fields)| names(fields)),
+            _ => self
+                .enums
+                .iter()
+                .find(|(declared, _)| declared == name)
+                .map(|(_, variants)| names(variants)),
+        };
+
+        match (found, query) {
+            (Some(expanded), _) => Ok(Some(expanded)),
+            (None, "fields_of") => Err(format!(
+                "{} needs a struct, but `{}` is {}",
+                asked, name, kind
+            )),
+            (None, _) => Err(format!(
+                "{} needs an enum, but `{}` is {}",
+                asked, name, kind
+            )),
+        }
+    }
+
+    fn expand_block(&self, body: &str, block: &mut NLBlock, errors: &mut Vec<String>) {
+        block.walk_mut(&mut |operation| match self.expand(body, operation) {
+            Ok(Some(expanded)) => *operation = expanded,
+            Ok(None) => {}
+            Err(error) => errors.push(error),
+        });
+    }
+}
+
+// Expands every reflection query in the file. Gives back an error for each one that asks about something that
+// was never declared, or asks for fields or variants that kind of type doesn't have.
+pub(super) fn expand_reflection(file: &mut NLFile) -> Vec<String> {
+    let declarations = Declarations {
+        structs: file
+            .structs
+            .iter()
+            .map(|nl_struct| {
+                let fields = nl_struct
+                    .variables
+                    .iter()
+                    .map(|variable| variable.name.to_string())
+                    .collect();
+                (nl_struct.name.to_string(), fields)
+            })
+            .collect(),
+        enums: file
+            .enums
+            .iter()
+            .map(|nl_enum| {
+                let variants = nl_enum
+                    .variants
+                    .iter()
+                    .map(|variant| variant.name.to_string())
+                    .collect();
+                (nl_enum.name.to_string(), variants)
+            })
+            .collect(),
+        traits: file
+            .traits
+            .iter()
+            .map(|nl_trait| nl_trait.name.to_string())
+            .collect(),
+    };
+
+    let mut errors = vec![];
+    for (body, block) in file.bodies_mut() {
+        declarations.expand_block(body, block, &mut errors);
+    }
+
+    errors
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::diagnostics::{apply_fixes, Severity};
 
 use unwrap_to::unwrap_to;
 
@@ -68,6 +69,28 @@ mod root {
         assert_eq!(file.name, file_name, "File name not copied correctly.");
     }
 
+    #[test]
+    /// A file of only whitespace and comments is an empty file.
+    fn blank_file() {
+        let file = parse_string(" \n\t// Nothing here.\n/* Or here. */\n", "test_file.nl").unwrap();
+
+        assert!(file.structs.is_empty(), "Unexpected structs.");
+        assert!(file.traits.is_empty(), "Unexpected traits.");
+        assert!(file.functions.is_empty(), "Unexpected functions.");
+        assert!(file.enums.is_empty(), "Unexpected enums.");
+    }
+
+    #[test]
+    /// A leading byte order mark is skipped.
+    fn byte_order_mark() {
+        let file = parse_string("\u{feff}", "test_file.nl").unwrap();
+        assert!(file.structs.is_empty(), "Unexpected structs.");
+
+        let file = parse_string("\u{feff}struct MyStruct {}", "test_file.nl").unwrap();
+        assert_eq!(file.structs.len(), 1, "Wrong number of structs.");
+        assert_eq!(file.structs[0].name, "MyStruct", "Wrong name for struct.");
+    }
+
     #[test]
     /// Compile a file with an empty struct and an empty trait. We should get no errors or warnings.
     fn empty_struct_and_trait() {
@@ -107,6 +130,156 @@ mod root {
         }
     }
 
+    #[test]
+    /// Errors are handed to the diagnostic sink as well as being returned.
+    fn errors_reported_to_sink() {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let result = parse_string_with_sink("struct {", "test_file.nl", &mut diagnostics);
+
+        let error = result.err().expect("No error when one was expected.");
+        assert_eq!(diagnostics.len(), 1, "Wrong number of diagnostics.");
+        assert_eq!(diagnostics[0].get_severity(), Severity::Error);
+        assert_eq!(diagnostics[0].get_message(), error.to_string());
+    }
+
+    #[test]
+    /// A file without problems doesn't report anything.
+    fn nothing_reported_to_sink() {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        parse_string_with_sink("struct MyStruct {}", "test_file.nl", &mut diagnostics).unwrap();
+
+        assert!(diagnostics.is_empty(), "Unexpected diagnostics.");
+    }
+
+    #[test]
+    /// With tracing on, a failed parse also reports the rules that were attempted.
+    fn trace_reported_on_failure() {
+        let options = ParseOptions {
+            trace: true,
+            ..ParseOptions::default()
+        };
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let result =
+            parse_string_with_options("\nstruct {", "test_file.nl", &options, &mut diagnostics);
+
+        assert!(result.is_err(), "No error when one was expected.");
+        assert_eq!(diagnostics.len(), 2, "Wrong number of diagnostics.");
+        assert_eq!(diagnostics[1].get_severity(), Severity::Note);
+
+        let trace = diagnostics[1].get_message();
+        assert!(
+            trace.contains("struct at line 1, column 1: failed"),
+            "{}",
+            trace
+        );
+        assert!(
+            trace.contains("enum at line 1, column 1: failed"),
+            "{}",
+            trace
+        );
+    }
+
+    #[test]
+    /// Nested rules are indented under the rule that attempted them.
+    fn trace_nesting() {
+        let options = ParseOptions {
+            trace: true,
+            ..ParseOptions::default()
+        };
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let result = parse_string_with_options(
+            "trait MyTrait { met a(); met b() {",
+            "test_file.nl",
+            &options,
+            &mut diagnostics,
+        );
+
+        // The second method is dropped, but the trait still fails to close.
+        assert!(result.is_err(), "No error when one was expected.");
+        let trace = diagnostics[1].get_message();
+        assert!(
+            trace.contains("\ntrait at line 1, column 1: failed"),
+            "{}",
+            trace
+        );
+        assert!(
+            trace.contains("\n  method at line 1, column 17: matched"),
+            "{}",
+            trace
+        );
+    }
+
+    #[test]
+    /// Nothing is traced when parsing succeeds.
+    fn no_trace_on_success() {
+        let options = ParseOptions {
+            trace: true,
+            ..ParseOptions::default()
+        };
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        parse_string_with_options("fn f() {}", "test_file.nl", &options, &mut diagnostics).unwrap();
+
+        assert!(diagnostics.is_empty(), "Unexpected diagnostics.");
+    }
+
+    #[test]
+    /// When no root declaration fits, the error comes from the one that got furthest.
+    fn furthest_root_error_reported() {
+        let result = parse_string("struct MyStruct {\n    a i32\n}", "test_file.nl");
+        let message = result
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(
+            message.contains("expected struct, trait, fn, or enum"),
+            "{}",
+            message
+        );
+        assert!(message.starts_with("0: at line 2:"), "{}", message);
+    }
+
+    #[test]
+    /// Input left over after the last declaration is an error rather than being ignored.
+    fn trailing_input_rejected() {
+        let result = parse_string("struct MyStruct {}\nI shouldn't be here.", "test_file.nl");
+        let message = result
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(
+            message.contains("expected struct, trait, fn, or enum"),
+            "{}",
+            message
+        );
+        assert!(message.starts_with("0: at line 2,"), "{}", message);
+    }
+
+    #[test]
+    /// Trailing whitespace and comments after the last declaration are fine.
+    fn trailing_blank_accepted() {
+        let result = parse_string("struct MyStruct {}\n\n// The end.\n", "test_file.nl");
+        assert!(
+            result.is_ok(),
+            "{:?}",
+            result.err().map(|error| error.to_string())
+        );
+    }
+
+    #[test]
+    /// Operations report the alternative that got furthest, rather than the last one tried.
+    fn furthest_operation_error_reported() {
+        let result = parse_string("fn f() {\n    let a = ;\n}", "test_file.nl");
+        let message = result
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(message.contains("expected an operation"), "{}", message);
+        assert!(message.starts_with("0: at line 2,"), "{}", message);
+    }
+
     mod nl_struct {
         use super::*;
 
@@ -303,6 +476,145 @@ mod root {
             })
             .unwrap();
         }
+
+        #[test]
+        /// Fields can be followed by a trailing comma.
+        fn trailing_comma() {
+            let file = parse_string("struct MyStruct { a: i32, b: i32, }", "test_file.nl").unwrap();
+            assert_eq!(
+                file.structs[0].variables.len(),
+                2,
+                "Wrong number of fields."
+            );
+        }
+
+        #[test]
+        /// A missing comma between fields is reported where the comma should have been.
+        fn missing_comma() {
+            let result = parse_string(
+                "struct MyStruct {\n    a: i32\n    b: i32\n}",
+                "test_file.nl",
+            );
+            let message = result
+                .err()
+                .expect("No error when one was expected.")
+                .to_string();
+
+            assert!(
+                message.contains("expected `,` or `}` after field"),
+                "{}",
+                message
+            );
+            assert!(message.starts_with("0: at line 3,"), "{}", message);
+        }
+
+        #[test]
+        fn bit_fields() {
+            let file = parse_string(
+                "struct Register {\n    flags: u8 { ready: 1, mode: 3, reserved: 4 },\n    value: u8 }",
+                "test_file.nl",
+            )
+            .unwrap();
+
+            let variables = &file.structs[0].variables;
+            assert_eq!(variables.len(), 2, "Wrong number of fields.");
+
+            let bit_fields = variables[0].get_bit_fields();
+            assert_eq!(
+                bit_fields
+                    .iter()
+                    .map(|field| (field.get_name(), field.get_offset(), field.get_width()))
+                    .collect::<Vec<_>>(),
+                vec![("ready", 0, 1), ("mode", 1, 3), ("reserved", 4, 4)],
+                "Wrong bit fields."
+            );
+            assert!(variables[1].get_bit_fields().is_empty());
+        }
+
+        #[test]
+        fn bit_fields_invalid() {
+            for (code, expected) in &[
+                (
+                    "struct A { flags: u8 { a: 4, b: 5 } }",
+                    "bit fields don't fit in their backing integer",
+                ),
+                (
+                    "struct A { flags: u8 { a: 0 } }",
+                    "a bit field must be at least one bit wide",
+                ),
+                (
+                    "struct A { flags: bool { a: 1 } }",
+                    "bit fields need an integer to live in",
+                ),
+                (
+                    "struct A { flags: u8 { a: 1 b: 1 } }",
+                    "expected `,` or `}` after bit field",
+                ),
+            ] {
+                let message = parse_string(code, "test_file.nl")
+                    .err()
+                    .expect("No error when one was expected.")
+                    .to_string();
+
+                assert!(message.contains(expected), "{}", message);
+            }
+        }
+
+        #[test]
+        /// A standalone implementation can come before its struct and still ends up on it.
+        fn implementation_before_struct() {
+            let file = parse_string(
+                "impl MyTrait for MyStruct {\n    met a();\n}\nstruct MyStruct {}\nimpl Self {}",
+                "test_file.nl",
+            )
+            .unwrap();
+
+            assert_eq!(file.structs.len(), 1, "Wrong number of structs.");
+            let implementations = &file.structs[0].implementations;
+            assert_eq!(implementations.len(), 2, "Wrong number of implementations.");
+            assert_eq!(implementations[0].name, "Self");
+            assert_eq!(implementations[0].target, None);
+            assert_eq!(implementations[1].name, "MyTrait");
+            assert_eq!(implementations[1].target, Some("MyStruct"));
+            assert_eq!(implementations[1].implementors.len(), 1);
+
+            assert!(
+                file.implementations.is_empty(),
+                "Implementation not linked."
+            );
+        }
+
+        #[test]
+        /// An implementation for a struct declared somewhere else stays on the file.
+        fn implementation_for_unknown_struct() {
+            let file = parse_string("impl MyTrait for Elsewhere {}", "test_file.nl").unwrap();
+
+            assert_eq!(
+                file.implementations.len(),
+                1,
+                "Wrong number of implementations."
+            );
+            assert_eq!(file.implementations[0].target, Some("Elsewhere"));
+        }
+
+        #[test]
+        fn generic_implementation() {
+            let result = parse_string(
+                "trait Printable {}\nimpl<T: Printable> Printable for List<T> {}",
+                "test_file.nl",
+            );
+            let message = result
+                .err()
+                .expect("No error when one was expected.")
+                .to_string();
+
+            assert!(
+                message.contains("implementations can't have type parameters"),
+                "{}",
+                message
+            );
+            assert!(message.contains("line 2"), "{}", message);
+        }
     }
 
     mod nl_trait {
@@ -340,6 +652,47 @@ mod root {
             })
             .unwrap();
         }
+
+        #[test]
+        /// Something that isn't a member is reported where it starts.
+        fn unknown_member() {
+            let result = parse_string(
+                "trait MyTrait {\n    met a();\n    fn b();\n}",
+                "test_file.nl",
+            );
+            let message = result
+                .err()
+                .expect("No error when one was expected.")
+                .to_string();
+
+            assert!(
+                message.contains("expected `met`, `get`, or `set`"),
+                "{}",
+                message
+            );
+            assert!(message.starts_with("0: at line 3,"), "{}", message);
+            assert!(message.contains("    fn b();\n    ^"), "{}", message);
+        }
+
+        #[test]
+        /// A member that starts correctly reports its own error rather than the member list's.
+        fn broken_member() {
+            let result = parse_string(
+                "impl Self for MyStruct {\n    met a() -> ;\n}",
+                "test_file.nl",
+            );
+            let message = result
+                .err()
+                .expect("No error when one was expected.")
+                .to_string();
+
+            assert!(message.starts_with("0: at line 2,"), "{}", message);
+            assert!(
+                !message.starts_with("0: at line 2, in expected `met`"),
+                "{}",
+                message
+            );
+        }
     }
 
     mod argument_list {
@@ -583,6 +936,184 @@ mod root {
                 "Wrong argument type."
             );
         }
+
+        #[test]
+        /// Testing the argument declaration reader.
+        fn tuple() {
+            let code = "(var: (i32, (bool, u8)), other: i32)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 2, "Wrong number of args.");
+            assert_eq!(
+                args[0].nl_type,
+                NLType::Tuple(vec![
+                    NLType::I32,
+                    NLType::Tuple(vec![NLType::Boolean, NLType::U8])
+                ]),
+                "Wrong argument type."
+            );
+            assert_eq!(args[1].nl_type, NLType::I32, "Wrong argument type.");
+        }
+
+        #[test]
+        /// Brackets around a single type don't make a tuple, a trailing comma does.
+        fn tuple_single_item() {
+            let code = "(a: (i32), b: (i32,), c: ())";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 3, "Wrong number of args.");
+            assert_eq!(args[0].nl_type, NLType::I32, "Wrong argument type.");
+            assert_eq!(
+                args[1].nl_type,
+                NLType::Tuple(vec![NLType::I32]),
+                "Wrong argument type."
+            );
+            assert_eq!(
+                args[2].nl_type,
+                NLType::Tuple(vec![]),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        /// Testing the argument declaration reader.
+        fn pointers() {
+            let code = "(a: *const i32, b: *mut List<u8>)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 2, "Wrong number of args.");
+            assert_eq!(
+                args[0].nl_type,
+                NLType::Pointer(Box::new(NLType::I32)),
+                "Wrong argument type."
+            );
+            assert_eq!(
+                args[1].nl_type,
+                NLType::MutablePointer(Box::new(NLType::List(Box::new(NLType::U8)))),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        /// Shared values and weak references to them.
+        fn shared() {
+            let code = "(a: rc Node, b: weak Node, c: List<rc str>)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 3, "Wrong number of args.");
+            assert_eq!(
+                args[0].nl_type,
+                NLType::Shared(Box::new(NLType::OwnedStruct("Node"))),
+                "Wrong argument type."
+            );
+            assert_eq!(
+                args[1].nl_type,
+                NLType::Weak(Box::new(NLType::OwnedStruct("Node"))),
+                "Wrong argument type."
+            );
+            assert_eq!(
+                args[2].nl_type,
+                NLType::List(Box::new(NLType::Shared(Box::new(NLType::BorrowedString)))),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        fn bytes() {
+            let code = "(packet: bytes, packets: List<bytes>)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args[0].nl_type, NLType::Bytes, "Wrong argument type.");
+            assert_eq!(
+                args[1].nl_type,
+                NLType::List(Box::new(NLType::Bytes)),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        /// A pointer has to say whether it's const or mut.
+        fn pointer_without_mutability() {
+            let error = match read_variable_type("*i32") {
+                Err(nom::Err::Error(error)) => convert_error("*i32", error),
+                _ => panic!("No error when one was expected."),
+            };
+
+            assert!(
+                error.contains("pointers must be `*const T` or `*mut T`"),
+                "{}",
+                error
+            );
+        }
+
+        #[test]
+        /// Testing the argument declaration reader.
+        fn list() {
+            let code = "(var: List<i32>)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 1, "Wrong number of args.");
+
+            let arg = &args[0];
+            assert_eq!(arg.name, "var", "Wrong argument name.");
+            assert_eq!(
+                arg.nl_type,
+                NLType::List(Box::new(NLType::I32)),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        /// Testing the argument declaration reader.
+        fn map() {
+            let code = "(var: Map<u8, &SomeStruct>, other: i32)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 2, "Wrong number of args.");
+
+            let arg = &args[0];
+            assert_eq!(arg.name, "var", "Wrong argument name.");
+            assert_eq!(
+                arg.nl_type,
+                NLType::Map(
+                    Box::new(NLType::U8),
+                    Box::new(NLType::ReferencedStruct("SomeStruct"))
+                ),
+                "Wrong argument type."
+            );
+
+            let arg = &args[1];
+            assert_eq!(arg.name, "other", "Wrong argument name.");
+            assert_eq!(arg.nl_type, NLType::I32, "Wrong argument type.");
+        }
+
+        #[test]
+        /// Testing the argument declaration reader.
+        fn nested_collections() {
+            let code = "(var: Map<i32, List<List<bool>>>)";
+            let args = pretty_read(code, &read_argument_deceleration_list);
+
+            assert_eq!(args.len(), 1, "Wrong number of args.");
+
+            let arg = &args[0];
+            assert_eq!(
+                arg.nl_type,
+                NLType::Map(
+                    Box::new(NLType::I32),
+                    Box::new(NLType::List(Box::new(NLType::List(Box::new(
+                        NLType::Boolean
+                    )))))
+                ),
+                "Wrong argument type."
+            );
+        }
+
+        #[test]
+        /// A list has exactly one item type.
+        fn list_wrong_type_count() {
+            let code = "List<i32, bool>";
+            assert!(read_collection_type(code).is_err());
+        }
     }
 
     mod global_functions {
@@ -699,6 +1230,20 @@ mod root {
             }
         }
 
+        #[test]
+        /// A method can return a tuple.
+        fn method_tuple_return() {
+            let code = "met my_method() -> (i32, bool);";
+
+            let (_, method) = pretty_read_method(code);
+
+            assert_eq!(
+                method.return_type,
+                NLType::Tuple(vec![NLType::I32, NLType::Boolean]),
+                "Wrong return type."
+            );
+        }
+
         #[test]
         /// Construct a blank and unimplemented method.
         fn method_no_arg_no_return_no_impl() {
@@ -871,6 +1416,34 @@ mod root {
                 "Getter did not have correct return type."
             );
         }
+
+        #[test]
+        /// Getters only ever take `&self`.
+        fn getter_bad_arguments() {
+            for code in &[
+                "get my_getter() -> i32;",
+                "get my_getter(&mut self) -> i32;",
+                "get my_getter(&self, value: i32) -> i32;",
+                "get my_getter(value: i32) -> i32;",
+            ] {
+                let error = match read_getter(code) {
+                    Err(nom::Err::Error(error)) => convert_error(*code, error),
+                    _ => panic!("No error for {}", code),
+                };
+
+                assert!(
+                    error.contains("a getter takes exactly one argument, `&self`"),
+                    "{}",
+                    error
+                );
+                // Points at the argument list.
+                assert!(
+                    error.contains(&format!("\n{}^", " ".repeat(13))),
+                    "{}",
+                    error
+                );
+            }
+        }
     }
 
     mod nl_setters {
@@ -1171,6 +1744,25 @@ mod root {
             assert_eq!(argument.get_name(), "d");
             assert_eq!(*unwrap_to!(argument.get_type() => NLType::OwnedStruct), "D");
         }
+
+        #[test]
+        fn tuple_payloads() {
+            let code = "enum Shape { Circle(f32), Rect(f32, f32,), Group(List<Shape>), Named(side: u32), Empty }";
+            let file = parse_string(code, "virtual_file").unwrap();
+            let variants = file.get_enums()[0].get_variants();
+            assert_eq!(variants.len(), 5);
+
+            assert_eq!(variants[0].get_payload(), vec![&NLType::F32]);
+            assert!(variants[0].get_arguments().is_empty());
+            assert_eq!(variants[1].get_payload(), vec![&NLType::F32, &NLType::F32]);
+            assert_eq!(
+                variants[2].get_payload(),
+                vec![&NLType::List(Box::new(NLType::Enum("Shape")))]
+            );
+            assert_eq!(variants[3].get_payload(), vec![&NLType::U32]);
+            assert_eq!(variants[3].get_arguments()[0].get_name(), "side");
+            assert!(variants[4].get_payload().is_empty());
+        }
     }
 }
 
@@ -1425,6 +2017,114 @@ mod executable_blocks {
                 _ => panic!("Expected string for constant type."),
             }
         }
+
+        #[test]
+        fn bytes() {
+            let constant = unwrap_constant(pretty_read("b\"GET \\n\"", &read_constant));
+            assert_eq!(constant, OpConstant::Bytes(b"GET \n".to_vec()));
+
+            let constant = unwrap_constant(pretty_read("x\"DEADbeef00\"", &read_constant));
+            assert_eq!(
+                constant,
+                OpConstant::Bytes(vec![0xde, 0xad, 0xbe, 0xef, 0x00])
+            );
+
+            // Without the quote straight after, the prefix is just a name.
+            let operation = pretty_read("b", &read_operation);
+            assert_eq!(
+                operation,
+                NLOperation::VariableAccess(OpVariable { name: "b" })
+            );
+        }
+
+        #[test]
+        fn bad_bytes() {
+            for (code, expected) in &[
+                ("x\"ABC\"", "hex literals need two digits for each byte"),
+                ("x\"0G\"", "hex literals can only hold hex digits"),
+                ("b\"caf\u{e9}\"", "byte strings can only hold ASCII"),
+            ] {
+                let error = match read_constant(code) {
+                    Err(nom::Err::Failure(error)) => convert_error(*code, error),
+                    _ => panic!("No failure when one was expected for {}.", code),
+                };
+                assert!(error.contains(expected), "{}", error);
+            }
+        }
+
+        #[test]
+        fn chars() {
+            for (code, expected) in &[
+                ("'a'", 'a'),
+                ("'\u{e9}'", '\u{e9}'),
+                ("'\\n'", '\n'),
+                ("'\\''", '\''),
+                ("'\\\\'", '\\'),
+                ("'\\0'", '\0'),
+                ("'\\u{1F600}'", '\u{1F600}'),
+                ("'\"'", '"'),
+            ] {
+                let constant = unwrap_constant(pretty_read(code, &read_constant));
+                assert_eq!(constant, OpConstant::Char(*expected), "{}", code);
+            }
+
+            // Escaped single quotes work in strings too.
+            let constant = unwrap_constant(pretty_read("\"it\\'s\"", &read_constant));
+            assert_eq!(constant, OpConstant::String("it's".to_string()));
+
+            let args = pretty_read("(letter: char)", &read_argument_deceleration_list);
+            assert_eq!(args[0].nl_type, NLType::Char);
+        }
+
+        #[test]
+        fn string_escapes() {
+            let constant = unwrap_constant(pretty_read("\"he said \\\"hi\\\"\"", &read_constant));
+            assert_eq!(constant, OpConstant::String("he said \"hi\"".to_string()));
+
+            let constant = unwrap_constant(pretty_read("\"a\\tb\\\\c\\u{e9}\"", &read_constant));
+            assert_eq!(constant, OpConstant::String("a\tb\\c\u{e9}".to_string()));
+        }
+
+        #[test]
+        fn bad_escapes() {
+            for (code, expected) in &[
+                ("\"a\\qb\"", "unknown escape"),
+                ("'\\q'", "unknown escape"),
+                ("\"\\u{zz}\"", "unicode escapes need one to six hex digits"),
+                (
+                    "\"\\u{D800}\"",
+                    "unicode escapes need one to six hex digits",
+                ),
+                ("b\"\\x\"", "unknown escape"),
+            ] {
+                let error = match read_constant(code) {
+                    Err(nom::Err::Failure(error)) => convert_error(*code, error),
+                    _ => panic!("No failure when one was expected for {}.", code),
+                };
+                assert!(error.contains(expected), "{}", error);
+            }
+        }
+
+        #[test]
+        fn bad_chars() {
+            for code in &["''", "'ab'", "'a"] {
+                let error = match read_constant(code) {
+                    Err(nom::Err::Failure(error)) => convert_error(*code, error),
+                    _ => panic!("No failure when one was expected for {}.", code),
+                };
+                assert!(
+                    error.contains("char literals hold exactly one character"),
+                    "{}",
+                    error
+                );
+            }
+
+            let error = match read_numerical_constant("5char") {
+                Err(nom::Err::Error(error)) => convert_error("5char", error),
+                _ => panic!("No error when one was expected."),
+            };
+            assert!(error.contains("as a char"), "{}", error);
+        }
     }
 
     mod variables {
@@ -1442,13 +2142,73 @@ mod executable_blocks {
                 _ => panic!("Expected variable access operation, got {:?}", operation),
             }
         }
-    }
-
-    mod tuples {
-        use super::*;
 
         #[test]
-        fn tuple_empty() {
+        fn index() {
+            let code = "data[start][1 .. 3]";
+            let operation = pretty_read(code, &read_operation);
+
+            let slice = unwrap_to!(operation => NLOperation::Index);
+            let index = unwrap_to!(*slice.collection => NLOperation::Index);
+            assert_eq!(
+                *index.collection,
+                NLOperation::VariableAccess(OpVariable { name: "data" })
+            );
+            assert_eq!(
+                *index.index,
+                NLOperation::VariableAccess(OpVariable { name: "start" })
+            );
+            unwrap_to!(*slice.index => NLOperation::Operator);
+
+            // With a space in between it's a list after the variable.
+            let block = pretty_read("{ data [1] }", &read_code_block_raw);
+            assert_eq!(block.operations.len(), 2, "Wrong number of operations.");
+        }
+
+        #[test]
+        fn index_anything() {
+            let operation = pretty_read("rows()[0]", &read_operation);
+            let index = unwrap_to!(operation => NLOperation::Index);
+            unwrap_to!(*index.collection => NLOperation::FunctionCall);
+
+            let operation = pretty_read("[1, 2][i]", &read_operation);
+            let index = unwrap_to!(operation => NLOperation::Index);
+            unwrap_to!(*index.collection => NLOperation::List);
+
+            let operation = pretty_read("totals[i] + 1", &read_operation);
+            let operator = unwrap_to!(operation => NLOperation::Operator);
+            let (left, _) = unwrap_to!(operator => OpOperator::ArithmeticAdd);
+            unwrap_to!(&**left => NLOperation::Index);
+        }
+
+        // Calls and lists, and whatever's been chained onto them, can be the first operand of an operator too.
+        #[test]
+        fn operators_after_calls() {
+            let operation = pretty_read("rows()[0] + 1", &read_operation);
+            let operator = unwrap_to!(operation => NLOperation::Operator);
+            let (left, _) = unwrap_to!(operator => OpOperator::ArithmeticAdd);
+            let index = unwrap_to!(&**left => NLOperation::Index);
+            unwrap_to!(&*index.collection => NLOperation::FunctionCall);
+
+            let operation = pretty_read("f() + 1 * 2", &read_operation);
+            let operator = unwrap_to!(operation => NLOperation::Operator);
+            let (left, right) = unwrap_to!(operator => OpOperator::ArithmeticAdd);
+            unwrap_to!(&**left => NLOperation::FunctionCall);
+            unwrap_to!(&**right => NLOperation::Operator);
+
+            let operation = pretty_read("[1, 2][i] == (3, 4).0", &read_operation);
+            let operator = unwrap_to!(operation => NLOperation::Operator);
+            let (left, right) = unwrap_to!(operator => OpOperator::CompareEqual);
+            unwrap_to!(&**left => NLOperation::Index);
+            unwrap_to!(&**right => NLOperation::FieldAccess);
+        }
+    }
+
+    mod tuples {
+        use super::*;
+
+        #[test]
+        fn tuple_empty() {
             let code = "()";
             let (_, tuple) = read_tuple(code).unwrap();
 
@@ -1530,6 +2290,41 @@ mod executable_blocks {
         }
     }
 
+    mod lists {
+        use super::*;
+
+        #[test]
+        fn list_empty() {
+            let code = "[]";
+            let operation = pretty_read(code, &read_operation);
+            let list = unwrap_to!(operation => NLOperation::List);
+
+            assert_eq!(list.len(), 0, "Wrong number of items in list.");
+        }
+
+        #[test]
+        fn list_one_item() {
+            let code = "[1]";
+            let operation = pretty_read(code, &read_operation);
+            let list = unwrap_to!(operation => NLOperation::List);
+
+            assert_eq!(list.len(), 1, "Wrong number of items in list.");
+            assert_eq!(unwrap_constant_signed(&list[0]), 1);
+        }
+
+        #[test]
+        fn list_three_items_trailing_comma() {
+            let code = "[ 1, 2 , 3, ]";
+            let operation = pretty_read(code, &read_operation);
+            let list = unwrap_to!(operation => NLOperation::List);
+
+            assert_eq!(list.len(), 3, "Wrong number of items in list.");
+            assert_eq!(unwrap_constant_signed(&list[0]), 1);
+            assert_eq!(unwrap_constant_signed(&list[1]), 2);
+            assert_eq!(unwrap_constant_signed(&list[2]), 3);
+        }
+    }
+
     mod assignment {
         use super::*;
 
@@ -1566,6 +2361,48 @@ mod executable_blocks {
             };
         }
 
+        #[test]
+        /// A name that starts with `let` is not a new variable.
+        fn keyword_prefixed_name() {
+            let code = "letter = 5;";
+            let (_, operation) = read_assignment(code).unwrap();
+
+            let assign = unwrap_to!(operation => NLOperation::Assign);
+            assert!(!assign.is_new, "Assignment should not have been new.");
+            assert_eq!(
+                assign.to_assign[0].name, "letter",
+                "Wrong name given to variable."
+            );
+        }
+
+        #[test]
+        /// A single variable keeps its tuple type whole.
+        fn single_variable_tuple_type() {
+            let code = "let pair: (i32, bool) = (4, true);";
+            let (_, operation) = read_assignment(code).unwrap();
+
+            let assign = unwrap_to!(operation => NLOperation::Assign);
+            assert_eq!(
+                assign.type_assignments,
+                vec![NLType::Tuple(vec![NLType::I32, NLType::Boolean])],
+                "Wrong type specified."
+            );
+        }
+
+        #[test]
+        /// A tuple type is split between destructured variables.
+        fn tuple_of_variables_tuple_type() {
+            let code = "let (fore, five): (i32, bool) = (4, true);";
+            let (_, operation) = read_assignment(code).unwrap();
+
+            let assign = unwrap_to!(operation => NLOperation::Assign);
+            assert_eq!(
+                assign.type_assignments,
+                vec![NLType::I32, NLType::Boolean],
+                "Wrong types specified."
+            );
+        }
+
         #[test]
         fn single_variable_to_constant_scoped() {
             let code = "let numbers.five = 5;";
@@ -1821,6 +2658,55 @@ mod executable_blocks {
                 _ => panic!("Expected assignment operation."),
             };
         }
+
+        #[test]
+        fn destructure_struct() {
+            let code = "let Line { start: Point { x, y: top }, end } = line";
+            let operation = pretty_read(code, &read_operation);
+            let let_pattern = unwrap_to!(operation => NLOperation::LetPattern);
+
+            let (name, fields) = match &let_pattern.pattern {
+                LetPattern::Struct(name, fields) => (name, fields),
+                _ => panic!("Expected a struct pattern."),
+            };
+            assert_eq!(*name, "Line");
+            assert_eq!(fields.len(), 2);
+            assert_eq!(fields[1], ("end", LetPattern::Variable("end")));
+
+            let (name, inner) = match &fields[0].1 {
+                LetPattern::Struct(name, inner) => (name, inner),
+                _ => panic!("Expected a struct pattern."),
+            };
+            assert_eq!(*name, "Point");
+            assert_eq!(inner[1], ("y", LetPattern::Variable("top")));
+
+            assert_eq!(let_pattern.pattern.variables(), vec!["x", "top", "end"]);
+            assert_eq!(
+                unwrap_to!(*let_pattern.assignment => NLOperation::VariableAccess).get_name(),
+                "line"
+            );
+            assert!(let_pattern.else_block.is_none());
+        }
+
+        #[test]
+        fn let_else() {
+            let code = "let Shape::Circle(radius) = shape else { break }";
+            let operation = pretty_read(code, &read_operation);
+            let let_pattern = unwrap_to!(operation => NLOperation::LetPattern);
+
+            assert_eq!(
+                let_pattern.pattern,
+                LetPattern::Variant("Shape", "Circle", vec![LetPattern::Variable("radius")])
+            );
+            let else_block = let_pattern.else_block.as_ref().unwrap();
+            assert_eq!(else_block.operations, vec![NLOperation::Break(None)]);
+        }
+
+        #[test]
+        fn plain_let_is_an_assignment() {
+            let operation = pretty_read("let x = y", &read_operation);
+            unwrap_to!(operation => NLOperation::Assign);
+        }
     }
 
     mod operators {
@@ -2178,6 +3064,32 @@ mod executable_blocks {
             }
         }
 
+        mod pointers {
+            use super::*;
+
+            #[test]
+            fn address_of() {
+                let code = "&value";
+                let operation = pretty_read(code, &read_operation);
+                let operation = unwrap_to!(operation => NLOperation::Operator);
+                let value = unwrap_to!(operation => OpOperator::AddressOf);
+                let variable = unwrap_to!(**value => NLOperation::VariableAccess);
+
+                assert_eq!(variable.name, "value", "Wrong variable.");
+            }
+
+            #[test]
+            fn dereference() {
+                let code = "*pointer";
+                let operation = pretty_read(code, &read_operation);
+                let operation = unwrap_to!(operation => NLOperation::Operator);
+                let value = unwrap_to!(operation => OpOperator::Dereference);
+                let variable = unwrap_to!(**value => NLOperation::VariableAccess);
+
+                assert_eq!(variable.name, "pointer", "Wrong variable.");
+            }
+        }
+
         mod precedence {
             use super::*;
             #[test]
@@ -2221,6 +3133,34 @@ mod executable_blocks {
                 assert_eq!(b, 2, "Wrong value for constant.");
             }
 
+            #[test]
+            fn mixed_chain() {
+                let code = "{ 1 + 2 * 3 - 4 }";
+                let block = pretty_read(code, &read_code_block_raw);
+                assert_eq!(
+                    block.operations.len(),
+                    1,
+                    "Chain was split into statements."
+                );
+
+                let operation = unwrap_to!(block.operations[0] => NLOperation::Operator);
+                let (a, b) = unwrap_to!(operation => OpOperator::ArithmeticSub);
+                let b = unwrap_constant_signed(b);
+                assert_eq!(b, 4, "Wrong value for constant.");
+
+                let operation = unwrap_to!(**a => NLOperation::Operator);
+                let (a, b) = unwrap_to!(operation => OpOperator::ArithmeticAdd);
+                let a = unwrap_constant_signed(a);
+                assert_eq!(a, 1, "Wrong value for constant.");
+
+                let operation = unwrap_to!(**b => NLOperation::Operator);
+                let (a, b) = unwrap_to!(operation => OpOperator::ArithmeticMul);
+                let a = unwrap_constant_signed(a);
+                let b = unwrap_constant_signed(b);
+                assert_eq!(a, 2, "Wrong value for constant.");
+                assert_eq!(b, 3, "Wrong value for constant.");
+            }
+
             #[test]
             fn right_shift_left_shift() {
                 let code = "{ 1 >> 2 << 3 }";
@@ -2296,26 +3236,27 @@ mod executable_blocks {
                 let (a, b) = unwrap_to!(operation => OpOperator::CompareEqual);
                 let a = unwrap_constant_signed(a);
                 let b = unwrap_constant_signed(b);
-                assert_eq!(b, 1, "Wrong value for constant.");
-                assert_eq!(a, 2, "Wrong value for constant.");
+                assert_eq!(a, 1, "Wrong value for constant.");
+                assert_eq!(b, 2, "Wrong value for constant.");
             }
 
             #[test]
             fn logical_and_or() {
+                // `&&` binds tighter than `||`, the same way `&` binds tighter than `|`.
                 let code = "{ 1 || 2 && 3 }";
                 let block = pretty_read(code, &read_code_block_raw);
 
                 let operation = unwrap_to!(block.operations[0] => NLOperation::Operator);
-                let (a, b) = unwrap_to!(operation => OpOperator::LogicalAnd);
-                let b = unwrap_constant_signed(b);
-                assert_eq!(b, 3, "Wrong value for constant.");
-
-                let operation = unwrap_to!(**a => NLOperation::Operator);
                 let (a, b) = unwrap_to!(operation => OpOperator::LogicalOr);
                 let a = unwrap_constant_signed(a);
+                assert_eq!(a, 1, "Wrong value for constant.");
+
+                let operation = unwrap_to!(**b => NLOperation::Operator);
+                let (a, b) = unwrap_to!(operation => OpOperator::LogicalAnd);
+                let a = unwrap_constant_signed(a);
                 let b = unwrap_constant_signed(b);
-                assert_eq!(b, 1, "Wrong value for constant.");
                 assert_eq!(a, 2, "Wrong value for constant.");
+                assert_eq!(b, 3, "Wrong value for constant.");
             }
         }
     }
@@ -2384,6 +3325,41 @@ mod executable_blocks {
             );
         }
 
+        #[test]
+        fn else_if() {
+            let code = "if a { 1 } else if b { 2 } else { 3 }";
+            let operation = pretty_read(code, &read_operation);
+            let statement = unwrap_to!(operation => NLOperation::If);
+
+            assert_eq!(
+                statement.false_block.operations.len(),
+                1,
+                "Wrong number of operations in false block."
+            );
+            let else_if = unwrap_to!(&statement.false_block.operations[0] => NLOperation::If);
+            assert_eq!(
+                *else_if.condition,
+                NLOperation::VariableAccess(OpVariable { name: "b" }),
+                "Wrong condition for else if."
+            );
+            assert_eq!(
+                unwrap_constant_signed(&else_if.true_block.operations[0]),
+                2,
+                "Wrong value in else if block."
+            );
+            assert_eq!(
+                unwrap_constant_signed(&else_if.false_block.operations[0]),
+                3,
+                "Wrong value in else block."
+            );
+
+            // Without a final else the last false block is empty.
+            let operation = pretty_read("if a { 1 } else if b { 2 }", &read_operation);
+            let statement = unwrap_to!(operation => NLOperation::If);
+            let else_if = unwrap_to!(&statement.false_block.operations[0] => NLOperation::If);
+            assert!(else_if.false_block.operations.is_empty());
+        }
+
         #[test]
         fn and_if() {
             let code = "if true && false {}";
@@ -2506,97 +3482,326 @@ mod executable_blocks {
             );
         }
 
+        #[test]
+        fn for_range() {
+            let code = "for x in 0..10 { x }";
+            let operation = pretty_read(code, &read_operation);
+            let for_loop = unwrap_to!(operation => NLOperation::ForLoop);
+            assert_eq!(for_loop.variable.name, "x");
+
+            let operator = unwrap_to!(&*for_loop.iterator => NLOperation::Operator);
+            let (start, end) = unwrap_to!(operator => OpOperator::Range);
+            assert_eq!(unwrap_constant_signed(start), 0);
+            assert_eq!(unwrap_constant_signed(end), 10);
+        }
+
+        #[test]
+        fn for_range_of_variables() {
+            let code = "for x in start..end { x }";
+            let operation = pretty_read(code, &read_operation);
+            let for_loop = unwrap_to!(operation => NLOperation::ForLoop);
+
+            let operator = unwrap_to!(&*for_loop.iterator => NLOperation::Operator);
+            let (start, end) = unwrap_to!(operator => OpOperator::Range);
+            assert_eq!(
+                unwrap_to!(**start => NLOperation::VariableAccess).get_name(),
+                "start"
+            );
+            assert_eq!(
+                unwrap_to!(**end => NLOperation::VariableAccess).get_name(),
+                "end"
+            );
+        }
+
+        #[test]
+        fn for_list() {
+            let code = "for x in [1, 2] { x }";
+            let operation = pretty_read(code, &read_operation);
+            let for_loop = unwrap_to!(operation => NLOperation::ForLoop);
+
+            let items = unwrap_to!(&*for_loop.iterator => NLOperation::List);
+            assert_eq!(items.len(), 2);
+        }
+
         #[test]
         fn break_keyword() {
             let code = "break";
             let operation = pretty_read(code, &read_operation);
 
             match operation {
-                NLOperation::Break => {
+                NLOperation::Break(None) => {
                     // We pass. That's it.
                 }
                 _ => panic!("Expected break operation, got {:?}", operation),
             }
         }
-    }
-
-    mod match_statements {
-        use super::*;
 
         #[test]
-        fn basic_match() {
-            let code = "match variable {}";
+        fn break_with_value() {
+            let code = "loop { if done { break total + 1 } continue }";
             let operation = pretty_read(code, &read_operation);
-            let nl_match = unwrap_to!(operation => NLOperation::Match);
+            let block = unwrap_to!(operation => NLOperation::Loop);
 
             assert_eq!(
-                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
-                "variable"
+                block.operations.len(),
+                2,
+                "Wrong number of operations in loop."
             );
-            assert_eq!(nl_match.branches.len(), 0);
+            let statement = unwrap_to!(&block.operations[0] => NLOperation::If);
+            let value = match &statement.true_block.operations[..] {
+                [NLOperation::Break(Some(value))] => value,
+                operations => panic!("Expected break with a value, got {:?}", operations),
+            };
+            unwrap_to!(**value => NLOperation::Operator);
+            assert_eq!(block.operations[1], NLOperation::Continue);
         }
 
         #[test]
-        fn one_branch() {
-            let code = "match variable { Enum::One => 0, }";
-            let operation = pretty_read(code, &read_operation);
-            let nl_match = unwrap_to!(operation => NLOperation::Match);
+        fn break_before_next_line() {
+            // The operation on the next line isn't the value of the break.
+            let code = "{ break\n    total }";
+            let block = pretty_read(code, &read_code_block_raw);
 
             assert_eq!(
-                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
-                "variable"
+                block.operations,
+                vec![
+                    NLOperation::Break(None),
+                    NLOperation::VariableAccess(OpVariable { name: "total" }),
+                ]
             );
+        }
+    }
 
-            let branches = &nl_match.branches;
-            assert_eq!(branches.len(), 1);
+    mod returns {
+        use super::*;
 
-            let (branch, operation) = &branches[0];
-            let branch = unwrap_to!(branch => MatchBranch::Enum);
-            assert_eq!(branch.nl_enum, "Enum");
-            assert_eq!(branch.variant, "One");
+        #[test]
+        fn bare_return() {
+            let operation = pretty_read("return", &read_operation);
+            assert_eq!(operation, NLOperation::Return(None));
+        }
 
-            assert_eq!(unwrap_constant_signed(operation), 0);
+        #[test]
+        fn return_value() {
+            let operation = pretty_read("return x + 1", &read_operation);
+            let value = unwrap_to!(operation => NLOperation::Return)
+                .as_ref()
+                .unwrap();
+            let operator = unwrap_to!(**value => NLOperation::Operator);
+            unwrap_to!(operator => OpOperator::ArithmeticAdd);
+        }
 
-            assert_eq!(branch.variables.len(), 0);
+        #[test]
+        fn return_before_next_line() {
+            let code = "{ return\n x }";
+            let operation = pretty_read(code, &read_operation);
+            let block = unwrap_to!(operation => NLOperation::Block);
+            assert_eq!(block.operations.len(), 2);
+            assert_eq!(block.operations[0], NLOperation::Return(None));
         }
 
         #[test]
-        fn one_branch_no_comma() {
-            let code = "match variable { Enum::One => 0 }";
+        fn early_return_from_let_else() {
+            let code = "enum Shape { Circle(f32), Square, }
+fn radius(shape: Shape) -> f32 { let Shape::Circle(radius) = shape else { return 0.0 } radius }";
+            let file = parse_string(code, "test_file.nl").unwrap();
+            let block = file.functions[0].block.as_ref().unwrap();
+            assert_eq!(block.operations.len(), 2);
+        }
+    }
+
+    mod catch_blocks {
+        use super::*;
+
+        #[test]
+        fn empty_catch() {
+            let code = "catch {}";
             let operation = pretty_read(code, &read_operation);
-            let nl_match = unwrap_to!(operation => NLOperation::Match);
+            let block = unwrap_to!(operation => NLOperation::Catch);
 
             assert_eq!(
-                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
-                "variable"
+                block.operations.len(),
+                0,
+                "Wrong number of operations in block."
             );
+        }
 
-            let branches = &nl_match.branches;
-            assert_eq!(branches.len(), 1);
+        #[test]
+        fn catch_with_operations() {
+            let code = "catch { true false }";
+            let operation = pretty_read(code, &read_operation);
+            let block = unwrap_to!(operation => NLOperation::Catch);
 
-            let (branch, operation) = &branches[0];
-            let branch = unwrap_to!(branch => MatchBranch::Enum);
-            assert_eq!(branch.nl_enum, "Enum");
-            assert_eq!(branch.variant, "One");
+            assert_eq!(
+                block.operations.len(),
+                2,
+                "Wrong number of operations in block."
+            );
+            assert!(unwrap_constant_boolean(&block.operations[0]));
+            assert!(!unwrap_constant_boolean(&block.operations[1]));
+        }
+    }
 
-            assert_eq!(unwrap_constant_signed(operation), 0);
+    mod type_queries {
+        use super::*;
 
-            assert_eq!(branch.variables.len(), 0);
+        #[test]
+        fn size_of() {
+            let code = "sizeof(u32)";
+            let operation = pretty_read(code, &read_operation);
+            let nl_type = unwrap_to!(operation => NLOperation::SizeOf);
+
+            assert_eq!(*nl_type, NLType::U32, "Wrong type.");
         }
 
         #[test]
-        fn one_branch_one_variable() {
-            let code = "match variable { Enum::One(a) => 0, }";
+        fn align_of() {
+            let code = "alignof( (u8, *mut u8) )";
             let operation = pretty_read(code, &read_operation);
-            let nl_match = unwrap_to!(operation => NLOperation::Match);
+            let nl_type = unwrap_to!(operation => NLOperation::AlignOf);
 
             assert_eq!(
-                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
-                "variable"
+                *nl_type,
+                NLType::Tuple(vec![
+                    NLType::U8,
+                    NLType::MutablePointer(Box::new(NLType::U8))
+                ]),
+                "Wrong type."
             );
+        }
 
-            let branches = &nl_match.branches;
-            assert_eq!(branches.len(), 1);
+        #[test]
+        /// A variable that only starts with `sizeof` is still a variable.
+        fn similar_name() {
+            let code = "sizeofs";
+            let operation = pretty_read(code, &read_operation);
+            let variable = unwrap_to!(operation => NLOperation::VariableAccess);
+
+            assert_eq!(variable.name, "sizeofs", "Wrong variable.");
+        }
+
+        #[test]
+        fn reflection() {
+            let code = "variants_of(Level)";
+            let operation = pretty_read(code, &read_operation);
+            let nl_type = unwrap_to!(operation => NLOperation::VariantsOf);
+
+            assert_eq!(*nl_type, NLType::OwnedStruct("Level"), "Wrong type.");
+        }
+    }
+
+    mod unsafe_blocks {
+        use super::*;
+
+        #[test]
+        fn unsafe_with_operations() {
+            let code = "unsafe { *pointer }";
+            let operation = pretty_read(code, &read_operation);
+            let block = unwrap_to!(operation => NLOperation::Unsafe);
+
+            assert_eq!(
+                block.operations.len(),
+                1,
+                "Wrong number of operations in block."
+            );
+            let operator = unwrap_to!(block.operations[0] => NLOperation::Operator);
+            unwrap_to!(operator => OpOperator::Dereference);
+        }
+
+        #[test]
+        fn unsafe_functions() {
+            let file = parse_string(
+                "fn a();\nunsafe fn b() {}\nstruct MyStruct {}\nimpl Self {\n    unsafe met c();\n}",
+                "test_file.nl",
+            )
+            .unwrap();
+
+            assert!(!file.functions[0].is_unsafe(), "Function should be safe.");
+            assert!(file.functions[1].is_unsafe(), "Function should be unsafe.");
+
+            let method = &file.structs[0].implementations[0].implementors[0];
+            let method = unwrap_to!(method => NLImplementor::Method);
+            assert!(method.is_unsafe(), "Method should be unsafe.");
+        }
+    }
+
+    mod match_statements {
+        use super::*;
+
+        #[test]
+        fn basic_match() {
+            let code = "match variable {}";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            assert_eq!(
+                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
+                "variable"
+            );
+            assert_eq!(nl_match.branches.len(), 0);
+        }
+
+        #[test]
+        fn one_branch() {
+            let code = "match variable { Enum::One => 0, }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            assert_eq!(
+                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
+                "variable"
+            );
+
+            let branches = &nl_match.branches;
+            assert_eq!(branches.len(), 1);
+
+            let (branch, operation) = &branches[0];
+            let branch = unwrap_to!(branch => MatchBranch::Enum);
+            assert_eq!(branch.nl_enum, "Enum");
+            assert_eq!(branch.variant, "One");
+
+            assert_eq!(unwrap_constant_signed(operation), 0);
+
+            assert_eq!(branch.variables.len(), 0);
+        }
+
+        #[test]
+        fn one_branch_no_comma() {
+            let code = "match variable { Enum::One => 0 }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            assert_eq!(
+                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
+                "variable"
+            );
+
+            let branches = &nl_match.branches;
+            assert_eq!(branches.len(), 1);
+
+            let (branch, operation) = &branches[0];
+            let branch = unwrap_to!(branch => MatchBranch::Enum);
+            assert_eq!(branch.nl_enum, "Enum");
+            assert_eq!(branch.variant, "One");
+
+            assert_eq!(unwrap_constant_signed(operation), 0);
+
+            assert_eq!(branch.variables.len(), 0);
+        }
+
+        #[test]
+        fn one_branch_one_variable() {
+            let code = "match variable { Enum::One(a) => 0, }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            assert_eq!(
+                unwrap_to!(*nl_match.input => NLOperation::VariableAccess).get_name(),
+                "variable"
+            );
+
+            let branches = &nl_match.branches;
+            assert_eq!(branches.len(), 1);
 
             let (branch, operation) = &branches[0];
             let branch = unwrap_to!(branch => MatchBranch::Enum);
@@ -2754,6 +3959,124 @@ mod executable_blocks {
 
             assert_eq!(unwrap_constant_signed(operation), 0);
         }
+
+        #[test]
+        fn negative_range() {
+            let code = "match variable { -5..5 => 0 }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            let (branch, _) = &nl_match.branches[0];
+            assert_eq!(*branch, MatchBranch::Range((-5, 5)));
+        }
+
+        #[test]
+        fn value_match() {
+            let code = "match variable { 1 => 10, \"two\" => 20, _ => 30 }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            let branches = &nl_match.branches;
+            assert_eq!(branches.len(), 3);
+
+            let constant = unwrap_to!(&branches[0].0 => MatchBranch::Constant);
+            assert_eq!(*constant, OpConstant::Signed(1, NLType::I32));
+            let constant = unwrap_to!(&branches[1].0 => MatchBranch::Constant);
+            assert_eq!(*constant, OpConstant::String("two".to_string()));
+
+            let (branch, operation) = &branches[2];
+            assert_eq!(*branch, MatchBranch::AllOther);
+            assert_eq!(unwrap_constant_signed(operation), 30);
+        }
+
+        #[test]
+        fn or_pattern() {
+            let code = "match variable { 1 | 2 | 3 => 0, Enum::One(x) | Enum::Two(x) => x }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            let patterns = unwrap_to!(&nl_match.branches[0].0 => MatchBranch::Or);
+            assert_eq!(patterns.len(), 3);
+            assert_eq!(
+                patterns[2],
+                MatchBranch::Constant(OpConstant::Signed(3, NLType::I32))
+            );
+
+            let patterns = unwrap_to!(&nl_match.branches[1].0 => MatchBranch::Or);
+            assert_eq!(unwrap_to!(&patterns[1] => MatchBranch::Enum).variant, "Two");
+            assert_eq!(nl_match.branches[1].0.variables(), vec!["x"]);
+        }
+
+        #[test]
+        fn or_pattern_different_variables() {
+            let code = "match variable { Enum::One(x) | Enum::Two(y) => 0 }";
+            assert!(read_operation(code).is_err());
+        }
+
+        #[test]
+        fn binding() {
+            let code = "match variable { n @ 1..10 => n }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            let (branch, _) = &nl_match.branches[0];
+            assert_eq!(
+                *branch,
+                MatchBranch::Binding("n", Box::new(MatchBranch::Range((1, 10))))
+            );
+            assert_eq!(branch.variables(), vec!["n"]);
+        }
+
+        #[test]
+        fn type_match() {
+            let code = "match type shape { Circle(circle) => 0, Square => 1, _ => 2 }";
+            let operation = pretty_read(code, &read_operation);
+            let type_match = unwrap_to!(operation => NLOperation::TypeMatch);
+
+            assert_eq!(
+                unwrap_to!(*type_match.input => NLOperation::VariableAccess).get_name(),
+                "shape"
+            );
+
+            let branches = &type_match.branches;
+            assert_eq!(branches.len(), 3);
+            assert_eq!(
+                branches[0].0,
+                TypeMatchBranch::Struct("Circle", Some("circle"))
+            );
+            assert_eq!(branches[1].0, TypeMatchBranch::Struct("Square", None));
+            assert_eq!(branches[2].0, TypeMatchBranch::AllOther);
+            assert_eq!(unwrap_constant_signed(&branches[2].1), 2);
+        }
+
+        #[test]
+        fn guard() {
+            let code = "match variable { Enum::One(x) if x > 10 => 0, Enum::One(x) => 1 }";
+            let operation = pretty_read(code, &read_operation);
+            let nl_match = unwrap_to!(operation => NLOperation::Match);
+
+            let branches = &nl_match.branches;
+            assert_eq!(branches.len(), 2);
+
+            let (branch, operation) = &branches[0];
+            let guard = branch.guard().unwrap();
+            let operator = unwrap_to!(guard => NLOperation::Operator);
+            let (left, right) = unwrap_to!(operator => OpOperator::CompareGreater);
+            assert_eq!(
+                unwrap_to!(**left => NLOperation::VariableAccess).get_name(),
+                "x"
+            );
+            assert_eq!(unwrap_constant_signed(right), 10);
+
+            let pattern = unwrap_to!(branch.pattern() => MatchBranch::Enum);
+            assert_eq!(pattern.variant, "One");
+            assert_eq!(pattern.variables, vec!["x"]);
+            assert_eq!(unwrap_constant_signed(operation), 0);
+
+            let (branch, operation) = &branches[1];
+            assert!(branch.guard().is_none());
+            assert_eq!(unwrap_constant_signed(operation), 1);
+        }
     }
 
     mod function_calls {
@@ -2770,13 +4093,58 @@ mod executable_blocks {
         }
 
         #[test]
-        fn call_from_namespace() {
-            let code = "namespace.function()";
+        fn method_call() {
+            let code = "shape.area(scale, 2)";
             let operation = pretty_read(code, &read_operation);
-            let function = unwrap_to!(operation => NLOperation::FunctionCall);
+            let call = unwrap_to!(operation => NLOperation::MethodCall);
 
-            assert_eq!(function.path, "namespace.function");
-            assert_eq!(function.arguments.len(), 0);
+            assert_eq!(
+                *call.receiver,
+                NLOperation::VariableAccess(OpVariable { name: "shape" })
+            );
+            assert_eq!(call.method, "area");
+            assert_eq!(call.arguments.len(), 2);
+        }
+
+        #[test]
+        fn field_access() {
+            let code = "point.x";
+            let operation = pretty_read(code, &read_operation);
+            let access = unwrap_to!(operation => NLOperation::FieldAccess);
+
+            assert_eq!(
+                *access.structure,
+                NLOperation::VariableAccess(OpVariable { name: "point" })
+            );
+            assert_eq!(access.field, "x");
+        }
+
+        #[test]
+        fn chained() {
+            let code = "a.b.c()[0].d";
+            let operation = pretty_read(code, &read_operation);
+
+            let d = unwrap_to!(operation => NLOperation::FieldAccess);
+            assert_eq!(d.field, "d");
+            let index = unwrap_to!(&*d.structure => NLOperation::Index);
+            let c = unwrap_to!(&*index.collection => NLOperation::MethodCall);
+            assert_eq!(c.method, "c");
+            let b = unwrap_to!(&*c.receiver => NLOperation::FieldAccess);
+            assert_eq!(b.field, "b");
+            assert_eq!(
+                *b.structure,
+                NLOperation::VariableAccess(OpVariable { name: "a" })
+            );
+        }
+
+        #[test]
+        fn range_is_not_a_field() {
+            let code = "start..end";
+            let operation = pretty_read(code, &read_operation);
+            assert!(matches!(
+                operation,
+                NLOperation::Operator(OpOperator::Range(_))
+            ));
         }
 
         #[test]
@@ -2789,7 +4157,10 @@ mod executable_blocks {
 
             let arguments = &function.arguments;
             assert_eq!(arguments.len(), 1);
-            assert_eq!(arguments[0], "one");
+            assert_eq!(
+                arguments[0],
+                NLOperation::VariableAccess(OpVariable { name: "one" })
+            );
         }
 
         #[test]
@@ -2803,8 +4174,1808 @@ mod executable_blocks {
             let arguments = &function.arguments;
 
             assert_eq!(arguments.len(), 2);
-            assert_eq!(arguments[0], "one");
-            assert_eq!(arguments[1], "two");
+            assert_eq!(
+                arguments[0],
+                NLOperation::VariableAccess(OpVariable { name: "one" })
+            );
+            assert_eq!(
+                arguments[1],
+                NLOperation::VariableAccess(OpVariable { name: "two" })
+            );
+        }
+
+        #[test]
+        fn call_expression_args() {
+            let code = "function(one + 1, inner(two), (three, 4),)";
+            let operation = pretty_read(code, &read_operation);
+            let function = unwrap_to!(operation => NLOperation::FunctionCall);
+
+            let arguments = &function.arguments;
+            assert_eq!(arguments.len(), 3);
+            assert!(matches!(
+                arguments[0],
+                NLOperation::Operator(OpOperator::ArithmeticAdd(_))
+            ));
+
+            let inner = unwrap_to!(&arguments[1] => NLOperation::FunctionCall);
+            assert_eq!(inner.path, "inner");
+            assert_eq!(inner.arguments.len(), 1);
+
+            let items = unwrap_to!(&arguments[2] => NLOperation::Tuple);
+            assert_eq!(items.len(), 2);
+        }
+    }
+
+    mod struct_construction {
+        use super::*;
+
+        #[test]
+        fn fields() {
+            let code = "Point { x: 1, y: a + b, }";
+            let operation = pretty_read(code, &read_operation);
+            let construction = unwrap_to!(operation => NLOperation::StructConstruction);
+
+            assert_eq!(construction.name, "Point");
+            assert_eq!(construction.fields.len(), 2);
+            assert_eq!(construction.fields[0].0, "x");
+            assert!(matches!(construction.fields[0].1, NLOperation::Constant(_)));
+            assert_eq!(construction.fields[1].0, "y");
+            assert!(matches!(
+                construction.fields[1].1,
+                NLOperation::Operator(OpOperator::ArithmeticAdd(_))
+            ));
+        }
+
+        #[test]
+        fn empty() {
+            let operation = pretty_read("Empty {}", &read_operation);
+            let construction = unwrap_to!(operation => NLOperation::StructConstruction);
+            assert_eq!(construction.name, "Empty");
+            assert!(construction.fields.is_empty());
+        }
+
+        #[test]
+        fn nested() {
+            let code = "Line { start: Point { x: 0, y: 0 }, end: end }";
+            let operation = pretty_read(code, &read_operation);
+            let construction = unwrap_to!(operation => NLOperation::StructConstruction);
+            assert!(matches!(
+                construction.fields[0].1,
+                NLOperation::StructConstruction(_)
+            ));
+        }
+
+        #[test]
+        fn blocks_stay_blocks() {
+            for code in &[
+                "if ready { x }",
+                "if Ready { x }",
+                "while Ready { Ready = false }",
+            ] {
+                let operation = pretty_read(code, &read_operation);
+                assert!(
+                    matches!(operation, NLOperation::If(_) | NLOperation::WhileLoop(_)),
+                    "{}",
+                    code
+                );
+            }
+        }
+    }
+}
+
+// Sample programs in `tests/corpus/`, from simple to more involved. They show what the parser supports, so each new
+// feature should find its way into one of them.
+mod corpus {
+    use super::*;
+
+    fn corpus_files() -> Vec<std::path::PathBuf> {
+        let mut files: Vec<_> = std::fs::read_dir("tests/corpus")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension() == Some(std::ffi::OsStr::new("nl")))
+            .collect();
+        files.sort();
+
+        files
+    }
+
+    #[test]
+    fn every_file_parses() {
+        let files = corpus_files();
+        assert!(!files.is_empty(), "The corpus is empty.");
+
+        for path in files {
+            let result = parse_file(&path, &|file: &NLFile| {
+                if let Err(error) = crate::checking::check_file(file, &mut Vec::new()) {
+                    panic!("{} failed its checks:\n{}", path.display(), error);
+                }
+            });
+            if let Err(error) = result {
+                panic!("{} failed to parse:\n{}", path.display(), error);
+            }
+        }
+    }
+
+    // Splits source into rough tokens: names and numbers, strings, and single punctuation characters. Whitespace
+    // and comments are skipped. Returns where each one starts and ends.
+    fn token_spans(source: &str) -> Vec<(usize, usize)> {
+        let mut spans = vec![];
+        let mut characters = source.char_indices().peekable();
+
+        while let Some((start, c)) = characters.next() {
+            let mut end = start + c.len_utf8();
+            if c.is_whitespace() {
+                continue;
+            } else if c == '/' && source[end..].starts_with('/') {
+                while characters.next_if(|(_, c)| *c != '\n').is_some() {}
+                continue;
+            } else if c.is_alphanumeric() || c == '_' {
+                while let Some((_, c)) =
+                    characters.next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                {
+                    end += c.len_utf8();
+                }
+            } else if c == '"' {
+                for (index, c) in characters.by_ref() {
+                    end = index + c.len_utf8();
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+
+            spans.push((start, end));
         }
+
+        spans
+    }
+
+    // Every file in the corpus is broken one token at a time, by deleting it or by writing it twice. Whatever comes
+    // out, the parser and the checks have to return: no panics and no endless loops. When either fails it has to say
+    // where.
+    #[test]
+    fn error_injection() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut problems = vec![];
+
+            for path in corpus_files() {
+                let source = std::fs::read_to_string(&path).unwrap();
+
+                for (start, end) in token_spans(&source) {
+                    let deleted = format!("{}{}", &source[..start], &source[end..]);
+                    let duplicated = format!("{} {}", &source[..end], &source[start..]);
+
+                    for (change, broken) in
+                        [("deleting", deleted), ("duplicating", duplicated)].iter()
+                    {
+                        let result =
+                            std::panic::catch_unwind(|| match parse_string(broken, "broken.nl") {
+                                Ok(file) => crate::checking::check_file(&file, &mut Vec::new())
+                                    .err()
+                                    .map(|error| error.to_string()),
+                                Err(error) => Some(error.to_string()),
+                            });
+
+                        let problem = match result {
+                            Ok(Some(message)) if !message.contains("at line") => {
+                                "error without a location"
+                            }
+                            Ok(_) => continue,
+                            Err(_) => "panic",
+                        };
+
+                        problems.push(format!(
+                            "{}: {} `{}` at byte {}",
+                            problem,
+                            change,
+                            &source[start..end],
+                            start
+                        ));
+                    }
+                }
+            }
+
+            sender.send(problems).unwrap();
+        });
+
+        let problems = receiver
+            .recv_timeout(std::time::Duration::from_secs(60))
+            .expect("The parser got stuck on a broken file.");
+        assert!(problems.is_empty(), "{}", problems.join("\n"));
+    }
+
+    #[test]
+    fn shapes() {
+        parse_file(Path::new("tests/corpus/01_shapes.nl"), &|file: &NLFile| {
+            assert_eq!(file.traits.len(), 1);
+            assert_eq!(file.structs.len(), 2);
+            assert_eq!(file.functions.len(), 1);
+            for nl_struct in &file.structs {
+                assert_eq!(nl_struct.implementations.len(), 1);
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn state_machine() {
+        parse_file(
+            Path::new("tests/corpus/02_state_machine.nl"),
+            &|file: &NLFile| {
+                assert_eq!(file.enums.len(), 1);
+                assert_eq!(file.enums[0].variants.len(), 3);
+                assert_eq!(file.structs.len(), 1);
+                assert_eq!(file.functions.len(), 2);
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn tokenizer() {
+        parse_file(
+            Path::new("tests/corpus/03_tokenizer.nl"),
+            &|file: &NLFile| {
+                assert_eq!(file.structs[0].layout().unwrap().get_size(), 12);
+                assert_eq!(file.functions.len(), 4);
+            },
+        )
+        .unwrap();
+    }
+}
+
+mod layout {
+    use super::*;
+    use crate::parsing::layout::*;
+
+    fn offsets(layout: &StructLayout) -> Vec<usize> {
+        layout
+            .get_fields()
+            .iter()
+            .map(|field| field.get_offset())
+            .collect()
+    }
+
+    #[test]
+    fn repr_attribute() {
+        let file = parse_string(
+            "#[repr(c)] struct A {}\n#[repr(packed)]\nstruct B {}\nstruct C {}",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        assert_eq!(file.structs[0].get_repr(), Repr::C);
+        assert_eq!(file.structs[1].get_repr(), Repr::Packed);
+        assert_eq!(file.structs[2].get_repr(), Repr::Default);
+    }
+
+    #[test]
+    fn unknown_repr() {
+        let result = parse_string("#[repr(rust)] struct A {}", "test_file.nl");
+        let message = result
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(message.contains("expected `c` or `packed`"), "{}", message);
+    }
+
+    #[test]
+    fn c_layout() {
+        let file = parse_string(
+            "#[repr(c)] struct A { a: u8, b: u32, c: u16 }",
+            "test_file.nl",
+        )
+        .unwrap();
+        let layout = file.structs[0].layout().unwrap();
+
+        assert_eq!(offsets(&layout), vec![0, 4, 8]);
+        assert_eq!(layout.get_size(), 12);
+        assert_eq!(layout.get_alignment(), 4);
+    }
+
+    #[test]
+    fn packed_layout() {
+        let file = parse_string(
+            "#[repr(packed)] struct A { a: u8, b: u32, c: u16 }",
+            "test_file.nl",
+        )
+        .unwrap();
+        let layout = file.structs[0].layout().unwrap();
+
+        assert_eq!(offsets(&layout), vec![0, 1, 5]);
+        assert_eq!(layout.get_size(), 7);
+        assert_eq!(layout.get_alignment(), 1);
+    }
+
+    #[test]
+    fn default_layout() {
+        let file = parse_string(
+            "struct A { a: u8, b: u32, c: u16, d: *const A, e: (u8, u16) }",
+            "test_file.nl",
+        )
+        .unwrap();
+        let layout = file.structs[0].layout().unwrap();
+
+        // Largest alignment first: d, b, c, e, then a.
+        assert_eq!(offsets(&layout), vec![18, 8, 12, 0, 14]);
+        assert_eq!(layout.get_size(), 24);
+        assert_eq!(layout.get_alignment(), 8);
+    }
+
+    #[test]
+    fn type_sizes() {
+        assert_eq!(NLType::Boolean.size_of(), Some(1));
+        assert_eq!(NLType::F64.align_of(), Some(8));
+        assert_eq!(
+            NLType::Tuple(vec![NLType::U8, NLType::U32]).size_of(),
+            Some(8)
+        );
+        assert_eq!(NLType::Tuple(vec![]).size_of(), Some(0));
+        assert_eq!(NLType::OwnedStruct("A").size_of(), None);
+    }
+
+    #[test]
+    fn byte_order() {
+        let file = parse_string(
+            "#[repr(packed)] struct A { a: u16_le, b: u32_be, c: u8 }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        let variables = file.structs[0].get_variables();
+        assert_eq!(
+            variables[0].get_type(),
+            &NLType::Endian(ByteOrder::Little, Box::new(NLType::U16))
+        );
+        assert_eq!(
+            variables[1].get_type(),
+            &NLType::Endian(ByteOrder::Big, Box::new(NLType::U32))
+        );
+
+        let layout = file.structs[0].layout().unwrap();
+        assert_eq!(offsets(&layout), vec![0, 2, 6]);
+        assert_eq!(
+            layout
+                .get_fields()
+                .iter()
+                .map(|field| field.get_byte_order())
+                .collect::<Vec<_>>(),
+            vec![Some(ByteOrder::Little), Some(ByteOrder::Big), None]
+        );
+    }
+
+    #[test]
+    fn byte_order_not_a_number() {
+        let message = parse_string("struct A { a: bool_le }", "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(
+            message.contains("only numbers have a byte order"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn unsized_field() {
+        let file = parse_string("struct A { a: u8, b: B }", "test_file.nl").unwrap();
+
+        match file.structs[0].layout() {
+            Err(LayoutError::UnsizedField(name)) => assert_eq!(name, "b"),
+            result => panic!("Expected an unsized field, got {:?}", result),
+        }
+    }
+
+    // `sizeof` and `alignof` are answered once the file has been read, so they can size structs that hold other structs.
+    #[test]
+    fn type_queries_expanded() {
+        let file = parse_string(
+            "fn main() { sizeof(Outer) alignof(Outer) sizeof((u8, Inner)) sizeof(*const Level) }
+#[repr(c)] struct Outer { flag: bool, inner: Inner }
+struct Inner { a: u16, b: u32 }
+enum Level { Low, High }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        let size = |size| NLOperation::Constant(OpConstant::Unsigned(size, NLType::U64));
+        assert_eq!(
+            file.functions[0].block.as_ref().unwrap().operations,
+            vec![size(12), size(4), size(12), size(8)]
+        );
+    }
+
+    #[test]
+    fn type_queries_unsized() {
+        let message = parse_string(
+            "fn main() { sizeof(Level) alignof(Outer) sizeof(A) sizeof(Missing) sizeof(bytes) }
+struct Outer { inner: Inner }
+struct Inner { names: List<str> }
+struct A { b: B }
+struct B { a: A }
+enum Level { Low, High }",
+            "test_file.nl",
+        )
+        .err()
+        .expect("No error when one was expected.")
+        .to_string();
+
+        assert_eq!(
+            message.lines().collect::<Vec<_>>(),
+            vec![
+                "test_file.nl: sizeof in `main` can't size `Level`, which is an enum, and enums don't have a layout \
+                 yet",
+                "test_file.nl: alignof in `main` can't size `Outer`, since field `names` of `Inner` is `List<str>`, \
+                 which has no fixed size",
+                "test_file.nl: sizeof in `main` can't size `A`, since field `a` of `B` is `A`, which holds itself",
+                "test_file.nl: sizeof in `main` can't size `Missing`, which isn't a declared struct",
+                "test_file.nl: sizeof in `main` can't size `bytes`, which has no fixed size",
+            ]
+        );
+    }
+}
+
+mod values {
+    use super::*;
+    use crate::parsing::values::*;
+
+    const SCHEMA: &str = "enum Level { Low, High }
+struct Server { host: str, port: u16, weight: f32 }
+struct Config { name: str, verbose: bool, level: Level, servers: List<Server>, range: (i8, i8) }";
+
+    fn error_message(document: &str) -> String {
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+        parse_value_document(document, "config.nlv", &schema, "Config")
+            .expect_err("No error when one was expected.")
+            .to_string()
+    }
+
+    #[test]
+    fn nested_values() {
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+        let value = parse_value_document(
+            "// Fields can be written in any order.
+Config {
+    verbose: true,
+    name: \"main\",
+    level: High,
+    servers: [
+        { host: \"a\", port: 80, weight: 0.5 },
+        Server { port: 0x1F90, host: \"b\", weight: 1 },
+    ],
+    range: (-5, 5),
+}",
+            "config.nlv",
+            &schema,
+            "Config",
+        )
+        .unwrap();
+
+        let config = match value {
+            NLValue::Struct(config) => config,
+            value => panic!("Expected a struct, got {:?}", value),
+        };
+
+        assert_eq!(config.get_name(), "Config");
+        let names: Vec<&str> = config.get_fields().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["name", "verbose", "level", "servers", "range"]);
+
+        assert_eq!(
+            config.get_field("name"),
+            Some(&NLValue::String("main".to_string()))
+        );
+        assert_eq!(config.get_field("verbose"), Some(&NLValue::Boolean(true)));
+        assert_eq!(config.get_field("level"), Some(&NLValue::Enum("High")));
+        assert_eq!(
+            config.get_field("range"),
+            Some(&NLValue::Tuple(vec![
+                NLValue::Signed(-5),
+                NLValue::Signed(5)
+            ]))
+        );
+
+        match config.get_field("servers") {
+            Some(NLValue::List(servers)) => {
+                assert_eq!(servers.len(), 2);
+                match &servers[1] {
+                    NLValue::Struct(server) => {
+                        assert_eq!(server.get_field("port"), Some(&NLValue::Unsigned(8080)));
+                        assert_eq!(server.get_field("weight"), Some(&NLValue::Float(1.0)));
+                    }
+                    value => panic!("Expected a struct, got {:?}", value),
+                }
+            }
+            value => panic!("Expected a list, got {:?}", value),
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let message = error_message(
+            "{ name: \"main\", verbose: true, level: Low, servers: [{ host: \"a\", port: 70000, weight: 1 }], range: (0, 0) }",
+        );
+
+        assert!(message.contains("70000 doesn't fit in u16"), "{}", message);
+        assert!(message.starts_with("config.nlv: at line 1"), "{}", message);
+    }
+
+    #[test]
+    fn missing_field() {
+        let message = error_message(
+            "{\n    name: \"main\",\n    verbose: true,\n    servers: [],\n    range: (0, 0),\n}",
+        );
+
+        assert!(
+            message.contains("missing a value for field `level` of `Config`"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn unknown_field() {
+        let message = error_message("{\n    name: \"main\",\n    colour: true,\n}");
+
+        assert!(
+            message.contains(
+                "at line 3, `Config` has no field named `colour`:\n    colour: true,\n    ^"
+            ),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn suggestions() {
+        let message = error_message("{ name: \"main\", levl: Low }");
+        assert!(
+            message.contains("`Config` has no field named `levl`, did you mean `level`?"),
+            "{}",
+            message
+        );
+
+        let message =
+            error_message("{ name: \"\", verbose: true, level: Hihg, servers: [], range: (0, 0) }");
+        assert!(
+            message.contains("`Level` has no variant named `Hihg`, did you mean `High`?"),
+            "{}",
+            message
+        );
+
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+        let message = parse_value_document("{}", "config.nlv", &schema, "Confg")
+            .expect_err("No error when one was expected.")
+            .to_string();
+        assert!(
+            message.contains("there is no struct named `Confg`, did you mean `Config`?"),
+            "{}",
+            message
+        );
+
+        // Nothing is suggested when no name is close.
+        let message = error_message("{ name: \"main\", colour: true }");
+        assert!(!message.contains("did you mean"), "{}", message);
+    }
+
+    #[test]
+    fn wrong_type() {
+        let message =
+            error_message("{ name: 5, verbose: true, level: Low, servers: [], range: (0, 0) }");
+        assert!(message.contains("expected a string"), "{}", message);
+
+        let message = error_message(
+            "{ name: \"\", verbose: true, level: Medium, servers: [], range: (0, 0) }",
+        );
+        assert!(
+            message.contains("`Level` has no variant named `Medium`"),
+            "{}",
+            message
+        );
+
+        let message = error_message(
+            "Server { name: \"\", verbose: true, level: Low, servers: [], range: (0, 0) }",
+        );
+        assert!(
+            message.contains("expected a `Config`, found a `Server`"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let message = error_message("{ name: \"main\" verbose: true }");
+        assert!(
+            message.contains("expected `,` or `}` after field"),
+            "{}",
+            message
+        );
+
+        let message = error_message("{ name: \"main\" } {}");
+        assert!(
+            message.contains("unexpected input after the value"),
+            "{}",
+            message
+        );
+    }
+
+    #[derive(PartialEq, Debug)]
+    struct Server {
+        host: String,
+        port: u16,
+        weight: f32,
+    }
+
+    impl FromNLValue for Server {
+        fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+            match value {
+                NLValue::Struct(server) => Ok(Server {
+                    host: server.decode_field("host")?,
+                    port: server.decode_field("port")?,
+                    weight: server.decode_field("weight")?,
+                }),
+                _ => Err(DecodeError::WrongKind("a server")),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Debug)]
+    struct Config {
+        name: String,
+        level: String,
+        servers: Vec<Server>,
+        range: (i8, i8),
+    }
+
+    impl FromNLValue for Config {
+        fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+            match value {
+                NLValue::Struct(config) => Ok(Config {
+                    name: config.decode_field("name")?,
+                    level: config.decode_field("level")?,
+                    servers: config.decode_field("servers")?,
+                    range: config.decode_field("range")?,
+                }),
+                _ => Err(DecodeError::WrongKind("a config")),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_into_rust() {
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+        let config: Config = decode(
+            "{ name: \"main\", verbose: false, level: Low, servers: [{ host: \"a\", port: 80, weight: 2 }], range: (1, 2) }",
+            "config.nlv",
+            &schema,
+            "Config",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                name: "main".to_string(),
+                level: "Low".to_string(),
+                servers: vec![Server {
+                    host: "a".to_string(),
+                    port: 80,
+                    weight: 2.0,
+                }],
+                range: (1, 2),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+
+        let result: Result<Config, _> = decode("{}", "config.nlv", &schema, "Config");
+        assert!(matches!(result, Err(DecodeError::Parse(_))));
+
+        let result: Result<(u8, u8), _> = decode(
+            "{ name: \"\", verbose: false, level: Low, servers: [], range: (-1, 2) }",
+            "config.nlv",
+            &schema,
+            "Config",
+        );
+        assert!(matches!(result, Err(DecodeError::WrongKind(_))));
+
+        let value = NLValue::Tuple(vec![NLValue::Signed(-1), NLValue::Signed(2)]);
+        assert!(matches!(
+            <(u8, u8)>::from_nl_value(&value),
+            Err(DecodeError::OutOfRange)
+        ));
+    }
+
+    #[test]
+    fn write_document() {
+        let schema = parse_string(SCHEMA, "schema.nl").unwrap();
+        let document = "{ name: \"say \\\"hi\\\"\\n\", verbose: true, level: High, servers: [{ host: \"a\", port: 80, weight: 2 }, { host: \"b\", port: 81, weight: -0.5 }], range: (-1, 1) }";
+        let value = parse_value_document(document, "config.nlv", &schema, "Config").unwrap();
+
+        let written = write_value_document(&value);
+        assert_eq!(
+            written,
+            "Config {
+    name: \"say \\\"hi\\\"\\n\",
+    verbose: true,
+    level: High,
+    servers: [Server {
+        host: \"a\",
+        port: 80,
+        weight: 2.0,
+    }, Server {
+        host: \"b\",
+        port: 81,
+        weight: -0.5,
+    }],
+    range: (-1, 1),
+}
+"
+        );
+
+        // Writing a value and reading it back gives the same value.
+        let read_back = parse_value_document(&written, "config.nlv", &schema, "Config").unwrap();
+        assert_eq!(read_back, value);
+    }
+}
+
+mod deprecation {
+    use super::*;
+
+    #[test]
+    fn attribute() {
+        let file = parse_string(
+            "#[deprecated(\"use new_api\")] fn old_api() {}
+#[deprecated] unsafe fn older_api();
+fn new_api() {}
+trait Sized { #[deprecated(\"use width\")] get size(&self) -> u32; #[deprecated] met grow(); }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        assert_eq!(file.functions[0].get_deprecation(), Some("use new_api"));
+        assert_eq!(file.functions[1].get_deprecation(), Some(""));
+        assert!(file.functions[1].is_unsafe());
+        assert_eq!(file.functions[2].get_deprecation(), None);
+
+        match &file.traits[0].implementors[..] {
+            [NLImplementor::Getter(getter), NLImplementor::Method(method)] => {
+                assert_eq!(getter.get_deprecation(), Some("use width"));
+                assert_eq!(method.get_deprecation(), Some(""));
+            }
+            _ => panic!("Wrong implementors in trait."),
+        }
+    }
+
+    #[test]
+    fn bad_message() {
+        let message = parse_string("#[deprecated(use_new_api)] fn old_api() {}", "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+
+        assert!(
+            message.contains("expected the deprecation message in quotes"),
+            "{}",
+            message
+        );
+    }
+}
+
+mod constness {
+    use super::*;
+
+    #[test]
+    fn modifier() {
+        let file = parse_string(
+            "const fn square(x: i32) -> i32 { x * x }
+const unsafe fn raw() -> i32;
+fn other() {}
+struct Point {} impl Point { const met origin() -> i32 { 0 } }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        assert!(file.functions[0].is_const());
+        assert!(file.functions[1].is_const());
+        assert!(file.functions[1].is_unsafe());
+        assert!(!file.functions[2].is_const());
+
+        match &file.structs[0].implementations[0].implementors[..] {
+            [NLImplementor::Method(method)] => assert!(method.is_const()),
+            _ => panic!("Wrong implementors in implementation."),
+        }
+    }
+}
+
+mod paths {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let file = parse_string(
+            "enum Color { Red } struct Circle {} impl Circle { met new() {} }
+fn main() { Circle::new() Color::Red }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        assert_eq!(
+            file.functions[0].block,
+            Some(NLBlock {
+                operations: vec![
+                    NLOperation::FunctionCall(FunctionCall {
+                        path: "Circle::new",
+                        arguments: vec![],
+                    }),
+                    NLOperation::VariableAccess(OpVariable { name: "Color::Red" }),
+                ]
+            })
+        );
+    }
+}
+
+mod metrics {
+    use super::*;
+    use crate::parsing::metrics::*;
+
+    const CODE: &str = "struct Light { on: bool, }
+impl Self {
+    met toggle(&self, times: u32) {
+        for n in times {
+            if ready && n { match n { 3 => a, 4 => b, _ => c, } } else { d }
+        }
+    }
+}
+fn main() { let x = 1 }
+fn declared();";
+
+    #[test]
+    fn functions() {
+        let file = parse_string(CODE, "test_file.nl").unwrap();
+        let metrics = measure(&file);
+
+        let functions = metrics.get_functions();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].get_name(), "main");
+        assert_eq!(functions[0].get_complexity(), 1);
+        assert_eq!(functions[0].get_nesting(), 0);
+
+        let toggle = &functions[1];
+        assert_eq!(toggle.get_name(), "Light::toggle");
+        assert_eq!(toggle.get_arguments(), 2);
+        // The loop, the `if`, the `&&` and two more match branches.
+        assert_eq!(toggle.get_complexity(), 6);
+        assert_eq!(toggle.get_nesting(), 3);
+
+        assert_eq!(metrics.get_total_complexity(), 7);
+        assert_eq!(metrics.get_max_complexity(), 6);
+        assert_eq!(metrics.get_max_nesting(), 3);
+    }
+
+    #[test]
+    fn reports() {
+        let file = parse_string("fn f(a: i32) { if a { b } else { c } }", "test_file.nl").unwrap();
+        let metrics = measure(&file);
+
+        assert_eq!(
+            metrics.to_json(),
+            "{\"functions\":[{\"name\":\"f\",\"arguments\":1,\"complexity\":2,\"nesting\":1,\"operations\":4}],\
+\"totals\":{\"functions\":1,\"complexity\":2,\"max_complexity\":2,\"max_nesting\":1,\"operations\":4}}"
+        );
+        assert_eq!(
+            metrics.to_table(),
+            "function   arguments  complexity  nesting  operations
+f                  1           2        1           4
+total (1)                      2        1           4
+"
+        );
+    }
+}
+
+mod duplicates {
+    use super::*;
+    use crate::parsing::duplicates::*;
+
+    const FIRST: &str = "fn total(items: u32) {
+    sum = 0
+    for item in items { sum = sum + item * 2 }
+    log(sum)
+}
+fn other() { while running { tick(1) } }";
+    const SECOND: &str = "fn add_up(values: u32) {
+    acc = 10
+    for value in values { acc = acc + value * 3 }
+    log(acc)
+}
+fn log_twice(values: u32) {
+    acc = 10
+    for value in values { acc = acc + value * 3 }
+    log(acc)
+    log(acc)
+}
+fn another() { while running { tick(2) } }";
+
+    #[test]
+    fn across_files() {
+        let first = parse_string(FIRST, "first.nl").unwrap();
+        let second = parse_string(SECOND, "second.nl").unwrap();
+        let files = [("first.nl", FIRST, &first), ("second.nl", SECOND, &second)];
+
+        let duplicates = find_duplicates(&files, 7);
+        assert_eq!(duplicates.len(), 1, "{:?}", duplicates);
+
+        let locations = duplicates[0].get_locations();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].get_file(), "first.nl");
+        assert_eq!(locations[0].get_body(), "total");
+        assert_eq!(locations[1].get_file(), "second.nl");
+        assert_eq!(locations[1].get_body(), "add_up");
+
+        // From the name of the function to the last variable it uses.
+        let (offset, length) = locations[0].get_span().unwrap();
+        assert_eq!(
+            &FIRST[offset..offset + length],
+            &FIRST[3..FIRST.find("sum)").unwrap() + 3]
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let first = parse_string(FIRST, "first.nl").unwrap();
+        let second = parse_string(SECOND, "second.nl").unwrap();
+        let files = [("first.nl", FIRST, &first), ("second.nl", SECOND, &second)];
+
+        // The loops aren't reported again inside of the bodies they were found in, but `log_twice` has one too.
+        let duplicates = find_duplicates(&files, 3);
+        let bodies: Vec<Vec<&str>> = duplicates
+            .iter()
+            .map(|duplicate| {
+                duplicate
+                    .get_locations()
+                    .iter()
+                    .map(Location::get_body)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            bodies,
+            vec![
+                vec!["total", "add_up"],
+                vec!["total", "add_up", "log_twice"],
+                vec!["other", "another"],
+            ]
+        );
+        assert!(find_duplicates(&files, 13).is_empty());
+    }
+}
+
+mod fixes {
+    use super::*;
+
+    fn reported(code: &str) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let _ = parse_string_with_sink(code, "test_file.nl", &mut diagnostics);
+        diagnostics
+    }
+
+    #[test]
+    fn missing_comma() {
+        let code = "struct Point {\n    x: i32\n    y: i32,\n}";
+        let diagnostics = reported(code);
+        assert_eq!(diagnostics[0].get_severity(), Severity::Error);
+        assert_eq!(
+            apply_fixes(code, &diagnostics),
+            "struct Point {\n    x: i32,\n    y: i32,\n}"
+        );
+
+        // The comma can't go after a line comment, so it goes before the next field instead.
+        let code = "struct Point { x: i32 // across\n y: i32 }";
+        assert_eq!(
+            apply_fixes(code, &reported(code)),
+            "struct Point { x: i32 // across\n ,y: i32 }"
+        );
+    }
+
+    #[test]
+    fn upgrade_notes() {
+        let code = "fn f() { let t = (x) }";
+        let diagnostics = reported(code);
+        assert_eq!(diagnostics[0].get_severity(), Severity::Note);
+        assert_eq!(apply_fixes(code, &diagnostics), "fn f() { let t = (x,) }");
+    }
+
+    #[test]
+    fn overlapping() {
+        let code = "\u{feff}abc";
+        let diagnostics = vec![
+            Diagnostic::warning("first".to_string()).with_fix(vec![TextEdit::new(
+                0,
+                2,
+                "xy".to_string(),
+            )]),
+            // Overlaps the first, so none of it is made.
+            Diagnostic::warning("second".to_string()).with_fix(vec![
+                TextEdit::new(1, 1, "z".to_string()),
+                TextEdit::new(3, 0, "!".to_string()),
+            ]),
+            // Past the end of the text.
+            Diagnostic::warning("third".to_string()).with_fix(vec![TextEdit::new(
+                4,
+                0,
+                "?".to_string(),
+            )]),
+            Diagnostic::warning("fourth".to_string()).with_fix(vec![TextEdit::new(
+                2,
+                1,
+                "C".to_string(),
+            )]),
+        ];
+        assert_eq!(apply_fixes(code, &diagnostics), "\u{feff}xyC");
+    }
+}
+
+mod enums {
+    use super::*;
+
+    #[test]
+    fn enum_types() {
+        let file = parse_string(
+            "enum Level { Low, High, }
+struct Point { x: i32, y: i32, }
+struct Sensor { level: Level, }
+fn read(sensor: Sensor, point: Point) -> List<Level> { let last: (Level, i32) = sample() }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        assert_eq!(file.get_enums()[0].get_name(), "Level");
+        assert_eq!(
+            *file.structs[1].get_variables()[0].get_type(),
+            NLType::Enum("Level")
+        );
+
+        let function = &file.functions[0];
+        assert_eq!(
+            function.get_arguments()[1].nl_type,
+            NLType::OwnedStruct("Point")
+        );
+        assert_eq!(
+            *function.get_return_type(),
+            NLType::List(Box::new(NLType::Enum("Level")))
+        );
+
+        let operations = &function.block.as_ref().unwrap().operations;
+        let assignment = unwrap_to!(&operations[0] => NLOperation::Assign);
+        assert_eq!(
+            assignment.type_assignments,
+            vec![NLType::Tuple(vec![NLType::Enum("Level"), NLType::I32])]
+        );
+    }
+
+    const ERRORS: &str = "error FileError { NotFound, Denied(str), }
+error ConfigError { File(FileError), Missing(name: str), }
+";
+
+    #[test]
+    fn error_declarations() {
+        let file = parse_string(ERRORS, "test_file.nl").unwrap();
+
+        let file_error = &file.get_enums()[0];
+        assert!(file_error.is_error());
+        assert_eq!(file_error.get_name(), "FileError");
+        assert_eq!(
+            file_error.get_variants()[1].get_payload(),
+            vec![&NLType::BorrowedString]
+        );
+        assert!(!parse_string("enum Level { Low, }", "test_file.nl")
+            .unwrap()
+            .get_enums()[0]
+            .is_error());
+    }
+
+    #[test]
+    fn error_conversions() {
+        let file = parse_string(ERRORS, "test_file.nl").unwrap();
+
+        assert_eq!(
+            file.get_error_conversion("FileError", "ConfigError"),
+            Some("File")
+        );
+        assert_eq!(file.get_error_conversion("ConfigError", "FileError"), None);
+        assert_eq!(file.get_error_conversion("Missing", "ConfigError"), None);
+    }
+}
+
+mod reflection {
+    use super::*;
+
+    const DECLARATIONS: &str = "struct Point { x: i32, y: i32, }
+enum Level { Low, High, }
+trait Shape {}
+";
+
+    fn strings(names: &[&str]) -> NLOperation<'static> {
+        NLOperation::List(
+            names
+                .iter()
+                .map(|name| NLOperation::Constant(OpConstant::String(name.to_string())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn expanded() {
+        let code = format!(
+            "{}fn describe() {{ name_of(Point) fields_of(Point) variants_of(Level) name_of(Shape) }}",
+            DECLARATIONS
+        );
+        let file = parse_string(&code, "test_file.nl").unwrap();
+
+        let operations = &file.functions[0].block.as_ref().unwrap().operations;
+        assert_eq!(
+            operations[0],
+            NLOperation::Constant(OpConstant::String("Point".to_string()))
+        );
+        assert_eq!(operations[1], strings(&["x", "y"]));
+        assert_eq!(operations[2], strings(&["Low", "High"]));
+        assert_eq!(
+            operations[3],
+            NLOperation::Constant(OpConstant::String("Shape".to_string()))
+        );
+    }
+
+    #[test]
+    fn declared_later() {
+        let file = parse_string(
+            "fn describe() { if true { fields_of(Late) } else {} } struct Late { value: u8, }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        match &file.functions[0].block.as_ref().unwrap().operations[0] {
+            NLOperation::If(statement) => {
+                assert_eq!(statement.true_block.operations[0], strings(&["value"]))
+            }
+            operation => panic!("Wrong operation: {:?}", operation),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let message = |body: &str| {
+            parse_string(
+                &format!("{}fn describe() {{ {} }}", DECLARATIONS, body),
+                "test_file.nl",
+            )
+            .err()
+            .expect("No error when one was expected.")
+            .to_string()
+        };
+
+        assert_eq!(
+            message("name_of(Piont)"),
+            "test_file.nl: unknown type `Piont` given to name_of in `describe`, did you mean `Point`?"
+        );
+        assert_eq!(
+            message("fields_of(Level)"),
+            "test_file.nl: fields_of in `describe` needs a struct, but `Level` is an enum"
+        );
+        assert_eq!(
+            message("variants_of(Point)"),
+            "test_file.nl: variants_of in `describe` needs an enum, but `Point` is a struct"
+        );
+        assert_eq!(
+            message("name_of(u32)"),
+            "test_file.nl: name_of in `describe` needs the name of a struct, enum or trait"
+        );
+    }
+}
+
+mod macros {
+    use super::*;
+
+    fn message(code: &str) -> String {
+        parse_string(code, "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string()
+    }
+
+    #[test]
+    fn expanded() {
+        let file = parse_string(
+            "macro log_call(name) { let copy = name log(copy) }
+fn main(value: u32) { log_call(value) }",
+            "test_file.nl",
+        )
+        .unwrap();
+        assert_eq!(file.get_macros()[0].get_name(), "log_call");
+        assert_eq!(file.get_macros()[0].get_parameters(), &vec!["name"]);
+
+        let expected = parse_string(
+            "fn main(value: u32) { { let copy = value log(copy) } }",
+            "test_file.nl",
+        )
+        .unwrap();
+        assert_eq!(file.functions[0].block, expected.functions[0].block);
+    }
+
+    #[test]
+    fn nested() {
+        let file = parse_string(
+            "fn main(value: u32) { outer(value) }
+macro outer(x) { inner(x) inner(x) }
+macro inner(y) { log(y) }",
+            "test_file.nl",
+        )
+        .unwrap();
+
+        let expected = parse_string(
+            "fn main(value: u32) { { { log(value) } { log(value) } } }",
+            "test_file.nl",
+        )
+        .unwrap();
+        assert_eq!(file.functions[0].block, expected.functions[0].block);
+    }
+
+    #[test]
+    fn hygiene() {
+        assert_eq!(
+            message("macro bump() { count }"),
+            "test_file.nl: macro `bump` uses `count`, which isn't one of its parameters or variables"
+        );
+        assert_eq!(
+            message("macro double(x) { let y = x log(y) } fn main(y: u32) { double(y) }"),
+            "test_file.nl: `y` can't be given to macro `double`, which has a variable of its own with that name in `main`"
+        );
+        assert_eq!(
+            message("macro double(x) { log(x, x) } fn main(y: u32) { double(y + 1) }"),
+            "test_file.nl: macro `double` can only be given variables in `main`"
+        );
+    }
+
+    #[test]
+    fn function_names() {
+        assert_eq!(
+            message("macro f(x) { log(x) } fn f(x: i32) {} fn main(a: i32) { f(a) }"),
+            "test_file.nl: macro `f` has the same name as a function, so calls to it can't say which they mean"
+        );
+    }
+
+    #[test]
+    fn backtraces() {
+        assert_eq!(
+            message("macro one(x) { log(x) } macro two(x) { one(x,x) } fn main(v: u32) { two(v) }"),
+            "test_file.nl: macro `one` takes 1 argument but was given 2, while expanding `two` in `main`"
+        );
+        assert_eq!(
+            message("macro ping(x) { pong(x) } macro pong(x) { ping(x) } fn main(v: u32) { ping(v) }"),
+            "test_file.nl: macro `ping` expands to itself, while expanding `pong` from `ping` in `main`"
+        );
+    }
+}
+
+mod pragmas {
+    use super::*;
+    use crate::parsing::pragmas::*;
+
+    const PROPAGATING: &str = "fn load() { let value = read()? value }";
+
+    #[test]
+    fn feature_enables_syntax() {
+        let code = format!("#![feature(error_propagation)]\n{}", PROPAGATING);
+        let file = parse_string(&code, "test_file.nl").unwrap();
+        assert_eq!(file.get_features(), &vec![Feature::ErrorPropagation]);
+
+        let block = file.functions[0].block.as_ref().unwrap();
+        let assignment = unwrap_to!(&block.operations[0] => NLOperation::Assign);
+        let operator = unwrap_to!(&*assignment.assignment => NLOperation::Operator);
+        let call = unwrap_to!(operator => OpOperator::PropError);
+        assert!(matches!(**call, NLOperation::FunctionCall(_)));
+    }
+
+    #[test]
+    fn feature_required() {
+        let message = parse_string(PROPAGATING, "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+        assert!(
+            message
+                .contains("`?` is experimental, enable it with `#![feature(error_propagation)]`"),
+            "{}",
+            message
+        );
+
+        // A file that enabled the feature doesn't enable it for the next one.
+        parse_string(
+            &format!("#![feature(error_propagation)]\n{}", PROPAGATING),
+            "test_file.nl",
+        )
+        .unwrap();
+        assert!(parse_string(PROPAGATING, "test_file.nl").is_err());
+    }
+
+    #[test]
+    fn unknown_feature() {
+        let message = parse_string("#![feature(closures)]\nfn f() {}", "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+        assert!(message.contains("unknown feature"), "{}", message);
+    }
+
+    #[test]
+    fn no_features() {
+        let file = parse_string("fn f() {}", "test_file.nl").unwrap();
+        assert!(file.get_features().is_empty());
+    }
+
+    fn first_operation<'a>(file: &'a NLFile<'a>) -> &'a NLOperation<'a> {
+        &file.functions[0].block.as_ref().unwrap().operations[0]
+    }
+
+    #[test]
+    fn version_pragma() {
+        let file = parse_string("#![nl_version = \"0.2\"]\nfn f() {}", "test_file.nl").unwrap();
+        assert_eq!(file.get_language_version(), LanguageVersion::V0_2);
+
+        // Files that don't say are read as 0.1, unless the options ask for something else.
+        let file = parse_string("fn f() {}", "test_file.nl").unwrap();
+        assert_eq!(file.get_language_version(), LanguageVersion::V0_1);
+
+        let options = ParseOptions {
+            language_version: LanguageVersion::LATEST,
+            ..ParseOptions::default()
+        };
+        let file =
+            parse_string_with_options("fn f() {}", "test_file.nl", &options, &mut Vec::new())
+                .unwrap();
+        assert_eq!(file.get_language_version(), LanguageVersion::V0_2);
+    }
+
+    #[test]
+    fn brackets_by_version() {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let file = parse_string_with_sink("fn f() {\n    (1)\n}", "test_file.nl", &mut diagnostics)
+            .unwrap();
+        assert!(matches!(first_operation(&file), NLOperation::Tuple(items) if items.len() == 1));
+
+        assert_eq!(diagnostics.len(), 1, "Wrong number of diagnostics.");
+        assert_eq!(diagnostics[0].get_severity(), Severity::Note);
+        assert!(
+            diagnostics[0]
+                .get_message()
+                .starts_with("test_file.nl: line 2: `(x)` is a tuple of one"),
+            "{}",
+            diagnostics[0].get_message()
+        );
+
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let file = parse_string_with_sink(
+            "#![nl_version = \"0.2\"]\nfn f() { (1) (1,) }",
+            "test_file.nl",
+            &mut diagnostics,
+        )
+        .unwrap();
+        let operations = &file.functions[0].block.as_ref().unwrap().operations;
+        assert_eq!(
+            operations[0],
+            NLOperation::Constant(OpConstant::Signed(1, NLType::I32))
+        );
+        assert!(matches!(&operations[1], NLOperation::Tuple(items) if items.len() == 1));
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn unknown_version() {
+        let message = parse_string("#![nl_version = \"9.0\"]\nfn f() {}", "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+        assert!(message.contains("unknown language version"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_lint() {
+        let message = parse_string("#![deny(shadows)]\nfn f() {}", "test_file.nl")
+            .err()
+            .expect("No error when one was expected.")
+            .to_string();
+        assert!(message.contains("unknown lint"), "{}", message);
+    }
+}
+
+mod migration {
+    use super::*;
+    use crate::parsing::migration::*;
+    use crate::parsing::pragmas::*;
+
+    #[test]
+    fn adds_version() {
+        let migration = migrate(
+            "// Pairs.\nfn f() {\n    (1)\n    (1, 2)\n}\n",
+            "test_file.nl",
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            migration.get_source(),
+            "#![nl_version = \"0.2\"]\n// Pairs.\nfn f() {\n    (1,)\n    (1, 2)\n}\n"
+        );
+        assert!(migration.get_unmigrated().is_empty());
+
+        // The rewritten file has to still mean the same thing.
+        let old = parse_string("fn f() {\n    (1)\n    (1, 2)\n}\n", "test_file.nl").unwrap();
+        let new = parse_string(migration.get_source(), "test_file.nl").unwrap();
+        assert_eq!(old.functions[0].block, new.functions[0].block);
+        assert_eq!(new.get_language_version(), LanguageVersion::V0_2);
+    }
+
+    #[test]
+    fn replaces_version() {
+        let migration = migrate(
+            "#![feature(error_propagation)]\n#![nl_version = \"0.1\"]\nfn f() { (value) }",
+            "test_file.nl",
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            migration.get_source(),
+            "#![feature(error_propagation)]\n#![nl_version = \"0.2\"]\nfn f() { (value,) }"
+        );
+    }
+
+    #[test]
+    fn already_latest() {
+        let input = "#![nl_version = \"0.2\"]\nfn f() { (1) }";
+        let migration = migrate(input, "test_file.nl", &ParseOptions::default()).unwrap();
+        assert_eq!(migration.get_source(), input);
+
+        // Nor does a file without a version when it's read as the latest one anyway.
+        let options = ParseOptions {
+            language_version: LanguageVersion::LATEST,
+            ..ParseOptions::default()
+        };
+        let migration = migrate("fn f() { (1) }", "test_file.nl", &options).unwrap();
+        assert_eq!(migration.get_source(), "fn f() { (1) }");
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(migrate("fn f( {}", "test_file.nl", &ParseOptions::default()).is_err());
+    }
+}
+
+mod includes {
+    use super::*;
+    use crate::parsing::includes::*;
+    use std::path::PathBuf;
+
+    // Writes each of the files into a directory of its own under the temporary directory.
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("nl_includes_{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+        for (path, contents) in files {
+            let path = directory.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn merges() {
+        let directory = directory(
+            "merges",
+            &[
+                ("main.nl", "include \"common/types.nl\";\nfn main() {}\n"),
+                ("common/types.nl", "struct Point {\n    x: i32,\n}"),
+            ],
+        );
+
+        let merged = read_with_includes(&directory.join("main.nl"), &[]).unwrap();
+        assert_eq!(merged, "struct Point {\n    x: i32,\n}\nfn main() {}\n");
+
+        let file = parse_string(&merged, "main.nl").unwrap();
+        assert_eq!(file.structs[0].get_name(), "Point");
+        assert_eq!(file.functions[0].get_name(), "main");
+    }
+
+    #[test]
+    fn include_paths() {
+        let directory = directory(
+            "include_paths",
+            &[
+                ("src/main.nl", "include \"types.nl\";\n"),
+                ("library/types.nl", "struct Point {}\n"),
+            ],
+        );
+
+        assert!(read_with_includes(&directory.join("src/main.nl"), &[]).is_err());
+        let merged =
+            read_with_includes(&directory.join("src/main.nl"), &[directory.join("library")])
+                .unwrap();
+        assert_eq!(merged, "struct Point {}\n");
+
+        // The options carry the include paths through to parsing.
+        let options = ParseOptions {
+            include_paths: vec![directory.join("library")],
+            ..ParseOptions::default()
+        };
+        let names = parse_file_with_options(
+            &directory.join("src/main.nl"),
+            &options,
+            &mut Vec::new(),
+            &|file| file.structs[0].get_name().to_string(),
+        )
+        .unwrap();
+        assert_eq!(names, "Point");
+    }
+
+    // Lines in parse errors are lines of the file the error is in, not of the merged text.
+    #[test]
+    fn error_lines() {
+        let directory = directory(
+            "error_lines",
+            &[
+                (
+                    "main.nl",
+                    "include \"a.nl\";\nstruct Main {\n    x i32,\n}\n",
+                ),
+                ("a.nl", "// Types.\ninclude \"b.nl\";\nstruct A {}\n"),
+                ("b.nl", "struct B {}\n"),
+            ],
+        );
+        let main = directory.join("main.nl");
+
+        let error = parse_file(&main, &|_| {}).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("0: at line 3:"), "{}", message);
+
+        std::fs::write(&main, "include \"a.nl\";\nstruct Main {}\n").unwrap();
+        std::fs::write(
+            directory.join("b.nl"),
+            "struct B {}\nstruct Broken {\n    x i32,\n}\n",
+        )
+        .unwrap();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let error =
+            parse_file_with_options(&main, &ParseOptions::default(), &mut diagnostics, &|_| {})
+                .unwrap_err();
+        let message = error.to_string();
+        let location = format!(
+            "0: at line 3 in `b.nl`, included from `a.nl` line 2, included from `{}` line 1:",
+            main.display()
+        );
+        assert!(message.starts_with(&location), "{}", message);
+        assert_eq!(diagnostics[0].get_message(), message);
+    }
+
+    #[test]
+    fn included_once() {
+        let directory = directory(
+            "included_once",
+            &[
+                ("main.nl", "include \"a.nl\";\ninclude \"b.nl\";\n"),
+                ("a.nl", "include \"shared.nl\";\nstruct A {}\n"),
+                ("b.nl", "include \"shared.nl\";\nstruct B {}\n"),
+                ("shared.nl", "struct Shared {}\n"),
+            ],
+        );
+
+        let merged = read_with_includes(&directory.join("main.nl"), &[]).unwrap();
+        assert_eq!(merged, "struct Shared {}\nstruct A {}\nstruct B {}\n");
+    }
+
+    #[test]
+    fn missing() {
+        let directory = directory(
+            "missing",
+            &[
+                ("main.nl", "// Types.\ninclude \"a.nl\";\n"),
+                ("a.nl", "\ninclude \"nowhere.nl\";\n"),
+            ],
+        );
+
+        let main = directory.join("main.nl");
+        let error = read_with_includes(&main, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "couldn't find `nowhere.nl` in `a.nl` line 2, included from `{}` line 2",
+                main.display()
+            )
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let directory = directory(
+            "cycle",
+            &[
+                ("a.nl", "include \"b.nl\";\n"),
+                ("b.nl", "include \"c.nl\";\n"),
+                ("c.nl", "include \"b.nl\";\n"),
+            ],
+        );
+
+        let main = directory.join("a.nl");
+        let error = read_with_includes(&main, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "`b.nl` includes itself through b.nl -> c.nl -> b.nl in `c.nl` line 1, included from `b.nl` line 1, \
+                 included from `{}` line 1",
+                main.display()
+            )
+        );
+    }
+
+    #[test]
+    fn not_an_include() {
+        // Only a whole line is an include, so an identifier that happens to start with it is left alone.
+        let directory = directory(
+            "not_an_include",
+            &[("main.nl", "fn f() {\n    included\n}\n")],
+        );
+
+        let merged = read_with_includes(&directory.join("main.nl"), &[]).unwrap();
+        assert_eq!(merged, "fn f() {\n    included\n}\n");
+    }
+
+    #[test]
+    fn prelude() {
+        let directory = directory(
+            "prelude",
+            &[
+                ("main.nl", "#![allow(shadowing)]\nfn main() {}\n"),
+                ("alone.nl", "#![no_prelude]\nfn main() {}\n"),
+                ("twice.nl", "include \"prelude.nl\";\nfn main() {}\n"),
+                ("std/prelude.nl", "include \"option.nl\";\nstruct Point {}"),
+                ("std/option.nl", "enum Option { Some, None }\n"),
+            ],
+        );
+        let include_paths = [directory.join("std")];
+        let prelude = [PathBuf::from("prelude.nl")];
+
+        let merged =
+            read_with_prelude(&directory.join("main.nl"), &include_paths, &prelude).unwrap();
+        assert_eq!(
+            merged,
+            "#![allow(shadowing)]\nenum Option { Some, None }\nstruct Point {}\nfn main() {}\n"
+        );
+        let (_, lines) =
+            read_with_line_map(&directory.join("main.nl"), &include_paths, &prelude).unwrap();
+        assert_eq!(lines.locate(1), "line 1");
+        assert_eq!(
+            lines.locate(2),
+            "line 1 in `option.nl`, included from `prelude.nl` line 1"
+        );
+        assert_eq!(lines.locate(4), "line 2");
+
+        let merged =
+            read_with_prelude(&directory.join("alone.nl"), &include_paths, &prelude).unwrap();
+        assert_eq!(merged, "#![no_prelude]\nfn main() {}\n");
+
+        // What the file already includes isn't merged in again.
+        let merged =
+            read_with_prelude(&directory.join("twice.nl"), &include_paths, &prelude).unwrap();
+        assert_eq!(
+            merged,
+            "enum Option { Some, None }\nstruct Point {}\nfn main() {}\n"
+        );
+
+        let error = read_with_prelude(&directory.join("main.nl"), &[], &prelude).unwrap_err();
+        assert_eq!(error.to_string(), "couldn't find prelude file `prelude.nl`");
+
+        let options = ParseOptions {
+            include_paths: include_paths.to_vec(),
+            prelude: prelude.to_vec(),
+            ..ParseOptions::default()
+        };
+        let counts = |name: &str| {
+            parse_file_with_options(&directory.join(name), &options, &mut Vec::new(), &|file| {
+                (file.structs.len(), file.enums.len())
+            })
+            .unwrap()
+        };
+        assert_eq!(counts("main.nl"), (1, 1));
+        assert_eq!(counts("alone.nl"), (0, 0));
+    }
+}
+
+mod keywords {
+    use super::*;
+
+    #[test]
+    fn whole_word() {
+        assert_eq!(keyword("let")("let x"), Ok((" x", "let")));
+        assert_eq!(keyword("self")("self)"), Ok((")", "self")));
+        assert_eq!(keyword("fn")("fn"), Ok(("", "fn")));
+    }
+
+    #[test]
+    fn prefix_of_name() {
+        assert!(keyword("let")("letter").is_err());
+        assert!(keyword("as")("assert").is_err());
+        assert!(keyword("mut")("mut_count").is_err());
+        assert!(keyword("struct")("struct2").is_err());
+    }
+}
+
+mod fingerprint {
+    use super::*;
+
+    #[test]
+    fn formatting_does_not_matter() {
+        let a = parse_string("struct MyStruct { a: i32 } fn f() -> i32 { 5 }", "a.nl").unwrap();
+        let b = parse_string(
+            "// A comment.\nstruct MyStruct {\n    a : i32,\n}\n\n/* Another. */ fn f() -> i32 {\n    5\n}\n",
+            "b.nl",
+        )
+        .unwrap();
+
+        assert_eq!(ast_fingerprint(&a), ast_fingerprint(&b));
+    }
+
+    #[test]
+    fn content_changes_fingerprint() {
+        let a = parse_string("fn f() -> i32 { 5 }", "a.nl").unwrap();
+        let b = parse_string("fn f() -> i32 { 6 }", "a.nl").unwrap();
+        let c = parse_string("fn f() -> i64 { 5 }", "a.nl").unwrap();
+
+        assert_ne!(ast_fingerprint(&a), ast_fingerprint(&b));
+        assert_ne!(ast_fingerprint(&a), ast_fingerprint(&c));
+    }
+
+    #[test]
+    fn pragmas_change_fingerprint() {
+        let code = "fn f() -> i32 { 5 }";
+        let plain = parse_string(code, "a.nl").unwrap();
+        let pragmas = [
+            "#![deny(shadowing)]\n",
+            "#![feature(error_propagation)]\n",
+            "#![nl_version = \"0.2\"]\n",
+        ];
+
+        for pragma in pragmas.iter() {
+            let code = format!("{}{}", pragma, code);
+            let file = parse_string(&code, "a.nl").unwrap();
+            assert_ne!(
+                ast_fingerprint(&plain),
+                ast_fingerprint(&file),
+                "{}",
+                pragma
+            );
+        }
+    }
+
+    #[test]
+    fn floats() {
+        let a = parse_string("fn f() { 1.5 }", "a.nl").unwrap();
+        let b = parse_string("fn f() { 1.5f32 }", "a.nl").unwrap();
+        let c = parse_string("fn f() { 1.5f64 }", "a.nl").unwrap();
+
+        assert_eq!(ast_fingerprint(&a), ast_fingerprint(&b));
+        assert_ne!(ast_fingerprint(&a), ast_fingerprint(&c));
+    }
+
+    #[test]
+    fn empty_file() {
+        let file = parse_string("", "a.nl").unwrap();
+
+        // Pinned so that the hash doesn't silently change between builds.
+        assert_eq!(ast_fingerprint(&file), 0xb9b2_3f3a_46fd_0825);
     }
 }
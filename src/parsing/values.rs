@@ -0,0 +1,748 @@
+// Value documents hold a single struct literal instead of declarations, so NL's struct syntax can be used to write
+// configuration files. The document is read in two steps: first its shape is parsed without knowing what it should
+// contain, then every value is checked against the struct it's meant to be, using the declarations of another file.
+//
+// This isn't a serde data format. Rust types are read with `FromNLValue` and written by building an `NLValue` for
+// `write_value_document`, so the crate doesn't depend on serde.
+
+use super::*;
+use crate::diagnostics::did_you_mean;
+use std::convert::TryFrom;
+
+/// A value read from a value document. Numbers have already been checked to fit the type that was declared for
+/// them, so the declared type is all that's needed to turn them back into something narrower.
+#[derive(PartialEq, Debug, Clone)]
+pub enum NLValue<'a> {
+    Boolean(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    String(String),
+    List(Vec<NLValue<'a>>),
+    Tuple(Vec<NLValue<'a>>),
+    Struct(NLStructValue<'a>),
+    Enum(&'a str), // The name of the variant.
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct NLStructValue<'a> {
+    name: &'a str,
+    fields: Vec<(&'a str, NLValue<'a>)>, // In the order the struct declares them, not the order they were written.
+}
+
+impl<'a> NLStructValue<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+    pub fn get_fields(&self) -> &Vec<(&'a str, NLValue<'a>)> {
+        &self.fields
+    }
+    pub fn get_field(&self, name: &str) -> Option<&NLValue<'a>> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+}
+
+// The shape of a value before we know what type it's supposed to be. Each one remembers where it starts in the
+// document so that type errors can point at it.
+struct RawValue<'a> {
+    position: &'a str,
+    kind: RawKind<'a>,
+}
+
+enum RawKind<'a> {
+    Boolean(bool),
+    Integer(ParsedInteger<'a>),
+    Float(&'a str),
+    String(String),
+    List(Vec<RawValue<'a>>),
+    Tuple(Vec<RawValue<'a>>),
+    Struct(Option<&'a str>, Vec<(&'a str, RawValue<'a>)>),
+    Name(&'a str),
+}
+
+// Reads the items of a list or tuple up to and including the closing bracket. A trailing comma is allowed.
+fn read_raw_items(input: &str, close: char) -> ParserResult<Vec<RawValue>> {
+    let mut items = vec![];
+    let mut input = input;
+
+    loop {
+        let (remaining, _) = blank(input)?;
+        if let (remaining, Some(_)) = opt(char(close))(remaining)? {
+            return Ok((remaining, items));
+        }
+
+        let (remaining, item) = read_raw_value(remaining)?;
+        items.push(item);
+
+        let (remaining, _) = blank(remaining)?;
+        if let (remaining, Some(_)) = opt(char(close))(remaining)? {
+            return Ok((remaining, items));
+        }
+
+        let (remaining, _) = match char::<_, VerboseError<&str>>(',')(remaining) {
+            Ok(result) => result,
+            Err(_) => {
+                return Err(verbose_error(
+                    remaining,
+                    "expected `,` or the end of the list",
+                ))
+            }
+        };
+        input = remaining;
+    }
+}
+
+fn read_raw_fields(input: &str) -> ParserResult<Vec<(&str, RawValue)>> {
+    let (mut input, _) = char('{')(input)?;
+    let mut fields = vec![];
+
+    loop {
+        let (remaining, _) = blank(input)?;
+        if let (remaining, Some(_)) = opt(char('}'))(remaining)? {
+            return Ok((remaining, fields));
+        }
+
+        let (remaining, name) = match read_variable_name(remaining) {
+            Ok(result) => result,
+            Err(_) => return Err(verbose_error(remaining, "expected the name of a field")),
+        };
+        let (remaining, _) = blank(remaining)?;
+        let (remaining, _) = match char::<_, VerboseError<&str>>(':')(remaining) {
+            Ok(result) => result,
+            Err(_) => {
+                return Err(verbose_error(
+                    remaining,
+                    "expected `:` after the field name",
+                ))
+            }
+        };
+        let (remaining, value) = read_raw_value(remaining)?;
+        fields.push((name, value));
+
+        let (remaining, _) = blank(remaining)?;
+        if let (remaining, Some(_)) = opt(char('}'))(remaining)? {
+            return Ok((remaining, fields));
+        }
+
+        let (remaining, _) = match char::<_, VerboseError<&str>>(',')(remaining) {
+            Ok(result) => result,
+            Err(_) => return Err(verbose_error(remaining, "expected `,` or `}` after field")),
+        };
+        input = remaining;
+    }
+}
+
+fn read_raw_value(input: &str) -> ParserResult<RawValue> {
+    let (input, _) = blank(input)?;
+    let position = input;
+
+    let (input, kind) = if let Some(items) = input.strip_prefix('[') {
+        let (input, items) = read_raw_items(items, ']')?;
+        (input, RawKind::List(items))
+    } else if let Some(items) = input.strip_prefix('(') {
+        let (input, items) = read_raw_items(items, ')')?;
+        (input, RawKind::Tuple(items))
+    } else if input.starts_with('{') {
+        let (input, fields) = read_raw_fields(input)?;
+        (input, RawKind::Struct(None, fields))
+    } else if input.starts_with('"') {
+        match read_string_constant(input)? {
+            (input, OpConstant::String(string)) => (input, RawKind::String(string)),
+            _ => unreachable!("string constants are always strings"),
+        }
+    } else if let Ok((input, float)) = parse_float(input) {
+        (input, RawKind::Float(float))
+    } else if let Ok((input, integer)) = parse_integer(input) {
+        (input, RawKind::Integer(integer))
+    } else if let Ok((input, _)) = keyword("true")(input) {
+        (input, RawKind::Boolean(true))
+    } else if let Ok((input, _)) = keyword("false")(input) {
+        (input, RawKind::Boolean(false))
+    } else if let Ok((input, name)) = take_while1::<_, _, VerboseError<&str>>(is_name)(input) {
+        let (after_blank, _) = blank(input)?;
+        if after_blank.starts_with('{') {
+            let (input, fields) = read_raw_fields(after_blank)?;
+            (input, RawKind::Struct(Some(name), fields))
+        } else {
+            (input, RawKind::Name(name))
+        }
+    } else {
+        return Err(verbose_error(input, "expected a value"));
+    };
+
+    Ok((input, RawValue { position, kind }))
+}
+
+fn read_raw_document(input: &str) -> ParserResult<RawValue> {
+    let (input, value) = read_raw_value(input)?;
+    let (input, _) = blank(input)?;
+
+    if input.is_empty() {
+        Ok((input, value))
+    } else {
+        Err(verbose_error(input, "unexpected input after the value"))
+    }
+}
+
+// A type error found while checking the document against its struct. Unlike parse errors these can name the fields
+// and types involved, so the message is built when the error is found.
+struct ValueError<'a> {
+    position: &'a str,
+    message: String,
+}
+
+type CheckResult<'a> = Result<NLValue<'a>, ValueError<'a>>;
+
+fn value_error<'a>(position: &'a str, message: String) -> ValueError<'a> {
+    ValueError { position, message }
+}
+
+// Formats a value error with the line it's on and a caret under where it starts, like nom's `convert_error` does.
+fn describe_value_error(input: &str, error: &ValueError) -> String {
+    let offset = error.position.as_ptr() as usize - input.as_ptr() as usize;
+    let line_start = input[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = input[offset..]
+        .find('\n')
+        .map_or(input.len(), |index| offset + index);
+    let line_number = input[..offset].matches('\n').count() + 1;
+    let column = input[line_start..offset].chars().count();
+
+    format!(
+        "at line {}, {}:\n{}\n{}^\n",
+        line_number,
+        error.message,
+        &input[line_start..line_end],
+        " ".repeat(column)
+    )
+}
+
+fn check_integer<'a>(
+    value: &RawValue<'a>,
+    integer: &ParsedInteger,
+    nl_type: &NLType,
+) -> CheckResult<'a> {
+    let bits = u32::from(nl_type.num_bits());
+    let (minimum, maximum) = if nl_type.is_signed() {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+
+    let digits = integer.text.replace('_', "");
+    match i128::from_str_radix(&digits, integer.radix) {
+        Ok(number) if number >= minimum && number <= maximum => {
+            if nl_type.is_signed() {
+                Ok(NLValue::Signed(number as i64))
+            } else {
+                Ok(NLValue::Unsigned(number as u64))
+            }
+        }
+        Ok(_) => Err(value_error(
+            value.position,
+            format!("{} doesn't fit in {}", integer.text, describe_type(nl_type)),
+        )),
+        Err(_) => Err(value_error(
+            value.position,
+            format!("{} is not a valid number", integer.text),
+        )),
+    }
+}
+
+fn check_struct<'a>(
+    value: &RawValue<'a>,
+    schema: &'a NLFile<'a>,
+    struct_name: &str,
+    written_name: Option<&'a str>,
+    written_fields: &[(&'a str, RawValue<'a>)],
+) -> CheckResult<'a> {
+    let nl_struct = match schema
+        .get_structs()
+        .iter()
+        .find(|nl_struct| nl_struct.get_name() == struct_name)
+    {
+        Some(nl_struct) => nl_struct,
+        None => {
+            return Err(value_error(
+                value.position,
+                format!(
+                    "there is no struct named `{}`{}",
+                    struct_name,
+                    did_you_mean(
+                        struct_name,
+                        schema
+                            .get_structs()
+                            .iter()
+                            .map(|nl_struct| nl_struct.get_name())
+                    )
+                ),
+            ))
+        }
+    };
+
+    if let Some(written_name) = written_name {
+        if written_name != struct_name {
+            return Err(value_error(
+                value.position,
+                format!("expected a `{}`, found a `{}`", struct_name, written_name),
+            ));
+        }
+    }
+
+    for (index, (name, field_value)) in written_fields.iter().enumerate() {
+        if written_fields[..index]
+            .iter()
+            .any(|(earlier, _)| earlier == name)
+        {
+            return Err(value_error(
+                field_value.position,
+                format!("field `{}` was given a value more than once", name),
+            ));
+        }
+
+        if !nl_struct
+            .get_variables()
+            .iter()
+            .any(|variable| variable.get_name() == *name)
+        {
+            return Err(value_error(
+                name,
+                format!(
+                    "`{}` has no field named `{}`{}",
+                    struct_name,
+                    name,
+                    did_you_mean(
+                        name,
+                        nl_struct
+                            .get_variables()
+                            .iter()
+                            .map(|variable| variable.get_name())
+                    )
+                ),
+            ));
+        }
+    }
+
+    let mut fields = Vec::with_capacity(nl_struct.get_variables().len());
+    for variable in nl_struct.get_variables() {
+        let written = written_fields
+            .iter()
+            .find(|(name, _)| *name == variable.get_name());
+
+        match written {
+            Some((name, field_value)) => fields.push((
+                *name,
+                check_value(field_value, schema, variable.get_type())?,
+            )),
+            None => {
+                return Err(value_error(
+                    value.position,
+                    format!(
+                        "missing a value for field `{}` of `{}`",
+                        variable.get_name(),
+                        struct_name
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(NLValue::Struct(NLStructValue {
+        name: nl_struct.get_name(),
+        fields,
+    }))
+}
+
+fn describe_type(nl_type: &NLType) -> String {
+    match nl_type {
+        NLType::Boolean => "bool".to_string(),
+        NLType::I8 => "i8".to_string(),
+        NLType::I16 => "i16".to_string(),
+        NLType::I32 => "i32".to_string(),
+        NLType::I64 => "i64".to_string(),
+        NLType::U8 => "u8".to_string(),
+        NLType::U16 => "u16".to_string(),
+        NLType::U32 => "u32".to_string(),
+        NLType::U64 => "u64".to_string(),
+        NLType::F32 => "f32".to_string(),
+        NLType::F64 => "f64".to_string(),
+        NLType::Char => "char".to_string(),
+        NLType::OwnedString | NLType::BorrowedString => "a string".to_string(),
+        NLType::List(_) => "a list".to_string(),
+        NLType::Tuple(items) => format!("a tuple of {}", items.len()),
+        NLType::OwnedStruct(name) => format!("a `{}`", name),
+        NLType::Enum(name) => format!("a variant of `{}`", name),
+        NLType::Endian(_, nl_type) => describe_type(nl_type),
+        _ => format!("{:?}", nl_type),
+    }
+}
+
+fn check_value<'a>(
+    value: &RawValue<'a>,
+    schema: &'a NLFile<'a>,
+    nl_type: &NLType,
+) -> CheckResult<'a> {
+    match (nl_type, &value.kind) {
+        (NLType::Endian(_, nl_type), _) => check_value(value, schema, nl_type),
+        (NLType::Boolean, RawKind::Boolean(boolean)) => Ok(NLValue::Boolean(*boolean)),
+        (NLType::I8, RawKind::Integer(integer))
+        | (NLType::I16, RawKind::Integer(integer))
+        | (NLType::I32, RawKind::Integer(integer))
+        | (NLType::I64, RawKind::Integer(integer))
+        | (NLType::U8, RawKind::Integer(integer))
+        | (NLType::U16, RawKind::Integer(integer))
+        | (NLType::U32, RawKind::Integer(integer))
+        | (NLType::U64, RawKind::Integer(integer)) => check_integer(value, integer, nl_type),
+
+        // Whole numbers are fine where a float is expected, config files are often written that way.
+        (NLType::F32, RawKind::Float(text))
+        | (NLType::F64, RawKind::Float(text))
+        | (NLType::F32, RawKind::Integer(ParsedInteger { text, radix: 10 }))
+        | (NLType::F64, RawKind::Integer(ParsedInteger { text, radix: 10 })) => {
+            match f64::from_str(&text.replace('_', "")) {
+                Ok(number) => Ok(NLValue::Float(number)),
+                Err(_) => Err(value_error(
+                    value.position,
+                    format!("{} is not a valid number", text),
+                )),
+            }
+        }
+        (NLType::OwnedString, RawKind::String(string))
+        | (NLType::BorrowedString, RawKind::String(string)) => Ok(NLValue::String(string.clone())),
+        (NLType::List(item_type), RawKind::List(items)) => {
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                values.push(check_value(item, schema, item_type)?);
+            }
+
+            Ok(NLValue::List(values))
+        }
+        (NLType::Tuple(item_types), RawKind::Tuple(items)) => {
+            if item_types.len() != items.len() {
+                return Err(value_error(
+                    value.position,
+                    format!(
+                        "expected {}, found a tuple of {}",
+                        describe_type(nl_type),
+                        items.len()
+                    ),
+                ));
+            }
+
+            let mut values = Vec::with_capacity(items.len());
+            for (item, item_type) in items.iter().zip(item_types) {
+                values.push(check_value(item, schema, item_type)?);
+            }
+
+            Ok(NLValue::Tuple(values))
+        }
+        (NLType::OwnedStruct(name), RawKind::Struct(written_name, fields)) => {
+            check_struct(value, schema, name, *written_name, fields)
+        }
+        // Enums are named like structs, so until there's a type checker a named type can be either.
+        (NLType::Enum(name), RawKind::Name(variant))
+        | (NLType::OwnedStruct(name), RawKind::Name(variant)) => {
+            let nl_enum = schema
+                .get_enums()
+                .iter()
+                .find(|nl_enum| nl_enum.get_name() == *name);
+            match nl_enum {
+                Some(nl_enum)
+                    if nl_enum
+                        .get_variants()
+                        .iter()
+                        .any(|known| known.get_name() == *variant) =>
+                {
+                    Ok(NLValue::Enum(variant))
+                }
+                Some(nl_enum) => Err(value_error(
+                    value.position,
+                    format!(
+                        "`{}` has no variant named `{}`{}",
+                        name,
+                        variant,
+                        did_you_mean(
+                            variant,
+                            nl_enum.get_variants().iter().map(|known| known.get_name())
+                        )
+                    ),
+                )),
+                None => Err(value_error(
+                    value.position,
+                    format!("expected a `{}`, found `{}`", name, variant),
+                )),
+            }
+        }
+        (NLType::Boolean, _)
+        | (NLType::I8, _)
+        | (NLType::I16, _)
+        | (NLType::I32, _)
+        | (NLType::I64, _)
+        | (NLType::U8, _)
+        | (NLType::U16, _)
+        | (NLType::U32, _)
+        | (NLType::U64, _)
+        | (NLType::F32, _)
+        | (NLType::F64, _)
+        | (NLType::OwnedString, _)
+        | (NLType::BorrowedString, _)
+        | (NLType::List(_), _)
+        | (NLType::Tuple(_), _)
+        | (NLType::OwnedStruct(_), _)
+        | (NLType::Enum(_), _) => Err(value_error(
+            value.position,
+            format!("expected {}", describe_type(nl_type)),
+        )),
+        _ => Err(value_error(
+            value.position,
+            format!(
+                "{} can't be written in a value document",
+                describe_type(nl_type)
+            ),
+        )),
+    }
+}
+
+/// Parses a document holding a single value of the struct named `root`, which must be declared in `schema`. Every
+/// field must be given a value of the type the struct declares for it. Naming the struct before its `{` is optional.
+pub fn parse_value_document<'a>(
+    input: &'a str,
+    file_name: &str,
+    schema: &'a NLFile<'a>,
+    root: &str,
+) -> Result<NLValue<'a>, ParseError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+    let raw = match read_raw_document(input) {
+        Ok((_, raw)) => raw,
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            let message = convert_error(input, e);
+            return Err(ParseError {
+                message: format!("{}: {}", file_name, message),
+            });
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            return Err(ParseError {
+                message: format!("{}: Unexpected end of file.", file_name),
+            })
+        }
+    };
+
+    match check_value(&raw, schema, &NLType::OwnedStruct(root)) {
+        Ok(value) => Ok(value),
+        Err(error) => Err(ParseError {
+            message: format!("{}: {}", file_name, describe_value_error(input, &error)),
+        }),
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Parse(ParseError),
+    WrongKind(&'static str), // What the Rust type wanted to find.
+    OutOfRange,              // The number fits the NL type but not the Rust one.
+    MissingField(String),
+}
+
+impl std::error::Error for DecodeError {}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            DecodeError::Parse(error) => write!(f, "{}", error),
+            DecodeError::WrongKind(expected) => write!(f, "expected {}", expected),
+            DecodeError::OutOfRange => write!(f, "number doesn't fit the type it's decoded into"),
+            DecodeError::MissingField(name) => write!(f, "there is no field named `{}`", name),
+        }
+    }
+}
+
+/// Rust types that can be built from a value in a value document. Structs implement this by pulling each field out
+/// with `NLStructValue::decode_field`.
+pub trait FromNLValue: Sized {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError>;
+}
+
+impl<'a> NLStructValue<'a> {
+    pub fn decode_field<T: FromNLValue>(&self, name: &str) -> Result<T, DecodeError> {
+        match self.get_field(name) {
+            Some(value) => T::from_nl_value(value),
+            None => Err(DecodeError::MissingField(name.to_string())),
+        }
+    }
+}
+
+impl FromNLValue for bool {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::Boolean(boolean) => Ok(*boolean),
+            _ => Err(DecodeError::WrongKind("a boolean")),
+        }
+    }
+}
+
+macro_rules! impl_from_nl_value_for_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl FromNLValue for $integer {
+                fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+                    let converted = match value {
+                        NLValue::Signed(number) => <$integer>::try_from(*number).ok(),
+                        NLValue::Unsigned(number) => <$integer>::try_from(*number).ok(),
+                        _ => return Err(DecodeError::WrongKind("an integer")),
+                    };
+
+                    converted.ok_or(DecodeError::OutOfRange)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_nl_value_for_integer!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+impl FromNLValue for f32 {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        f64::from_nl_value(value).map(|number| number as f32)
+    }
+}
+
+impl FromNLValue for f64 {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::Float(number) => Ok(*number),
+            _ => Err(DecodeError::WrongKind("a number")),
+        }
+    }
+}
+
+impl FromNLValue for String {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::String(string) => Ok(string.clone()),
+            NLValue::Enum(variant) => Ok(variant.to_string()),
+            _ => Err(DecodeError::WrongKind("a string")),
+        }
+    }
+}
+
+impl<T: FromNLValue> FromNLValue for Vec<T> {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::List(items) => items.iter().map(T::from_nl_value).collect(),
+            _ => Err(DecodeError::WrongKind("a list")),
+        }
+    }
+}
+
+impl<A: FromNLValue, B: FromNLValue> FromNLValue for (A, B) {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::Tuple(items) if items.len() == 2 => {
+                Ok((A::from_nl_value(&items[0])?, B::from_nl_value(&items[1])?))
+            }
+            _ => Err(DecodeError::WrongKind("a tuple of 2")),
+        }
+    }
+}
+
+impl<A: FromNLValue, B: FromNLValue, C: FromNLValue> FromNLValue for (A, B, C) {
+    fn from_nl_value(value: &NLValue) -> Result<Self, DecodeError> {
+        match value {
+            NLValue::Tuple(items) if items.len() == 3 => Ok((
+                A::from_nl_value(&items[0])?,
+                B::from_nl_value(&items[1])?,
+                C::from_nl_value(&items[2])?,
+            )),
+            _ => Err(DecodeError::WrongKind("a tuple of 3")),
+        }
+    }
+}
+
+/// Parses a value document against the struct named `root` in `schema` and builds a `T` out of it.
+pub fn decode<'a, T: FromNLValue>(
+    document: &'a str,
+    file_name: &str,
+    schema: &'a NLFile<'a>,
+    root: &str,
+) -> Result<T, DecodeError> {
+    match parse_value_document(document, file_name, schema, root) {
+        Ok(value) => T::from_nl_value(&value),
+        Err(error) => Err(DecodeError::Parse(error)),
+    }
+}
+
+fn write_string(output: &mut String, string: &str) {
+    output.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+fn write_value(output: &mut String, value: &NLValue, indent: usize) {
+    match value {
+        NLValue::Boolean(boolean) => output.push_str(if *boolean { "true" } else { "false" }),
+        NLValue::Signed(number) => output.push_str(&number.to_string()),
+        NLValue::Unsigned(number) => output.push_str(&number.to_string()),
+        // Debug formatting always includes a `.` or an exponent, so the number is read back as a float.
+        NLValue::Float(number) => output.push_str(&format!("{:?}", number)),
+        NLValue::String(string) => write_string(output, string),
+        NLValue::Enum(variant) => output.push_str(variant),
+        NLValue::List(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_value(output, item, indent);
+            }
+            output.push(']');
+        }
+        NLValue::Tuple(items) => {
+            output.push('(');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_value(output, item, indent);
+            }
+            if items.len() == 1 {
+                output.push(','); // Otherwise it would just be a value in brackets.
+            }
+            output.push(')');
+        }
+        NLValue::Struct(nl_struct) => {
+            output.push_str(nl_struct.get_name());
+            output.push_str(" {\n");
+            for (name, field) in nl_struct.get_fields() {
+                output.push_str(&"    ".repeat(indent + 1));
+                output.push_str(name);
+                output.push_str(": ");
+                write_value(output, field, indent + 1);
+                output.push_str(",\n");
+            }
+            output.push_str(&"    ".repeat(indent));
+            output.push('}');
+        }
+    }
+}
+
+/// Writes a value out in the syntax `parse_value_document` reads. Structs are written one field per line, with
+/// their names, so the document can be read on its own. Floats that are infinite or not a number can't be read back.
+pub fn write_value_document(value: &NLValue) -> String {
+    let mut output = String::new();
+    write_value(&mut output, value, 0);
+    output.push('\n');
+
+    output
+}
@@ -0,0 +1,303 @@
+// Checks whether a new version of a set of declarations can still read data written with an old version. This matters
+// when NL files are used to describe messages or config files that outlive the program that wrote them.
+
+use crate::api::type_text;
+use crate::parsing::{NLFile, NLStructVariable, NLType};
+use std::fmt::Formatter;
+
+// All tests are kept in their own module.
+#[cfg(test)]
+mod tests;
+
+#[derive(PartialEq, Debug)]
+pub enum BreakingChange<'a> {
+    RemovedStruct(&'a str),
+    RemovedField(&'a str, &'a str), // Names of the struct and the field.
+    ChangedFieldType {
+        structure: &'a str,
+        field: &'a str,
+        old_type: NLType<'a>,
+        new_type: NLType<'a>,
+    },
+    ReorderedField {
+        structure: &'a str,
+        field: &'a str,
+        old_index: usize,
+        new_index: usize,
+    },
+    InsertedField(&'a str, &'a str), // Names of the struct and the field, which comes before some of the old ones.
+    RemovedEnum(&'a str),
+    RemovedVariant(&'a str, &'a str), // Names of the enum and the variant.
+    ReorderedVariant {
+        enumeration: &'a str,
+        variant: &'a str,
+        old_index: usize,
+        new_index: usize,
+    },
+    ChangedVariantPayload {
+        enumeration: &'a str,
+        variant: &'a str,
+        old_payload: Vec<NLType<'a>>,
+        new_payload: Vec<NLType<'a>>,
+    },
+}
+
+// What a variant holds, like "`(f32, f32)`".
+fn payload_text(payload: &[NLType]) -> String {
+    if payload.is_empty() {
+        return "nothing".to_string();
+    }
+
+    let types: Vec<String> = payload.iter().map(type_text).collect();
+    format!("`({})`", types.join(", "))
+}
+
+impl<'a> std::fmt::Display for BreakingChange<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            BreakingChange::RemovedStruct(name) => write!(f, "struct `{}` was removed", name),
+            BreakingChange::RemovedField(structure, field) => {
+                write!(f, "field `{}` was removed from `{}`", field, structure)
+            }
+            BreakingChange::ChangedFieldType {
+                structure,
+                field,
+                old_type,
+                new_type,
+            } => write!(
+                f,
+                "field `{}` of `{}` changed type from `{}` to `{}`",
+                field,
+                structure,
+                type_text(old_type),
+                type_text(new_type)
+            ),
+            BreakingChange::ReorderedField {
+                structure,
+                field,
+                old_index,
+                new_index,
+            } => write!(
+                f,
+                "field `{}` of `{}` moved from position {} to {}",
+                field, structure, old_index, new_index
+            ),
+            BreakingChange::InsertedField(structure, field) => write!(
+                f,
+                "field `{}` was inserted into `{}` before fields that were already there",
+                field, structure
+            ),
+            BreakingChange::RemovedEnum(name) => write!(f, "enum `{}` was removed", name),
+            BreakingChange::RemovedVariant(enumeration, variant) => {
+                write!(
+                    f,
+                    "variant `{}` was removed from `{}`",
+                    variant, enumeration
+                )
+            }
+            BreakingChange::ReorderedVariant {
+                enumeration,
+                variant,
+                old_index,
+                new_index,
+            } => write!(
+                f,
+                "variant `{}` of `{}` moved from position {} to {}",
+                variant, enumeration, old_index, new_index
+            ),
+            BreakingChange::ChangedVariantPayload {
+                enumeration,
+                variant,
+                old_payload,
+                new_payload,
+            } => write!(
+                f,
+                "variant `{}` of `{}` changed what it holds from {} to {}",
+                variant,
+                enumeration,
+                payload_text(old_payload),
+                payload_text(new_payload)
+            ),
+        }
+    }
+}
+
+// The names that have to move for `old` to end up in the order of `new`, when both have the same names. The rest keep
+// their order relative to each other, and are the most of them that do.
+fn moved<'n>(old: &[&'n str], new: &[&'n str]) -> Vec<&'n str> {
+    // kept[i][j] is how many of the first i names in `old` can keep their order among the first j in `new`.
+    let mut kept = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in 1..=old.len() {
+        for j in 1..=new.len() {
+            kept[i][j] = if old[i - 1] == new[j - 1] {
+                kept[i - 1][j - 1] + 1
+            } else {
+                kept[i - 1][j].max(kept[i][j - 1])
+            };
+        }
+    }
+
+    let mut moved = vec![];
+    let (mut i, mut j) = (old.len(), new.len());
+    while i > 0 && j > 0 {
+        if old[i - 1] == new[j - 1] {
+            i -= 1;
+            j -= 1;
+        } else if kept[i - 1][j] >= kept[i][j - 1] {
+            moved.push(old[i - 1]);
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    moved.extend(&old[..i]);
+
+    moved
+}
+
+// The names of the fields that are in `others` too, in order.
+fn kept_names<'f>(fields: &'f [NLStructVariable], others: &[NLStructVariable]) -> Vec<&'f str> {
+    fields
+        .iter()
+        .map(|field| field.get_name())
+        .filter(|name| others.iter().any(|other| other.get_name() == *name))
+        .collect()
+}
+
+/// Lists everything about `new` that would stop it from reading data written with `old`. Adding structs, fields, enums
+/// or variants at the end is fine. Removing or changing them, or moving fields or variants around, is not.
+pub fn find_breaking_changes<'a>(
+    old: &'a NLFile<'a>,
+    new: &'a NLFile<'a>,
+) -> Vec<BreakingChange<'a>> {
+    let mut changes = vec![];
+
+    for old_struct in old.get_structs() {
+        let new_struct = new
+            .get_structs()
+            .iter()
+            .find(|new_struct| new_struct.get_name() == old_struct.get_name());
+
+        let new_struct = match new_struct {
+            Some(new_struct) => new_struct,
+            None => {
+                changes.push(BreakingChange::RemovedStruct(old_struct.get_name()));
+                continue;
+            }
+        };
+
+        // Fields are written in the order they're declared, so they have to stay where they were too. Only fields that
+        // changed places with each other have moved. Removing or inserting one shifts those after it, which is reported
+        // on its own.
+        let new_fields = new_struct.get_variables();
+        let moved = moved(
+            &kept_names(old_struct.get_variables(), new_fields),
+            &kept_names(new_fields, old_struct.get_variables()),
+        );
+        let mut last_kept = None;
+        for (old_index, old_field) in old_struct.get_variables().iter().enumerate() {
+            let new_index = new_fields
+                .iter()
+                .position(|new_field| new_field.get_name() == old_field.get_name());
+
+            let new_index = match new_index {
+                Some(new_index) => new_index,
+                None => {
+                    changes.push(BreakingChange::RemovedField(
+                        old_struct.get_name(),
+                        old_field.get_name(),
+                    ));
+                    continue;
+                }
+            };
+            last_kept = last_kept.max(Some(new_index));
+
+            let new_field = &new_fields[new_index];
+            if new_field.get_type() != old_field.get_type() {
+                changes.push(BreakingChange::ChangedFieldType {
+                    structure: old_struct.get_name(),
+                    field: old_field.get_name(),
+                    old_type: old_field.get_type().clone(),
+                    new_type: new_field.get_type().clone(),
+                });
+            }
+            if moved.contains(&old_field.get_name()) {
+                changes.push(BreakingChange::ReorderedField {
+                    structure: old_struct.get_name(),
+                    field: old_field.get_name(),
+                    old_index,
+                    new_index,
+                });
+            }
+        }
+
+        if let Some(last_kept) = last_kept {
+            for new_field in &new_fields[..last_kept] {
+                let is_new = !old_struct
+                    .get_variables()
+                    .iter()
+                    .any(|old_field| old_field.get_name() == new_field.get_name());
+                if is_new {
+                    changes.push(BreakingChange::InsertedField(
+                        old_struct.get_name(),
+                        new_field.get_name(),
+                    ));
+                }
+            }
+        }
+    }
+
+    for old_enum in old.get_enums() {
+        let new_enum = new
+            .get_enums()
+            .iter()
+            .find(|new_enum| new_enum.get_name() == old_enum.get_name());
+
+        let new_enum = match new_enum {
+            Some(new_enum) => new_enum,
+            None => {
+                changes.push(BreakingChange::RemovedEnum(old_enum.get_name()));
+                continue;
+            }
+        };
+
+        // Variants are stored by their position, so they have to stay where they were.
+        for (old_index, old_variant) in old_enum.get_variants().iter().enumerate() {
+            let new_index = new_enum
+                .get_variants()
+                .iter()
+                .position(|new_variant| new_variant.get_name() == old_variant.get_name());
+
+            let new_index = match new_index {
+                Some(new_index) => new_index,
+                None => {
+                    changes.push(BreakingChange::RemovedVariant(
+                        old_enum.get_name(),
+                        old_variant.get_name(),
+                    ));
+                    continue;
+                }
+            };
+
+            let new_variant = &new_enum.get_variants()[new_index];
+            if new_variant.get_payload() != old_variant.get_payload() {
+                changes.push(BreakingChange::ChangedVariantPayload {
+                    enumeration: old_enum.get_name(),
+                    variant: old_variant.get_name(),
+                    old_payload: old_variant.get_payload().into_iter().cloned().collect(),
+                    new_payload: new_variant.get_payload().into_iter().cloned().collect(),
+                });
+            }
+            if new_index != old_index {
+                changes.push(BreakingChange::ReorderedVariant {
+                    enumeration: old_enum.get_name(),
+                    variant: old_variant.get_name(),
+                    old_index,
+                    new_index,
+                });
+            }
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,174 @@
+use super::*;
+use crate::parsing::parse_string;
+
+#[test]
+fn compatible_additions() {
+    let old = parse_string("struct A { a: i32 }\nenum E { One, Two }", "old.nl").unwrap();
+    let new = parse_string(
+        "struct A { a: i32, b: bool }\nstruct B {}\nenum E { One, Two, Three }",
+        "new.nl",
+    )
+    .unwrap();
+
+    assert_eq!(find_breaking_changes(&old, &new), vec![]);
+}
+
+#[test]
+fn struct_changes() {
+    let old = parse_string("struct A { a: i32, b: bool, c: u8 }\nstruct B {}", "old.nl").unwrap();
+    let new = parse_string("struct A { c: u8, a: i64 }", "new.nl").unwrap();
+
+    let changes = find_breaking_changes(&old, &new);
+    assert_eq!(
+        changes,
+        vec![
+            BreakingChange::ChangedFieldType {
+                structure: "A",
+                field: "a",
+                old_type: NLType::I32,
+                new_type: NLType::I64,
+            },
+            BreakingChange::RemovedField("A", "b"),
+            BreakingChange::ReorderedField {
+                structure: "A",
+                field: "c",
+                old_index: 2,
+                new_index: 0,
+            },
+            BreakingChange::RemovedStruct("B"),
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "field `a` of `A` changed type from `i32` to `i64`"
+    );
+}
+
+#[test]
+fn field_order_changes() {
+    let old = parse_string(
+        "struct A { a: i32, b: bool }
+struct B { a: i32, b: bool }",
+        "old.nl",
+    )
+    .unwrap();
+    let new = parse_string(
+        "struct A { a: i32, c: u8, b: bool }
+struct B { b: bool, a: i32 }",
+        "new.nl",
+    )
+    .unwrap();
+
+    // Only one of the fields of `B` has to move for them to swap places.
+    let changes = find_breaking_changes(&old, &new);
+    assert_eq!(
+        changes,
+        vec![
+            BreakingChange::InsertedField("A", "c"),
+            BreakingChange::ReorderedField {
+                structure: "B",
+                field: "b",
+                old_index: 1,
+                new_index: 0,
+            },
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "field `c` was inserted into `A` before fields that were already there"
+    );
+}
+
+// Fields after one that was removed or inserted end up somewhere else, but they haven't changed places with each other.
+#[test]
+fn shifted_fields() {
+    let old = parse_string("struct A { a: i32, b: bool, c: u8, d: u8 }", "old.nl").unwrap();
+    let removed = parse_string("struct A { a: i32, c: u8, d: u8 }", "new.nl").unwrap();
+    let inserted = parse_string(
+        "struct A { a: i32, x: u8, b: bool, c: u8, d: u8 }",
+        "new.nl",
+    )
+    .unwrap();
+    let moved = parse_string(
+        "struct A { a: i32, c: u8, d: u8, x: u8, b: bool }",
+        "new.nl",
+    )
+    .unwrap();
+
+    assert_eq!(
+        find_breaking_changes(&old, &removed),
+        vec![BreakingChange::RemovedField("A", "b")]
+    );
+    assert_eq!(
+        find_breaking_changes(&old, &inserted),
+        vec![BreakingChange::InsertedField("A", "x")]
+    );
+    assert_eq!(
+        find_breaking_changes(&old, &moved),
+        vec![
+            BreakingChange::ReorderedField {
+                structure: "A",
+                field: "b",
+                old_index: 1,
+                new_index: 4,
+            },
+            BreakingChange::InsertedField("A", "x"),
+        ]
+    );
+}
+
+#[test]
+fn enum_changes() {
+    let old = parse_string("enum E { One, Two, Three }\nenum F {}", "old.nl").unwrap();
+    let new = parse_string("enum E { Two, One }", "new.nl").unwrap();
+
+    let changes = find_breaking_changes(&old, &new);
+    assert_eq!(
+        changes,
+        vec![
+            BreakingChange::ReorderedVariant {
+                enumeration: "E",
+                variant: "One",
+                old_index: 0,
+                new_index: 1,
+            },
+            BreakingChange::ReorderedVariant {
+                enumeration: "E",
+                variant: "Two",
+                old_index: 1,
+                new_index: 0,
+            },
+            BreakingChange::RemovedVariant("E", "Three"),
+            BreakingChange::RemovedEnum("F"),
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "variant `One` of `E` moved from position 0 to 1"
+    );
+}
+
+#[test]
+fn variant_payload_changes() {
+    let old = parse_string(
+        "enum Shape { Circle(f64), Square(side: f64), Empty }",
+        "old.nl",
+    )
+    .unwrap();
+    let new = parse_string("enum Shape { Circle(i32), Square(f64), Empty }", "new.nl").unwrap();
+
+    let changes = find_breaking_changes(&old, &new);
+    assert_eq!(
+        changes,
+        vec![BreakingChange::ChangedVariantPayload {
+            enumeration: "Shape",
+            variant: "Circle",
+            old_payload: vec![NLType::F64],
+            new_payload: vec![NLType::I32],
+        }]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "variant `Circle` of `Shape` changed what it holds from `(f64)` to `(i32)`"
+    );
+}
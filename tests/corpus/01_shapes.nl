@@ -0,0 +1,38 @@
+// Shapes: structs, a trait, and implementations of it.
+
+trait Shape {
+    met area(&self) -> f64;
+    met name(&self) -> &str;
+}
+
+struct Circle {
+    radius: f64,
+}
+
+impl Shape for Circle {
+    met area(&self) -> f64 {
+        3.14159 * self.radius * self.radius
+    }
+    met name(&self) -> &str {
+        "circle"
+    }
+}
+
+struct Rectangle {
+    width: f64,
+    height: f64,
+}
+
+impl Shape for Rectangle {
+    met area(&self) -> f64 {
+        self.width * self.height
+    }
+    met name(&self) -> &str {
+        "rectangle"
+    }
+}
+
+fn square(side: f64) -> Rectangle {
+    let rectangle: Rectangle = make_rectangle(side, side)
+    rectangle
+}
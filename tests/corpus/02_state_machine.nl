@@ -0,0 +1,29 @@
+// A traffic light that steps through its states.
+
+enum Light {
+    Red,
+    Amber,
+    Green,
+}
+
+struct Crossing {
+    light: Light,
+    ticks: u32,
+}
+
+// How many ticks the light stays in a state.
+fn duration(light: Light) -> u32 {
+    match light {
+        Light::Red => 30u32,
+        Light::Amber => 3u32,
+        Light::Green => 25u32,
+    }
+}
+
+fn run(steps: u32) -> u32 {
+    let count = 0u32
+    while count < steps {
+        count = count + 1u32
+    }
+    count
+}
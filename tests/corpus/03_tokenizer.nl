@@ -0,0 +1,53 @@
+// Pieces of a tokenizer: fixed size records, loops and branching.
+
+#[repr(c)]
+struct Token {
+    kind: u8,
+    start: u32,
+    length: u32,
+}
+
+struct Tokenizer {
+    position: u32,
+    line: u32,
+    column: u32,
+}
+
+fn is_digit(c: u8) -> bool {
+    if c < 48u8 {
+        false
+    } else {
+        c <= 57u8
+    }
+}
+
+fn is_space(c: u8) -> bool {
+    if c == 32u8 {
+        true
+    } else {
+        c == 10u8
+    }
+}
+
+fn classify(c: u8) -> u8 {
+    if is_digit(c) {
+        1u8
+    } else {
+        if is_space(c) {
+            2u8
+        } else {
+            0u8
+        }
+    }
+}
+
+fn skip(count: u32) -> u32 {
+    let skipped = 0u32
+    loop {
+        if skipped >= count {
+            break
+        }
+        skipped = skipped + 1u32
+    }
+    skipped
+}